@@ -0,0 +1,367 @@
+//! Native support for AVI (RIFF) files, as written by older digital cameras.
+//!
+//! Only the header list (`hdrl`), the `INFO` list and the `IDIT` chunk are read; the `movi` list
+//! with the media data is skipped.
+
+use crate::{
+    CodecInfo, CreationDate, CreationDateOptions, DateSource, FrameRate, MediaDateTime, MetaData,
+    Result, TrackDetails, TrackInfo, TrackKind,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::io;
+use std::time::Duration;
+
+/// Upper bound for the size of a header chunk or list that is loaded into memory.
+const MAX_CHUNK_SIZE: u32 = 1024 * 1024;
+
+/// Extracts metadata from an AVI (RIFF) file.
+///
+/// Dimensions, frame rate and duration are read from the AVI and stream headers, the creation
+/// date from the `IDIT` chunk or the `ICRD` entry of the `INFO` list.
+pub fn extract_avi_metadata<R: io::Read + io::Seek>(io: R) -> Result<MetaData> {
+    extract_avi_metadata_with_options(io, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_avi_metadata_with_options<R: io::Read + io::Seek>(
+    mut io: R,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let avi = read_avi(&mut io)?;
+    let tracks: Vec<TrackInfo> = avi.streams.iter().map(convert_stream).collect();
+    let video = tracks.iter().find_map(|track| match &track.details {
+        TrackDetails::Video(details) => Some(details),
+        _ => None,
+    });
+    let (width, height) = match video {
+        Some(video) if video.width > 0 && video.height > 0 => (video.width, video.height),
+        _ => (avi.main_header.width as u64, avi.main_header.height as u64),
+    };
+    let creation_date = options
+        .select(&get_creation_dates(&avi))
+        .map(|date| date.date);
+
+    Ok(MetaData {
+        width,
+        height,
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration: get_duration(&avi),
+        tracks,
+        ..Default::default()
+    })
+}
+
+pub(crate) fn extract_avi_creation_dates<R: io::Read + io::Seek>(
+    mut io: R,
+) -> Result<Vec<CreationDate>> {
+    let avi = read_avi(&mut io)?;
+    Ok(get_creation_dates(&avi))
+}
+
+/// Headers of an AVI file.
+#[derive(Debug, Default)]
+struct Avi {
+    main_header: MainHeader,
+    streams: Vec<Stream>,
+    /// Frame count of the OpenDML extended header, which covers all `RIFF` chunks of files larger
+    /// than 1 GB, unlike the main header.
+    total_frames: Option<u32>,
+    /// Value of the `IDIT` chunk.
+    date_time_original: Option<String>,
+    /// `ICRD` entry of the `INFO` list.
+    creation_date: Option<String>,
+}
+
+/// Main AVI header (`avih`).
+#[derive(Debug, Default)]
+struct MainHeader {
+    micro_sec_per_frame: u32,
+    total_frames: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Stream header (`strh`) with its format (`strf`) and name (`strn`).
+#[derive(Debug, Default)]
+struct Stream {
+    /// `vids`, `auds`, `txts` or `mids`.
+    stream_type: [u8; 4],
+    handler: [u8; 4],
+    /// Time scale, the stream has `rate / scale` samples per second.
+    scale: u32,
+    rate: u32,
+    /// Length in units of `scale / rate` seconds.
+    length: u32,
+    /// `BITMAPINFOHEADER` for video and `WAVEFORMATEX` for audio streams.
+    format: Vec<u8>,
+    name: Option<String>,
+}
+
+fn read_avi<R: io::Read + io::Seek>(io: &mut R) -> io::Result<Avi> {
+    let start = io.stream_position()?;
+    let mut header = [0u8; 12];
+    io.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"AVI " {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an AVI file",
+        ));
+    }
+    let stream_len = io.seek(io::SeekFrom::End(0))?;
+    // recordings that were interrupted leave the size at 0 or beyond the end of the file
+    let end = match read_u32(&header, 4).unwrap_or_default() {
+        0 => stream_len,
+        size => stream_len.min(start + 8 + size as u64),
+    };
+
+    let mut avi = Avi::default();
+    let mut position = start + 12;
+    while position + 8 <= end {
+        io.seek(io::SeekFrom::Start(position))?;
+        let mut chunk = [0u8; 12];
+        // files from cameras that ran out of power are often truncated
+        match io.read_exact(&mut chunk[..8]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let size = read_u32(&chunk, 4).unwrap_or_default();
+        let wanted = match &chunk[0..4] {
+            b"LIST" => {
+                io.read_exact(&mut chunk[8..12])?;
+                matches!(&chunk[8..12], b"hdrl" | b"INFO")
+            }
+            b"IDIT" => true,
+            _ => false,
+        };
+        if wanted && size <= MAX_CHUNK_SIZE {
+            io.seek(io::SeekFrom::Start(position + 8))?;
+            let mut data = vec![0; size as usize];
+            io.read_exact(&mut data)?;
+            let list = data.get(4..).unwrap_or_default();
+            match (&chunk[0..4], &chunk[8..12]) {
+                (b"IDIT", _) => avi.date_time_original = Some(read_text(&data)),
+                (_, b"hdrl") => parse_hdrl(list, &mut avi),
+                _ => parse_info(list, &mut avi),
+            }
+        }
+        position += 8 + size as u64 + (size & 1) as u64;
+    }
+    Ok(avi)
+}
+
+fn parse_hdrl(hdrl: &[u8], avi: &mut Avi) {
+    for (id, data) in chunks(hdrl) {
+        match (&id, data.get(0..4)) {
+            (b"avih", _) => {
+                avi.main_header = MainHeader {
+                    micro_sec_per_frame: read_u32(data, 0).unwrap_or_default(),
+                    total_frames: read_u32(data, 16).unwrap_or_default(),
+                    width: read_u32(data, 32).unwrap_or_default(),
+                    height: read_u32(data, 36).unwrap_or_default(),
+                }
+            }
+            (b"LIST", Some(b"strl")) => avi.streams.push(parse_strl(&data[4..])),
+            (b"LIST", Some(b"odml")) => {
+                avi.total_frames = chunks(&data[4..])
+                    .find(|(id, _)| id == b"dmlh")
+                    .and_then(|(_, dmlh)| read_u32(dmlh, 0));
+            }
+            // some cameras store the date in the header list
+            (b"IDIT", _) => avi.date_time_original = Some(read_text(data)),
+            _ => {}
+        }
+    }
+}
+
+fn parse_strl(strl: &[u8]) -> Stream {
+    let mut stream = Stream::default();
+    for (id, data) in chunks(strl) {
+        match &id {
+            b"strh" => {
+                stream.stream_type = data
+                    .get(0..4)
+                    .and_then(|v| v.try_into().ok())
+                    .unwrap_or_default();
+                stream.handler = data
+                    .get(4..8)
+                    .and_then(|v| v.try_into().ok())
+                    .unwrap_or_default();
+                stream.scale = read_u32(data, 20).unwrap_or_default();
+                stream.rate = read_u32(data, 24).unwrap_or_default();
+                stream.length = read_u32(data, 32).unwrap_or_default();
+            }
+            b"strf" => stream.format = data.to_vec(),
+            b"strn" => stream.name = Some(read_text(data)).filter(|name| !name.is_empty()),
+            _ => {}
+        }
+    }
+    stream
+}
+
+fn parse_info(info: &[u8], avi: &mut Avi) {
+    if let Some((_, icrd)) = chunks(info).find(|(id, _)| id == b"ICRD") {
+        avi.creation_date = Some(read_text(icrd));
+    }
+}
+
+fn convert_stream(stream: &Stream) -> TrackInfo {
+    let format = stream.format.as_slice();
+    let mut info = match &stream.stream_type {
+        b"vids" => {
+            // the compression of the bitmap header is more reliable than the stream handler
+            let fourcc = format
+                .get(16..20)
+                .and_then(|v| <[u8; 4]>::try_from(v).ok())
+                .filter(|fourcc| fourcc.iter().any(|byte| *byte != 0))
+                .unwrap_or(stream.handler);
+            TrackInfo::new(TrackKind::Video, CodecInfo::from_avi_fourcc(&fourcc))
+        }
+        b"auds" => {
+            let format_tag = read_u16(format, 0).unwrap_or_default();
+            TrackInfo::new(
+                TrackKind::Audio,
+                CodecInfo::from_wave_format_tag(format_tag),
+            )
+        }
+        b"txts" => TrackInfo::new(
+            TrackKind::Subtitle,
+            CodecInfo::from_avi_fourcc(&stream.handler),
+        ),
+        _ => TrackInfo::new(
+            TrackKind::Other,
+            CodecInfo::from_avi_fourcc(&stream.handler),
+        ),
+    };
+    info.name = stream.name.clone();
+    match &mut info.details {
+        TrackDetails::Video(details) => {
+            details.width = read_u32(format, 4).unwrap_or_default() as u64;
+            // negative heights mark top-down bitmaps
+            details.height = (read_u32(format, 8).unwrap_or_default() as i32).unsigned_abs() as u64;
+            details.frame_rate =
+                FrameRate::from_ratio(stream.rate as u64, stream.scale as u64, false);
+        }
+        TrackDetails::Audio(details) => {
+            details.channels = read_u16(format, 2)
+                .map(u32::from)
+                .filter(|channels| *channels > 0);
+            details.sample_rate = read_u32(format, 4).filter(|rate| *rate > 0);
+        }
+        _ => {}
+    }
+    info
+}
+
+/// Computes the duration from the video stream header, falling back to the frame count and frame
+/// duration of the main header.
+fn get_duration(avi: &Avi) -> Option<Duration> {
+    let video = avi
+        .streams
+        .iter()
+        .find(|stream| &stream.stream_type == b"vids" && stream.rate > 0 && stream.length > 0);
+    let nanos = match video {
+        Some(video) => {
+            video.length as u128 * video.scale as u128 * 1_000_000_000 / video.rate as u128
+        }
+        None => {
+            let frames = avi.total_frames.unwrap_or(avi.main_header.total_frames);
+            frames as u128 * avi.main_header.micro_sec_per_frame as u128 * 1_000
+        }
+    };
+    Some(Duration::from_nanos(u64::try_from(nanos).ok()?)).filter(|duration| !duration.is_zero())
+}
+
+/// Reads `IDIT`, then `ICRD`. Both store local time without an offset.
+fn get_creation_dates(avi: &Avi) -> Vec<CreationDate> {
+    [
+        (&avi.date_time_original, DateSource::AviDateTimeOriginal),
+        (&avi.creation_date, DateSource::AviInfoCreationDate),
+    ]
+    .into_iter()
+    .filter_map(|(value, source)| {
+        let date = parse_avi_date(value.as_deref()?)?;
+        Some(CreationDate::new(date, source))
+    })
+    .collect()
+}
+
+/// Parses the date formats found in `IDIT` and `ICRD`: `ctime` style like
+/// `MON JAN 01 12:00:00 2007`, Exif style like `2007:01:01 12:00:00`, ISO 8601 and plain dates.
+fn parse_avi_date(value: &str) -> Option<MediaDateTime> {
+    // `ctime` pads the day of month with a space
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    [
+        "%a %b %d %H:%M:%S %Y",
+        "%Y:%m:%d %H:%M:%S",
+        "%Y/%m/%d %H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+    .map(MediaDateTime::floating)
+    .or_else(|| MediaDateTime::parse_iso8601(&value))
+    .or_else(|| {
+        let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok()?;
+        Some(MediaDateTime::floating(date.and_hms_opt(0, 0, 0)?))
+    })
+}
+
+/// Iterates over the chunks of a RIFF list, yielding each id with its data.
+fn chunks(mut data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        let id: [u8; 4] = data.get(0..4)?.try_into().ok()?;
+        let size = read_u32(data, 4)? as usize;
+        let chunk = data.get(8..8 + size)?;
+        // chunks are padded to an even size
+        data = data.get(8 + size + (size & 1)..).unwrap_or_default();
+        Some((id, chunk))
+    })
+}
+
+/// Decodes a zero-terminated string.
+fn read_text(data: &[u8]) -> String {
+    let text = data.split(|byte| *byte == 0).next().unwrap_or_default();
+    String::from_utf8_lossy(text).trim().to_owned()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_avi_date;
+    use crate::MediaDateTime;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_avi_date() {
+        let expected = MediaDateTime::floating(
+            NaiveDate::from_ymd_opt(2007, 1, 8)
+                .and_then(|date| date.and_hms_opt(12, 30, 15))
+                .unwrap(),
+        );
+        for value in [
+            "MON JAN  8 12:30:15 2007\n",
+            "Mon Jan 08 12:30:15 2007",
+            "2007:01:08 12:30:15",
+            "2007/01/08 12:30:15",
+            "2007-01-08T12:30:15",
+        ] {
+            assert_eq!(parse_avi_date(value), Some(expected), "{value}");
+        }
+        assert_eq!(
+            parse_avi_date("2007-01-08").map(|date| date.datetime.date()),
+            NaiveDate::from_ymd_opt(2007, 1, 8)
+        );
+        assert_eq!(parse_avi_date("unknown"), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! Bit-level reading of video codec headers.
+
+/// Reads bits most significant first, as well as the Exp-Golomb codes of H.264 and HEVC, which
+/// are the same as the `uvlc` codes of AV1.
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub(crate) fn flag(&mut self) -> Option<bool> {
+        let byte = self.data.get(self.position / 8)?;
+        let bit = byte >> (7 - self.position % 8) & 1;
+        self.position += 1;
+        Some(bit == 1)
+    }
+
+    /// Reads up to 32 bits.
+    pub(crate) fn bits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = value << 1 | self.flag()? as u32;
+        }
+        Some(value)
+    }
+
+    pub(crate) fn skip(&mut self, count: usize) -> Option<()> {
+        if self.position + count > self.data.len() * 8 {
+            return None;
+        }
+        self.position += count;
+        Some(())
+    }
+
+    /// Reads an unsigned Exp-Golomb code.
+    pub(crate) fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+        while !self.flag()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+        Some(((1u64 << leading_zeros) - 1 + self.bits(leading_zeros)? as u64) as u32)
+    }
+
+    /// Reads a signed Exp-Golomb code.
+    pub(crate) fn se(&mut self) -> Option<i32> {
+        let value = self.ue()? as i64;
+        let value = if value % 2 == 1 {
+            (value + 1) / 2
+        } else {
+            -value / 2
+        };
+        Some(value as i32)
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+/// Chapter of a video or audio file.
+///
+/// Chapters are read from the Matroska `Chapters` element, and from QuickTime chapter tracks or
+/// the Nero `chpl` atom of MP4 files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub start: Duration,
+    /// End of the chapter. When the file does not store it, this is the start of the next chapter
+    /// or the end of the file.
+    pub end: Option<Duration>,
+    /// Display strings, usually one per language.
+    pub titles: Vec<ChapterTitle>,
+    /// Index of the Matroska edition the chapter belongs to. MP4 files have a single edition.
+    pub edition: usize,
+    /// Whether players should hide the chapter from the user.
+    pub hidden: bool,
+    /// Nested chapters, e.g. the scenes of a Matroska chapter.
+    pub children: Vec<Chapter>,
+}
+
+impl Chapter {
+    /// Returns the first title.
+    pub fn title(&self) -> Option<&str> {
+        self.titles.first().map(|title| title.title.as_str())
+    }
+}
+
+/// Display string of a [`Chapter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterTitle {
+    pub title: String,
+    /// Language as an ISO 639-2 code or a BCP 47 tag, `None` if unknown.
+    pub language: Option<String>,
+}
+
+/// Sets missing end times to the start of the following chapter, or to `end` for the last one.
+pub(crate) fn fill_end_times(chapters: &mut [Chapter], end: Option<Duration>) {
+    let starts: Vec<_> = chapters
+        .iter()
+        .map(|chapter| chapter.start)
+        .skip(1)
+        .collect();
+    for (index, chapter) in chapters.iter_mut().enumerate() {
+        if chapter.end.is_none() {
+            chapter.end = starts.get(index).copied().or(end);
+        }
+    }
+}
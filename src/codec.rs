@@ -0,0 +1,336 @@
+/// Codec of a track, normalized across containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Codec {
+    H264,
+    Hevc,
+    Av1,
+    Vp8,
+    Vp9,
+    Mpeg4Visual,
+    Mpeg2Video,
+    Mpeg1Video,
+    ProRes,
+    Mjpeg,
+    Aac,
+    Mp3,
+    /// MPEG-1/2 Audio Layer I or II.
+    MpegAudio,
+    Opus,
+    Vorbis,
+    Flac,
+    Alac,
+    Ac3,
+    Eac3,
+    Dts,
+    Pcm,
+    /// SubRip or other plain UTF-8 text subtitles.
+    SubRip,
+    Ass,
+    WebVtt,
+    /// 3GPP/QuickTime timed text (`tx3g`, `text`).
+    TimedText,
+    Ttml,
+    Pgs,
+    VobSub,
+    DvbSubtitle,
+    Cea608,
+    Unknown,
+}
+
+/// Codec of a track together with the identifier used by the container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecInfo {
+    pub codec: Codec,
+    /// Original codec identifier, e.g. the MP4 sample entry FourCC `hvc1`, the Matroska `CodecID`
+    /// `V_MPEGH/ISO/HEVC`, the AVI FourCC `XVID` or format tag `0x0055`, the MPEG-TS stream type
+    /// `0x1B`, or the mediainfo `Format` `HEVC`.
+    pub id: String,
+}
+
+impl CodecInfo {
+    /// Creates codec information from an MP4 sample entry type.
+    ///
+    /// `object_type` is the object type indication from the `esds` box, which identifies the
+    /// actual codec of `mp4a` and `mp4v` sample entries.
+    pub(crate) fn from_fourcc(fourcc: &[u8; 4], object_type: Option<u8>) -> Self {
+        let codec = match fourcc {
+            b"avc1" | b"avc2" | b"avc3" | b"avc4" | b"dva1" | b"dvav" => Codec::H264,
+            b"hvc1" | b"hev1" | b"dvh1" | b"dvhe" => Codec::Hevc,
+            b"av01" => Codec::Av1,
+            b"vp08" => Codec::Vp8,
+            b"vp09" => Codec::Vp9,
+            b"apch" | b"apcn" | b"apcs" | b"apco" | b"ap4h" | b"ap4x" => Codec::ProRes,
+            b"jpeg" | b"mjpa" | b"mjpb" => Codec::Mjpeg,
+            b"mp4v" | b"mp4a" => match object_type {
+                Some(object_type) => Self::codec_from_object_type(object_type),
+                None if fourcc == b"mp4v" => Codec::Mpeg4Visual,
+                None => Codec::Aac,
+            },
+            b".mp3" => Codec::Mp3,
+            b"Opus" => Codec::Opus,
+            b"fLaC" => Codec::Flac,
+            b"alac" => Codec::Alac,
+            b"ac-3" => Codec::Ac3,
+            b"ec-3" => Codec::Eac3,
+            b"dtsc" | b"dtsh" | b"dtsl" | b"dtse" => Codec::Dts,
+            b"lpcm" | b"sowt" | b"twos" | b"ipcm" | b"fpcm" | b"in24" | b"in32" | b"fl32"
+            | b"fl64" | b"raw " => Codec::Pcm,
+            b"tx3g" | b"text" => Codec::TimedText,
+            b"wvtt" => Codec::WebVtt,
+            b"stpp" => Codec::Ttml,
+            b"c608" => Codec::Cea608,
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: String::from_utf8_lossy(fourcc).into_owned(),
+        }
+    }
+
+    /// Maps an MPEG-4 systems object type indication to a codec.
+    fn codec_from_object_type(object_type: u8) -> Codec {
+        match object_type {
+            0x20 => Codec::Mpeg4Visual,
+            0x21 => Codec::H264,
+            0x23 => Codec::Hevc,
+            0x40 | 0x66..=0x68 => Codec::Aac,
+            0x60..=0x65 => Codec::Mpeg2Video,
+            0x69 | 0x6B => Codec::Mp3,
+            0x6A => Codec::Mpeg1Video,
+            0x6C => Codec::Mjpeg,
+            0xA5 => Codec::Ac3,
+            0xA6 => Codec::Eac3,
+            0xA9 | 0xAC => Codec::Dts,
+            0xAD => Codec::Opus,
+            0xB1 => Codec::Vp9,
+            0xDD => Codec::Vorbis,
+            _ => Codec::Unknown,
+        }
+    }
+
+    /// Creates codec information from a Matroska `CodecID`.
+    pub(crate) fn from_matroska_id(id: &str) -> Self {
+        let codec = match id {
+            "V_MPEG4/ISO/AVC" => Codec::H264,
+            "V_MPEGH/ISO/HEVC" => Codec::Hevc,
+            "V_AV1" => Codec::Av1,
+            "V_VP8" => Codec::Vp8,
+            "V_VP9" => Codec::Vp9,
+            "V_MPEG2" => Codec::Mpeg2Video,
+            "V_MPEG1" => Codec::Mpeg1Video,
+            "V_PRORES" => Codec::ProRes,
+            "V_MJPEG" => Codec::Mjpeg,
+            "A_MPEG/L3" => Codec::Mp3,
+            "A_MPEG/L2" | "A_MPEG/L1" => Codec::MpegAudio,
+            "A_OPUS" => Codec::Opus,
+            "A_VORBIS" => Codec::Vorbis,
+            "A_FLAC" => Codec::Flac,
+            "A_ALAC" => Codec::Alac,
+            "A_AC3" => Codec::Ac3,
+            "A_EAC3" => Codec::Eac3,
+            "S_TEXT/UTF8" | "S_TEXT/ASCII" => Codec::SubRip,
+            "S_TEXT/ASS" | "S_TEXT/SSA" | "S_ASS" | "S_SSA" => Codec::Ass,
+            "S_TEXT/WEBVTT" => Codec::WebVtt,
+            "S_HDMV/PGS" => Codec::Pgs,
+            "S_VOBSUB" => Codec::VobSub,
+            "S_DVBSUB" => Codec::DvbSubtitle,
+            id if id.starts_with("V_MPEG4/ISO/") => Codec::Mpeg4Visual,
+            id if id.starts_with("A_AAC") => Codec::Aac,
+            id if id.starts_with("A_DTS") => Codec::Dts,
+            id if id.starts_with("A_PCM/") => Codec::Pcm,
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: id.to_owned(),
+        }
+    }
+
+    /// Creates codec information from an AVI video FourCC, i.e. the `biCompression` of the bitmap
+    /// header or the stream handler. FourCCs are matched case-insensitively.
+    pub(crate) fn from_avi_fourcc(fourcc: &[u8; 4]) -> Self {
+        let mut upper = *fourcc;
+        upper.make_ascii_uppercase();
+        let codec = match &upper {
+            b"H264" | b"X264" | b"AVC1" | b"DAVC" | b"VSSH" => Codec::H264,
+            b"HEVC" | b"H265" | b"X265" | b"HVC1" | b"HEV1" => Codec::Hevc,
+            b"AV01" => Codec::Av1,
+            b"VP80" => Codec::Vp8,
+            b"VP90" => Codec::Vp9,
+            b"XVID" | b"DIVX" | b"DX50" | b"FMP4" | b"MP4V" | b"3IV2" | b"M4S2" => {
+                Codec::Mpeg4Visual
+            }
+            b"MPG2" | b"MPEG" => Codec::Mpeg2Video,
+            b"MPG1" => Codec::Mpeg1Video,
+            b"MJPG" | b"AVRN" | b"JPEG" | b"DMB1" => Codec::Mjpeg,
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: String::from_utf8_lossy(fourcc)
+                .trim_end_matches([' ', '\0'])
+                .to_owned(),
+        }
+    }
+
+    /// Creates codec information from the `wFormatTag` of a `WAVEFORMATEX` structure, as used by
+    /// AVI audio streams.
+    pub(crate) fn from_wave_format_tag(format_tag: u16) -> Self {
+        let codec = match format_tag {
+            0x0001 | 0x0003 => Codec::Pcm,
+            0x0050 => Codec::MpegAudio,
+            0x0055 => Codec::Mp3,
+            0x00FF | 0x1610 | 0x706D => Codec::Aac,
+            0x2000 => Codec::Ac3,
+            0x2001 => Codec::Dts,
+            0xF1AC => Codec::Flac,
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: format!("0x{format_tag:04X}"),
+        }
+    }
+
+    /// Creates codec information from an MPEG-TS stream type. Private data streams (`0x06`) are
+    /// identified by the tags of their descriptors.
+    pub(crate) fn from_ts_stream_type(stream_type: u8, descriptor_tags: &[u8]) -> Self {
+        let codec = match stream_type {
+            0x01 => Codec::Mpeg1Video,
+            0x02 => Codec::Mpeg2Video,
+            0x03 | 0x04 => Codec::MpegAudio,
+            0x0F | 0x11 => Codec::Aac,
+            0x10 => Codec::Mpeg4Visual,
+            0x1B => Codec::H264,
+            0x24 => Codec::Hevc,
+            0x80 => Codec::Pcm,
+            0x81 => Codec::Ac3,
+            0x82 | 0x85 | 0x86 => Codec::Dts,
+            0x84 | 0x87 => Codec::Eac3,
+            0x90 => Codec::Pgs,
+            0x06 => descriptor_tags
+                .iter()
+                .find_map(|tag| match tag {
+                    0x6A => Some(Codec::Ac3),
+                    0x7A => Some(Codec::Eac3),
+                    0x7B => Some(Codec::Dts),
+                    0x59 => Some(Codec::DvbSubtitle),
+                    _ => None,
+                })
+                .unwrap_or(Codec::Unknown),
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: format!("0x{stream_type:02X}"),
+        }
+    }
+
+    /// Creates codec information from a mediainfo `Format` and optional `Format_Profile`.
+    #[cfg(feature = "mediainfo")]
+    pub(crate) fn from_mediainfo_format(format: &str, profile: Option<&str>) -> Self {
+        let codec = match format {
+            "AVC" => Codec::H264,
+            "HEVC" => Codec::Hevc,
+            "AV1" => Codec::Av1,
+            "VP8" => Codec::Vp8,
+            "VP9" => Codec::Vp9,
+            "MPEG-4 Visual" => Codec::Mpeg4Visual,
+            "MPEG Video" => match profile {
+                Some(profile) if profile.contains("Version 1") => Codec::Mpeg1Video,
+                _ => Codec::Mpeg2Video,
+            },
+            "ProRes" => Codec::ProRes,
+            "JPEG" => Codec::Mjpeg,
+            "AAC" => Codec::Aac,
+            "MPEG Audio" => match profile {
+                Some(profile) if profile.contains("Layer 3") => Codec::Mp3,
+                _ => Codec::MpegAudio,
+            },
+            "Opus" => Codec::Opus,
+            "Vorbis" => Codec::Vorbis,
+            "FLAC" => Codec::Flac,
+            "ALAC" => Codec::Alac,
+            "AC-3" => Codec::Ac3,
+            "E-AC-3" => Codec::Eac3,
+            "DTS" => Codec::Dts,
+            "PCM" => Codec::Pcm,
+            "UTF-8" | "ASCII" => Codec::SubRip,
+            "ASS" | "SSA" => Codec::Ass,
+            "WebVTT" => Codec::WebVtt,
+            "Timed Text" => Codec::TimedText,
+            "TTML" => Codec::Ttml,
+            "PGS" => Codec::Pgs,
+            "VobSub" => Codec::VobSub,
+            "DVB Subtitle" => Codec::DvbSubtitle,
+            "EIA-608" => Codec::Cea608,
+            _ => Codec::Unknown,
+        };
+        Self {
+            codec,
+            id: format.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Codec, CodecInfo};
+
+    #[test]
+    fn test_fourcc() {
+        assert_eq!(CodecInfo::from_fourcc(b"hvc1", None).codec, Codec::Hevc);
+        assert_eq!(
+            CodecInfo::from_fourcc(b"mp4a", Some(0x40)).codec,
+            Codec::Aac
+        );
+        assert_eq!(
+            CodecInfo::from_fourcc(b"mp4a", Some(0x6B)).codec,
+            Codec::Mp3
+        );
+        let unknown = CodecInfo::from_fourcc(b"abcd", None);
+        assert_eq!(unknown.codec, Codec::Unknown);
+        assert_eq!(unknown.id, "abcd");
+    }
+
+    #[test]
+    fn test_matroska_id() {
+        assert_eq!(
+            CodecInfo::from_matroska_id("V_MPEGH/ISO/HEVC").codec,
+            Codec::Hevc
+        );
+        assert_eq!(
+            CodecInfo::from_matroska_id("A_AAC/MPEG4/LC").codec,
+            Codec::Aac
+        );
+        assert_eq!(CodecInfo::from_matroska_id("A_OPUS").codec, Codec::Opus);
+    }
+
+    #[test]
+    fn test_avi() {
+        assert_eq!(
+            CodecInfo::from_avi_fourcc(b"xvid").codec,
+            Codec::Mpeg4Visual
+        );
+        assert_eq!(CodecInfo::from_avi_fourcc(b"MJPG").id, "MJPG");
+        let mp3 = CodecInfo::from_wave_format_tag(0x55);
+        assert_eq!(mp3.codec, Codec::Mp3);
+        assert_eq!(mp3.id, "0x0055");
+    }
+
+    #[test]
+    fn test_ts_stream_type() {
+        let h264 = CodecInfo::from_ts_stream_type(0x1B, &[]);
+        assert_eq!(h264.codec, Codec::H264);
+        assert_eq!(h264.id, "0x1B");
+        assert_eq!(
+            CodecInfo::from_ts_stream_type(0x06, &[0x0A, 0x6A]).codec,
+            Codec::Ac3
+        );
+        assert_eq!(
+            CodecInfo::from_ts_stream_type(0x06, &[]).codec,
+            Codec::Unknown
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+/// Date and time as recorded in a media file, together with its relation to UTC.
+///
+/// Containers store dates differently: Matroska and the MP4 movie header use UTC, while EXIF and
+/// most camera formats store the local wall-clock time, sometimes accompanied by its UTC offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MediaDateTime {
+    /// The date and time as stored in the file, i.e. the UTC time for [`DateTimeOffset::Utc`] and
+    /// the local wall-clock time otherwise.
+    pub datetime: NaiveDateTime,
+    pub offset: DateTimeOffset,
+}
+
+/// Relation of a [`MediaDateTime`] to UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateTimeOffset {
+    /// The time is in UTC.
+    Utc,
+    /// The time is local time with a known offset from UTC.
+    Fixed(FixedOffset),
+    /// The time is local time in an unknown time zone.
+    Floating,
+}
+
+impl MediaDateTime {
+    pub(crate) fn utc(datetime: NaiveDateTime) -> Self {
+        Self {
+            datetime,
+            offset: DateTimeOffset::Utc,
+        }
+    }
+
+    pub(crate) fn fixed(datetime: NaiveDateTime, offset: FixedOffset) -> Self {
+        Self {
+            datetime,
+            offset: DateTimeOffset::Fixed(offset),
+        }
+    }
+
+    pub(crate) fn floating(datetime: NaiveDateTime) -> Self {
+        Self {
+            datetime,
+            offset: DateTimeOffset::Floating,
+        }
+    }
+
+    /// Returns the time with its offset, or `None` if the time zone is unknown.
+    pub fn to_fixed_offset(&self) -> Option<DateTime<FixedOffset>> {
+        match self.offset {
+            DateTimeOffset::Utc => Some(self.datetime.and_utc().fixed_offset()),
+            DateTimeOffset::Fixed(offset) => self.datetime.and_local_timezone(offset).single(),
+            DateTimeOffset::Floating => None,
+        }
+    }
+
+    /// Returns the time in UTC.
+    ///
+    /// Floating local time is interpreted as UTC, so it may be off by the unknown offset.
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        match self.to_fixed_offset() {
+            Some(datetime) => datetime.to_utc(),
+            None => self.datetime.and_utc(),
+        }
+    }
+
+    /// Returns the time as [`SystemTime`], see [`MediaDateTime::to_utc`].
+    pub fn to_system_time(&self) -> SystemTime {
+        self.to_utc().into()
+    }
+
+    /// Returns why the date is likely wrong, or `None` if it looks plausible.
+    ///
+    /// Devices with a dead clock battery and some muxers write placeholder values instead of the
+    /// actual date. Old scans or manually set dates may be flagged as well, so it is up to the
+    /// caller to decide whether to drop them.
+    pub fn bogus_reason(&self) -> Option<BogusDate> {
+        // the offset is at most a day, so comparing local times is good enough
+        let day = TimeDelta::days(1);
+        let date = |year, month, day| {
+            NaiveDate::from_ymd_opt(year, month, day)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .unwrap()
+        };
+        let datetime = self.datetime;
+        if [date(1904, 1, 1), date(1970, 1, 1), date(2001, 1, 1)]
+            .iter()
+            .any(|epoch| (datetime - *epoch).abs() < day)
+        {
+            Some(BogusDate::Epoch)
+        } else if datetime.date() == date(2000, 1, 1).date() {
+            Some(BogusDate::FactoryDefault)
+        } else if datetime < date(1990, 1, 1) {
+            Some(BogusDate::TooEarly)
+        } else if self.to_utc() > DateTime::<Utc>::from(SystemTime::now()) + day {
+            Some(BogusDate::InFuture)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn from_system_time(time: SystemTime) -> Self {
+        Self::utc(DateTime::<Utc>::from(time).naive_utc())
+    }
+
+    /// Parses an ISO 8601 date and time with an optional offset, e.g. `2021-08-13T20:04:35+0200`,
+    /// `2021-08-13T18:04:35Z` or `2021-08-13 20:04:35`. Fractional seconds are preserved.
+    pub(crate) fn parse_iso8601(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (datetime, rest) = NaiveDateTime::parse_and_remainder(value, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_and_remainder(value, "%Y-%m-%d %H:%M:%S%.f"))
+            .ok()?;
+        match rest.trim() {
+            "" => Some(Self::floating(datetime)),
+            "Z" | "UTC" => Some(Self::utc(datetime)),
+            offset => Some(Self::fixed(datetime, parse_offset(offset)?)),
+        }
+    }
+}
+
+impl Display for MediaDateTime {
+    /// Formats the date as RFC 3339, omitting the offset for floating local time.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let datetime = self.datetime.format("%Y-%m-%dT%H:%M:%S%.f");
+        match self.offset {
+            DateTimeOffset::Utc => write!(f, "{datetime}Z"),
+            DateTimeOffset::Fixed(offset) => write!(f, "{datetime}{offset}"),
+            DateTimeOffset::Floating => write!(f, "{datetime}"),
+        }
+    }
+}
+
+/// Reason why a date is likely not the actual creation date, see [`MediaDateTime::bogus_reason`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BogusDate {
+    /// Within a day of the MP4 (1904), Unix (1970) or Matroska (2001) epoch, i.e. a zero or tiny
+    /// timestamp.
+    Epoch,
+    /// 2000-01-01, the date many cameras reset to when their clock battery runs out.
+    FactoryDefault,
+    /// Before 1990, when consumer digital cameras did not exist yet.
+    TooEarly,
+    /// More than a day in the future.
+    InFuture,
+}
+
+/// Metadata field a creation date was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DateSource {
+    /// EXIF `DateTimeOriginal`, the time the photo was taken.
+    ExifDateTimeOriginal,
+    /// EXIF `DateTimeDigitized`, the time the image was stored digitally, e.g. scanned.
+    ExifDateTimeDigitized,
+    /// EXIF `DateTime`, the time the file was last changed.
+    ExifDateTime,
+    /// QuickTime `com.apple.quicktime.creationdate` metadata key.
+    QuickTimeCreationDate,
+    /// Creation time of the MP4 movie header (`mvhd`).
+    Mp4CreationTime,
+    /// Matroska `DateUTC` segment information.
+    MatroskaDateUtc,
+    /// Global Matroska `DATE_RECORDED` tag.
+    MatroskaDateRecorded,
+    /// AVI `IDIT` chunk, the time the video was recorded by a camera.
+    AviDateTimeOriginal,
+    /// `ICRD` entry of the AVI `INFO` list.
+    AviInfoCreationDate,
+    /// Recording time in the `MDPM` user data of AVCHD camcorder video, with its time zone.
+    AvchdDateTimeOriginal,
+    /// Date of the XMP metadata embedded in an image, from `exif:DateTimeOriginal`,
+    /// `photoshop:DateCreated` or `xmp:CreateDate`, in this order.
+    XmpDateCreated,
+    /// PNG `Creation Time` text entry.
+    PngCreationTime,
+    /// PNG `tIME` chunk, the time of the last modification of the image.
+    PngModificationTime,
+    /// `Recorded_Date` reported by mediainfo.
+    MediainfoRecordedDate,
+    /// `Encoded_Date` reported by mediainfo.
+    MediainfoEncodedDate,
+    /// Date encoded in the file name, e.g. `IMG_20210813_180435.jpg`, see
+    /// [`parse_filename_date`](crate::parse_filename_date). Not used by default.
+    ///
+    /// As a last resort, it is only used if no metadata source yields a date.
+    FileName,
+    /// Birth time of the file in the file system, where supported (e.g. `statx` on Linux). Not
+    /// used by default.
+    ///
+    /// This is when the file was copied to the current location rather than when the media was
+    /// recorded, so it is only used if no other source yields a date.
+    FileSystemCreated,
+    /// Last modification time of the file in the file system. Not used by default.
+    ///
+    /// Like [`DateSource::FileSystemCreated`], it is only used if no other source yields a date.
+    FileSystemModified,
+}
+
+impl DateSource {
+    /// Sources in the order they are used by default. Sources that are not derived from metadata,
+    /// like [`DateSource::FileName`], must be enabled explicitly.
+    pub const DEFAULT_ORDER: [DateSource; 15] = [
+        DateSource::ExifDateTimeOriginal,
+        DateSource::ExifDateTimeDigitized,
+        DateSource::ExifDateTime,
+        DateSource::QuickTimeCreationDate,
+        DateSource::Mp4CreationTime,
+        DateSource::MatroskaDateUtc,
+        DateSource::MatroskaDateRecorded,
+        DateSource::AviDateTimeOriginal,
+        DateSource::AviInfoCreationDate,
+        DateSource::AvchdDateTimeOriginal,
+        DateSource::XmpDateCreated,
+        DateSource::PngCreationTime,
+        DateSource::PngModificationTime,
+        DateSource::MediainfoRecordedDate,
+        DateSource::MediainfoEncodedDate,
+    ];
+
+    /// Returns `true` if the date is read from the media metadata, as opposed to the file name or
+    /// file system.
+    pub fn is_metadata(&self) -> bool {
+        !matches!(
+            self,
+            DateSource::FileName | DateSource::FileSystemCreated | DateSource::FileSystemModified
+        )
+    }
+
+    /// Returns `true` if the date is reported by the `mediainfo` tool.
+    pub fn is_mediainfo(&self) -> bool {
+        matches!(
+            self,
+            DateSource::MediainfoRecordedDate | DateSource::MediainfoEncodedDate
+        )
+    }
+}
+
+/// Creation date of a media file together with the field it was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreationDate {
+    pub date: MediaDateTime,
+    pub source: DateSource,
+}
+
+impl CreationDate {
+    pub(crate) fn new(date: MediaDateTime, source: DateSource) -> Self {
+        Self { date, source }
+    }
+}
+
+/// Options for [`extract_file_creation_date_with_options`](crate::extract_file_creation_date_with_options).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreationDateOptions {
+    /// Sources to read the creation date from, in order of preference. Sources missing from the
+    /// list are ignored. Defaults to [`DateSource::DEFAULT_ORDER`].
+    pub sources: Vec<DateSource>,
+    /// Skip dates that are likely wrong, see [`MediaDateTime::bogus_reason`], and use the next
+    /// source instead. Disabled by default.
+    pub reject_bogus: bool,
+    /// Additional `chrono` formats for [`DateSource::FileName`], tried before the built-in ones.
+    pub filename_patterns: Vec<String>,
+}
+
+impl Default for CreationDateOptions {
+    fn default() -> Self {
+        Self {
+            sources: DateSource::DEFAULT_ORDER.to_vec(),
+            reject_bogus: false,
+            filename_patterns: Vec::new(),
+        }
+    }
+}
+
+impl CreationDateOptions {
+    /// Creates options that use only the given sources, in the given order.
+    pub fn with_sources(sources: impl IntoIterator<Item = DateSource>) -> Self {
+        Self {
+            sources: sources.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Enables or disables [`CreationDateOptions::reject_bogus`].
+    pub fn reject_bogus(mut self, reject_bogus: bool) -> Self {
+        self.reject_bogus = reject_bogus;
+        self
+    }
+
+    /// Enables [`DateSource::FileName`] with additional user `patterns`, which may be empty.
+    pub fn with_filename(mut self, patterns: impl IntoIterator<Item = String>) -> Self {
+        self.add_source(DateSource::FileName);
+        self.filename_patterns.extend(patterns);
+        self
+    }
+
+    /// Enables [`DateSource::FileSystemCreated`] and then [`DateSource::FileSystemModified`].
+    pub fn with_file_system(mut self) -> Self {
+        self.add_source(DateSource::FileSystemCreated);
+        self.add_source(DateSource::FileSystemModified);
+        self
+    }
+
+    fn add_source(&mut self, source: DateSource) {
+        if !self.sources.contains(&source) {
+            self.sources.push(source);
+        }
+    }
+
+    /// Picks the date from the most preferred source among `dates`.
+    pub(crate) fn select(&self, dates: &[CreationDate]) -> Option<CreationDate> {
+        self.sources
+            .iter()
+            .find_map(|source| {
+                dates.iter().find(|date| {
+                    date.source == *source
+                        && !(self.reject_bogus && date.date.bogus_reason().is_some())
+                })
+            })
+            .copied()
+    }
+}
+
+/// Parses a UTC offset like `+02:00`, `+0200` or `-05`.
+pub(crate) fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match value.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BogusDate, CreationDate, CreationDateOptions, DateSource, DateTimeOffset, MediaDateTime,
+    };
+    use chrono::FixedOffset;
+
+    #[test]
+    fn test_parse_iso8601() {
+        let date = MediaDateTime::parse_iso8601("2021-08-13T20:04:35+0200").unwrap();
+        assert_eq!(
+            date.offset,
+            DateTimeOffset::Fixed(FixedOffset::east_opt(7200).unwrap())
+        );
+        assert_eq!(date.to_string(), "2021-08-13T20:04:35+02:00");
+        assert_eq!(
+            date.to_system_time(),
+            crate::parse_date("2021-08-13T18:04:35")
+        );
+
+        let date = MediaDateTime::parse_iso8601("2021-08-13T18:04:35.250Z").unwrap();
+        assert_eq!(date.offset, DateTimeOffset::Utc);
+        assert_eq!(
+            date.to_system_time(),
+            crate::parse_date("2021-08-13T18:04:35.250")
+        );
+
+        let date = MediaDateTime::parse_iso8601("2021-08-13 20:04:35").unwrap();
+        assert_eq!(date.offset, DateTimeOffset::Floating);
+        assert_eq!(date.to_fixed_offset(), None);
+
+        assert_eq!(MediaDateTime::parse_iso8601("2021-08-13"), None);
+        assert_eq!(MediaDateTime::parse_iso8601("2021-08-13T20:04:35+2"), None);
+    }
+
+    #[test]
+    fn test_select_source() {
+        let date =
+            |value, source| CreationDate::new(MediaDateTime::parse_iso8601(value).unwrap(), source);
+        let dates = [
+            date("2021-08-13T20:04:35", DateSource::ExifDateTime),
+            date("2021-08-13T18:04:35", DateSource::ExifDateTimeDigitized),
+        ];
+
+        let options = CreationDateOptions::default();
+        assert_eq!(options.select(&dates), Some(dates[1]));
+
+        let options = CreationDateOptions::with_sources([
+            DateSource::ExifDateTime,
+            DateSource::ExifDateTimeDigitized,
+        ]);
+        assert_eq!(options.select(&dates), Some(dates[0]));
+
+        let options = CreationDateOptions::with_sources([DateSource::ExifDateTimeOriginal]);
+        assert_eq!(options.select(&dates), None);
+    }
+
+    #[test]
+    fn test_bogus_date() {
+        let reason = |value| MediaDateTime::parse_iso8601(value).unwrap().bogus_reason();
+        assert_eq!(reason("1970-01-01T00:00:00Z"), Some(BogusDate::Epoch));
+        assert_eq!(reason("1969-12-31T19:00:00-05:00"), Some(BogusDate::Epoch));
+        assert_eq!(reason("1904-01-01T00:00:42Z"), Some(BogusDate::Epoch));
+        assert_eq!(
+            reason("2000-01-01T08:15:00"),
+            Some(BogusDate::FactoryDefault)
+        );
+        assert_eq!(reason("1985-06-01T12:00:00"), Some(BogusDate::TooEarly));
+        assert_eq!(reason("2090-06-01T12:00:00Z"), Some(BogusDate::InFuture));
+        assert_eq!(reason("2021-08-13T20:04:35+02:00"), None);
+
+        let date =
+            |value, source| CreationDate::new(MediaDateTime::parse_iso8601(value).unwrap(), source);
+        let dates = [
+            date("2000-01-01T00:00:05", DateSource::ExifDateTimeOriginal),
+            date("2021-08-13T20:04:35", DateSource::ExifDateTime),
+        ];
+        let options = CreationDateOptions::default();
+        assert_eq!(options.select(&dates), Some(dates[0]));
+        let options = options.reject_bogus(true);
+        assert_eq!(options.select(&dates), Some(dates[1]));
+    }
+}
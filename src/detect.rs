@@ -0,0 +1,270 @@
+use crate::{ebml, ContainerType, Error, Result};
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of bytes inspected by [`detect_container_type`].
+const SNIFF_LEN: usize = 512;
+
+const HEIF_BRANDS: &[&str] = &[
+    "heic", "heix", "heim", "heis", "hevc", "hevx", "mif1", "mif2", "msf1",
+];
+const AVIF_BRANDS: &[&str] = &["avif", "avis"];
+const TIFF_BASED_EXTENSIONS: &[&str] = &[
+    "tiff", "tif", "dng", "cr2", "nef", "nrw", "arw", "sr2", "orf", "rw2", "pef", "srw", "3fr",
+    "erf", "mos",
+];
+
+/// Result of content-based container detection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedContainer {
+    /// Container type that should be passed to [`extract_metadata`](crate::extract_metadata).
+    pub container_type: ContainerType,
+    /// ISOBMFF major brand (e.g. `isom`, `qt`, `heic`) or EBML `DocType` (e.g. `matroska`,
+    /// `webm`). Trailing spaces are trimmed.
+    pub brand: Option<String>,
+    /// ISOBMFF compatible brands, empty for other formats.
+    pub compatible_brands: Vec<String>,
+}
+
+impl DetectedContainer {
+    fn new(container_type: ContainerType) -> Self {
+        Self {
+            container_type,
+            brand: None,
+            compatible_brands: Vec::new(),
+        }
+    }
+}
+
+/// Detects the container type of a stream by inspecting its first bytes.
+///
+/// The stream is read from its current position, which is restored before returning. Unlike
+/// [`get_container_type`](crate::get_container_type), this function does not depend on the file
+/// name, so it works for files with unusual, upper-case, missing or wrong extensions.
+pub fn detect_container_type<R: io::Read + io::Seek>(reader: &mut R) -> Result<DetectedContainer> {
+    let buf = read_prefix(reader)?;
+    detect_from_bytes(&buf, None)
+}
+
+/// Detects the container type of a file, using its extension as a hint.
+///
+/// Content detection takes priority. The extension is used to pick a more specific Exif variant
+/// (e.g. `cr2` instead of `tiff`) and as a fallback when the content is not recognized.
+pub(crate) fn detect_file_container_type<R, P>(
+    reader: &mut R,
+    file_path: P,
+) -> Result<ContainerType>
+where
+    R: io::Read + io::Seek,
+    P: AsRef<Path>,
+{
+    let extension = file_path
+        .as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+
+    let buf = read_prefix(reader)?;
+    match detect_from_bytes(&buf, extension.as_deref()) {
+        Ok(detected) => Ok(detected.container_type),
+        Err(_) => crate::get_container_type(file_path),
+    }
+}
+
+/// Reads the first bytes of the stream and rewinds it to the original position.
+fn read_prefix<R: io::Read + io::Seek>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let start = reader.stream_position()?;
+    let mut buf = Vec::with_capacity(SNIFF_LEN);
+    let read = reader.by_ref().take(SNIFF_LEN as u64).read_to_end(&mut buf);
+    reader.seek(io::SeekFrom::Start(start))?;
+    read?;
+    Ok(buf)
+}
+
+fn detect_from_bytes(buf: &[u8], extension: Option<&str>) -> Result<DetectedContainer> {
+    if buf.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Ok(exif("jpg"));
+    }
+    if buf.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Ok(DetectedContainer::new(ContainerType::Png));
+    }
+    if buf.starts_with(b"II*\0") || buf.starts_with(b"MM\0*") {
+        return Ok(exif(tiff_extension(extension)));
+    }
+    if buf.starts_with(b"IIRO") || buf.starts_with(b"IIRS") {
+        return Ok(exif("orf"));
+    }
+    if buf.starts_with(b"IIU\0") {
+        return Ok(exif("rw2"));
+    }
+    if buf.starts_with(b"FUJIFILMCCD-RAW") {
+        return Ok(exif("raf"));
+    }
+    if buf.len() >= 12 && buf.starts_with(b"RIFF") {
+        return match &buf[8..12] {
+            b"WEBP" => Ok(exif("webp")),
+            b"AVI " => Ok(DetectedContainer::new(ContainerType::Avi)),
+            b"WAVE" => Err(Error::UnsupportedContainerType("wav".to_string())),
+            _ => Err(Error::UnsupportedContainerType("riff".to_string())),
+        };
+    }
+    if buf.starts_with(&ebml::EBML_HEADER.to_be_bytes()) {
+        let mut detected = DetectedContainer::new(ContainerType::Mkv);
+        detected.brand = ebml::parse_doc_type(buf).map(|(doc_type, _)| doc_type);
+        return Ok(detected);
+    }
+    if let Some(detected) = detect_isobmff(buf) {
+        return Ok(detected);
+    }
+    if is_transport_stream(buf, 188, 0) || is_transport_stream(buf, 192, 4) {
+        return Ok(DetectedContainer::new(ContainerType::MpegTs));
+    }
+    Err(Error::UnsupportedContainerType("unknown".to_string()))
+}
+
+fn exif(extension: &str) -> DetectedContainer {
+    DetectedContainer::new(ContainerType::Exif(extension.to_string()))
+}
+
+fn tiff_extension(extension: Option<&str>) -> &str {
+    match extension {
+        Some(extension) if TIFF_BASED_EXTENSIONS.contains(&extension) => extension,
+        _ => "tiff",
+    }
+}
+
+fn detect_isobmff(buf: &[u8]) -> Option<DetectedContainer> {
+    if buf.len() < 8 {
+        return None;
+    }
+    let box_type = &buf[4..8];
+    if box_type != b"ftyp" {
+        // QuickTime files written before `ftyp` was introduced start directly with atoms
+        return match box_type {
+            b"moov" | b"mdat" | b"wide" | b"free" | b"skip" | b"pnot" => {
+                Some(DetectedContainer::new(ContainerType::Mp4))
+            }
+            _ => None,
+        };
+    }
+
+    let size = u32::from_be_bytes(buf[0..4].try_into().ok()?) as usize;
+    let ftyp = buf.get(8..size.min(buf.len()))?;
+    let brand = fourcc(ftyp.get(0..4)?);
+    let compatible_brands: Vec<String> = ftyp
+        .get(8..)
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .map(fourcc)
+        .filter(|brand| !brand.is_empty())
+        .collect();
+
+    let is_heif_brand = |brand: &str| HEIF_BRANDS.contains(&brand) || AVIF_BRANDS.contains(&brand);
+    let container_type = if AVIF_BRANDS.contains(&brand.as_str()) {
+        ContainerType::Exif("avif".to_string())
+    } else if HEIF_BRANDS.contains(&brand.as_str()) {
+        ContainerType::Exif("heic".to_string())
+    } else if brand == "crx" {
+        ContainerType::Exif("cr3".to_string())
+    } else if compatible_brands.iter().any(|b| b == "mif1" || b == "msf1")
+        && !compatible_brands
+            .iter()
+            .any(|b| !is_heif_brand(b) && b != "miaf")
+    {
+        ContainerType::Exif("heic".to_string())
+    } else {
+        ContainerType::Mp4
+    };
+
+    Some(DetectedContainer {
+        container_type,
+        brand: Some(brand),
+        compatible_brands,
+    })
+}
+
+fn fourcc(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches([' ', '\0'])
+        .to_string()
+}
+
+pub(crate) fn is_transport_stream(buf: &[u8], packet_size: usize, offset: usize) -> bool {
+    (0..3).all(|i| buf.get(offset + i * packet_size) == Some(&0x47))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(buf: &[u8]) -> Result<DetectedContainer> {
+        detect_from_bytes(buf, None)
+    }
+
+    #[test]
+    fn test_ftyp_brands() -> anyhow::Result<()> {
+        let mp4 = detect(b"\0\0\0\x18ftypmp42\0\0\0\0mp42isom")?;
+        assert_eq!(mp4.container_type, ContainerType::Mp4);
+        assert_eq!(mp4.brand.as_deref(), Some("mp42"));
+        assert_eq!(mp4.compatible_brands, vec!["mp42", "isom"]);
+
+        let mov = detect(b"\0\0\0\x14ftypqt  \0\0\0\0qt  ")?;
+        assert_eq!(mov.container_type, ContainerType::Mp4);
+        assert_eq!(mov.brand.as_deref(), Some("qt"));
+
+        let heic = detect(b"\0\0\0\x18ftypheic\0\0\0\0mif1heic")?;
+        assert_eq!(heic.container_type, ContainerType::Exif("heic".to_string()));
+
+        let avif = detect(b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf")?;
+        assert_eq!(avif.container_type, ContainerType::Exif("avif".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_signatures() -> anyhow::Result<()> {
+        assert_eq!(
+            detect(b"\xFF\xD8\xFF\xE1")?.container_type,
+            ContainerType::Exif("jpg".to_string())
+        );
+        assert_eq!(
+            detect(b"\x89PNG\r\n\x1a\n")?.container_type,
+            ContainerType::Png
+        );
+        assert_eq!(
+            detect(b"RIFF\0\0\0\0WEBPVP8 ")?.container_type,
+            ContainerType::Exif("webp".to_string())
+        );
+        assert_eq!(
+            detect_from_bytes(b"II*\0\x08\0\0\0", Some("nef"))?.container_type,
+            ContainerType::Exif("nef".to_string())
+        );
+        assert_eq!(
+            detect_from_bytes(b"MM\0*\0\0\0\x08", Some("jpg"))?.container_type,
+            ContainerType::Exif("tiff".to_string())
+        );
+        assert_eq!(
+            detect(b"RIFF\0\0\0\0AVI LIST")?.container_type,
+            ContainerType::Avi
+        );
+        assert!(detect(b"RIFF\0\0\0\0WAVEfmt ").is_err());
+        assert!(detect(b"plain text").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transport_stream() {
+        let mut ts = vec![0u8; 188 * 3];
+        for packet in ts.chunks_mut(188) {
+            packet[0] = 0x47;
+        }
+        assert_eq!(detect(&ts).unwrap().container_type, ContainerType::MpegTs);
+
+        // AVCHD and Blu-ray prefix every packet with a 4-byte timestamp
+        let mut m2ts = vec![0u8; 192 * 3];
+        for packet in m2ts.chunks_mut(192) {
+            packet[4] = 0x47;
+        }
+        assert_eq!(detect(&m2ts).unwrap().container_type, ContainerType::MpegTs);
+    }
+}
@@ -0,0 +1,30 @@
+/// Information about the device and software that produced the media file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Device {
+    /// Manufacturer of the camera or phone, e.g. `Apple`.
+    pub make: Option<String>,
+    /// Model of the camera or phone, e.g. `iPhone 15 Pro`.
+    pub model: Option<String>,
+    /// Firmware or application that created the file.
+    pub software: Option<String>,
+    /// Library or application that encoded or muxed the file, e.g. `Lavf60.16.100`.
+    pub encoder: Option<String>,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+    /// Serial number of the camera body.
+    pub serial_number: Option<String>,
+    pub lens_serial_number: Option<String>,
+}
+
+impl Device {
+    /// Returns `None` if no field is set, so that empty devices are not reported.
+    pub(crate) fn non_empty(self) -> Option<Self> {
+        (self != Self::default()).then_some(self)
+    }
+}
+
+/// Trims padding that some writers leave around text values and drops empty values.
+pub(crate) fn clean_value(value: &str) -> Option<String> {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!value.is_empty()).then(|| value.to_owned())
+}
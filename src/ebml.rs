@@ -0,0 +1,356 @@
+//! Minimal EBML reader used where the `matroska` crate does not expose the required elements.
+
+use std::io::{self, Read};
+
+pub(crate) const EBML_HEADER: u32 = 0x1A45_DFA3;
+pub(crate) const DOC_TYPE: u32 = 0x4282;
+pub(crate) const DOC_TYPE_VERSION: u32 = 0x4287;
+pub(crate) const SEGMENT: u32 = 0x1853_8067;
+pub(crate) const SEEK_HEAD: u32 = 0x114D_9B74;
+pub(crate) const SEEK: u32 = 0x4DBB;
+pub(crate) const SEEK_ID: u32 = 0x53AB;
+pub(crate) const SEEK_POSITION: u32 = 0x53AC;
+pub(crate) const INFO: u32 = 0x1549_A966;
+pub(crate) const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+pub(crate) const TRACKS: u32 = 0x1654_AE6B;
+pub(crate) const TRACK_ENTRY: u32 = 0xAE;
+pub(crate) const TRACK_NUMBER: u32 = 0xD7;
+pub(crate) const TRACK_TYPE: u32 = 0x83;
+pub(crate) const VIDEO: u32 = 0xE0;
+pub(crate) const ALPHA_MODE: u32 = 0x53C0;
+pub(crate) const PROJECTION: u32 = 0x7670;
+pub(crate) const PROJECTION_POSE_ROLL: u32 = 0x7675;
+pub(crate) const CLUSTER: u32 = 0x1F43_B675;
+pub(crate) const TIMESTAMP: u32 = 0xE7;
+pub(crate) const SIMPLE_BLOCK: u32 = 0xA3;
+pub(crate) const BLOCK_GROUP: u32 = 0xA0;
+pub(crate) const BLOCK: u32 = 0xA1;
+pub(crate) const BLOCK_DURATION: u32 = 0x9B;
+pub(crate) const REFERENCE_BLOCK: u32 = 0xFB;
+pub(crate) const CHAPTERS: u32 = 0x1043_A770;
+pub(crate) const EDITION_ENTRY: u32 = 0x45B9;
+pub(crate) const EDITION_FLAG_HIDDEN: u32 = 0x45BD;
+pub(crate) const CHAPTER_ATOM: u32 = 0xB6;
+pub(crate) const CHAPTER_TIME_START: u32 = 0x91;
+pub(crate) const CHAPTER_TIME_END: u32 = 0x92;
+pub(crate) const CHAPTER_FLAG_HIDDEN: u32 = 0x98;
+pub(crate) const CHAPTER_FLAG_ENABLED: u32 = 0x4598;
+pub(crate) const CHAPTER_DISPLAY: u32 = 0x80;
+pub(crate) const CHAP_STRING: u32 = 0x85;
+pub(crate) const CHAP_LANGUAGE: u32 = 0x437C;
+pub(crate) const CHAP_LANGUAGE_BCP47: u32 = 0x437D;
+pub(crate) const ATTACHMENTS: u32 = 0x1941_A469;
+pub(crate) const ATTACHED_FILE: u32 = 0x61A7;
+pub(crate) const FILE_DESCRIPTION: u32 = 0x467E;
+pub(crate) const FILE_NAME: u32 = 0x466E;
+pub(crate) const FILE_MIME_TYPE: u32 = 0x4660;
+pub(crate) const FILE_DATA: u32 = 0x465C;
+pub(crate) const FILE_UID: u32 = 0x46AE;
+
+/// Upper bound for elements that are loaded into memory.
+const MAX_ELEMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Header of a single EBML element.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ElementHeader {
+    pub id: u32,
+    /// Size of the element payload, `None` if the size is unknown.
+    pub size: Option<u64>,
+    /// Length of the encoded id and size.
+    pub header_len: u64,
+}
+
+/// Reads an element id and size from the stream.
+pub(crate) fn read_element_header<R: io::Read>(r: &mut R) -> io::Result<ElementHeader> {
+    let (id, id_len) = read_vint(r, false)?;
+    let (size, size_len) = read_vint(r, true)?;
+    let unknown = size == (1u64 << (7 * size_len)) - 1;
+    Ok(ElementHeader {
+        id: id as u32,
+        size: if unknown { None } else { Some(size) },
+        header_len: (id_len + size_len) as u64,
+    })
+}
+
+pub(crate) fn read_vint<R: io::Read>(r: &mut R, strip_marker: bool) -> io::Result<(u64, usize)> {
+    let mut first = [0u8; 1];
+    r.read_exact(&mut first)?;
+    let len = first[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid EBML variable size integer",
+        ));
+    }
+    let mut value = if strip_marker {
+        (first[0] as u64) & (0xFF >> len)
+    } else {
+        first[0] as u64
+    };
+    let mut rest = [0u8; 7];
+    r.read_exact(&mut rest[..len - 1])?;
+    for byte in &rest[..len - 1] {
+        value = (value << 8) | *byte as u64;
+    }
+    Ok((value, len))
+}
+
+/// Iterates over the child elements stored in `data`, yielding each id with its payload.
+pub(crate) fn children(data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        let mut cursor = io::Cursor::new(rest);
+        let header = read_element_header(&mut cursor).ok()?;
+        let start = header.header_len as usize;
+        let end = match header.size {
+            Some(size) => start.checked_add(usize::try_from(size).ok()?)?,
+            None => rest.len(),
+        };
+        // truncated elements are returned as far as the data goes
+        let end = end.min(rest.len());
+        let payload = &rest[start..end];
+        rest = &rest[end..];
+        Some((header.id, payload))
+    })
+}
+
+pub(crate) fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+pub(crate) fn read_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Parses the EBML header and returns the `DocType` with its version.
+pub(crate) fn parse_doc_type(data: &[u8]) -> Option<(String, u64)> {
+    let (id, header) = children(data).next()?;
+    if id != EBML_HEADER {
+        return None;
+    }
+    let mut doc_type = None;
+    let mut doc_type_version = 1;
+    for (id, payload) in children(header) {
+        match id {
+            DOC_TYPE => doc_type = Some(read_string(payload)),
+            DOC_TYPE_VERSION => doc_type_version = read_uint(payload),
+            _ => {}
+        }
+    }
+    doc_type.map(|doc_type| (doc_type, doc_type_version))
+}
+
+/// Reads the EBML header at the current position and returns the `DocType` with its version.
+pub(crate) fn read_doc_type<R: io::Read>(io: &mut R) -> io::Result<Option<(String, u64)>> {
+    let mut data = Vec::new();
+    io.take(1024).read_to_end(&mut data)?;
+    Ok(parse_doc_type(&data))
+}
+
+/// Locates a top-level element of the first segment and returns its payload position and size.
+///
+/// The stream must be positioned at the start of the file. The `SeekHead` is used when available,
+/// otherwise the segment is scanned linearly, skipping clusters by their size.
+pub(crate) fn find_segment_child<R: io::Read + io::Seek>(
+    io: &mut R,
+    id: u32,
+) -> io::Result<Option<(u64, u64)>> {
+    let segment = loop {
+        let header = read_element_header(io)?;
+        if header.id == SEGMENT {
+            break header;
+        }
+        let Some(size) = header.size else {
+            return Ok(None);
+        };
+        io.seek(io::SeekFrom::Current(size as i64))?;
+    };
+    let segment_start = io.stream_position()?;
+    // sizes that overflow are treated like an unknown size
+    let segment_end = segment
+        .size
+        .and_then(|size| segment_start.checked_add(size));
+
+    let mut position = segment_start;
+    while segment_end.map_or(true, |end| position < end) {
+        let header = match read_element_header(io) {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let payload_start = position + header.header_len;
+        let Some(size) = header.size else {
+            // only clusters are expected to have an unknown size, nothing can be found after them
+            return Ok((header.id == id).then_some((payload_start, u64::MAX)));
+        };
+        if header.id == id {
+            return Ok(Some((payload_start, size)));
+        }
+        if header.id == SEEK_HEAD && size <= MAX_ELEMENT_SIZE {
+            let mut payload = vec![0; size as usize];
+            io.read_exact(&mut payload)?;
+            // positions that overflow can't be valid, the entry is skipped
+            let target = find_seek_position(&payload, id)
+                .and_then(|target| segment_start.checked_add(target));
+            if let Some(target) = target {
+                io.seek(io::SeekFrom::Start(target))?;
+                let header = read_element_header(io)?;
+                if header.id == id {
+                    let payload_start = target + header.header_len;
+                    return Ok(Some((payload_start, header.size.unwrap_or(u64::MAX))));
+                }
+            }
+        }
+        let Some(next) = payload_start.checked_add(size) else {
+            break;
+        };
+        position = next;
+        io.seek(io::SeekFrom::Start(position))?;
+    }
+    Ok(None)
+}
+
+/// Reads the payload of a top-level element of the first segment into memory.
+pub(crate) fn read_segment_child<R: io::Read + io::Seek>(
+    io: &mut R,
+    id: u32,
+) -> io::Result<Option<Vec<u8>>> {
+    let Some((position, size)) = find_segment_child(io, id)? else {
+        return Ok(None);
+    };
+    if size > MAX_ELEMENT_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "EBML element is too large",
+        ));
+    }
+    io.seek(io::SeekFrom::Start(position))?;
+    let mut payload = vec![0; size as usize];
+    io.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Reads the start of a file that was written live, with a segment or clusters of unknown size,
+/// like the WebM screen recordings of browsers. The `matroska` crate can't skip such elements.
+///
+/// Returns a copy of the file up to the first cluster, with the size of the segment set, or
+/// `None` if all sizes are known. Seek heads are left out, as they can point past the copy.
+pub(crate) fn read_live_head<R: io::Read + io::Seek>(io: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut head = Vec::new();
+    let segment = loop {
+        let position = io.stream_position()?;
+        let header = read_element_header(io)?;
+        if header.id == SEGMENT {
+            break header;
+        }
+        match header.size {
+            Some(size) if size <= MAX_ELEMENT_SIZE => {
+                copy_element(io, position, header.header_len + size, &mut head)?
+            }
+            _ => return Ok(None),
+        }
+    };
+    let segment_start = io.stream_position()?;
+    // sizes that overflow are treated like an unknown size
+    let segment_end = segment
+        .size
+        .and_then(|size| segment_start.checked_add(size));
+
+    let mut live = segment.size.is_none();
+    let mut children = Vec::new();
+    let mut position = segment_start;
+    while segment_end.map_or(true, |end| position < end) {
+        let header = match read_element_header(io) {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let Some(size) = header.size.filter(|_| header.id != CLUSTER) else {
+            live |= header.size.is_none();
+            break;
+        };
+        if header.id != SEEK_HEAD && size <= MAX_ELEMENT_SIZE {
+            copy_element(io, position, header.header_len + size, &mut children)?;
+        }
+        let Some(next) = position.checked_add(header.header_len + size) else {
+            break;
+        };
+        position = next;
+        io.seek(io::SeekFrom::Start(position))?;
+    }
+    if !live {
+        return Ok(None);
+    }
+    head.extend_from_slice(&SEGMENT.to_be_bytes());
+    // an 8-byte size with the length marker
+    head.extend_from_slice(&(1 << 56 | children.len() as u64).to_be_bytes());
+    head.extend(children);
+    Ok(Some(head))
+}
+
+fn copy_element<R: io::Read + io::Seek>(
+    io: &mut R,
+    position: u64,
+    len: u64,
+    buf: &mut Vec<u8>,
+) -> io::Result<()> {
+    io.seek(io::SeekFrom::Start(position))?;
+    let read = io.take(len).read_to_end(buf)?;
+    if (read as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn find_seek_position(seek_head: &[u8], id: u32) -> Option<u64> {
+    children(seek_head)
+        .filter(|(seek_id, _)| *seek_id == SEEK)
+        .find_map(|(_, seek)| {
+            let mut target = None;
+            let mut position = None;
+            for (child_id, payload) in children(seek) {
+                match child_id {
+                    SEEK_ID => target = Some(read_uint(payload) as u32),
+                    SEEK_POSITION => position = Some(read_uint(payload)),
+                    _ => {}
+                }
+            }
+            (target == Some(id)).then_some(position).flatten()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_segment_child, INFO, SEEK, SEEK_HEAD, SEEK_ID, SEEK_POSITION, SEGMENT, TIMESTAMP_SCALE,
+    };
+    use crate::fixtures::ebml::{element, uint};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_overflowing_seek_position() -> std::io::Result<()> {
+        let seek = [
+            element(SEEK_ID, &INFO.to_be_bytes()),
+            uint(SEEK_POSITION, u64::MAX),
+        ]
+        .concat();
+        let segment = [
+            element(SEEK_HEAD, &element(SEEK, &seek)),
+            element(INFO, &uint(TIMESTAMP_SCALE, 1000)),
+        ]
+        .concat();
+        let mut io = Cursor::new(element(SEGMENT, &segment));
+        // the entry is skipped and `Info` is found by scanning the segment
+        assert_eq!(find_segment_child(&mut io, INFO)?, Some((78, 19)));
+        Ok(())
+    }
+}
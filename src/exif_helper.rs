@@ -1,4 +1,8 @@
-use crate::{Error, MetaData, Result};
+use crate::device::clean_value;
+use crate::{
+    heif_helper, jpeg, tiff, CreationDate, CreationDateOptions, DateSource, Device, Error,
+    Location, MediaDateTime, MetaData, Orientation, Result, Thumbnail, ThumbnailSource,
+};
 use exif::Tag;
 use std::io;
 
@@ -8,47 +12,337 @@ use std::time::SystemTime;
 /// Extracts metadata from an Exif-based media file.
 ///
 /// This function reads Exif metadata from an image or media file using the `kamadak-exif` crate.
-/// If resolution information is missing from the Exif metadata and the `image` feature is enabled,
-/// it attempts to resolve the image's resolution using the `image` crate.
-pub fn extract_exif_metadata<R>(mut io: R, extension: String) -> Result<MetaData>
+/// The size and orientation of HEIF/AVIF images are read from the item properties of the primary
+/// image instead, and the Exif item may be missing. If resolution information is missing from the
+/// Exif metadata and the `image` feature is enabled, it attempts to resolve the image's resolution
+/// using the `image` crate.
+pub fn extract_exif_metadata<R>(io: R, extension: String) -> Result<MetaData>
 where
     R: io::BufRead + io::Seek,
 {
+    extract_exif_metadata_with_options(io, extension, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_exif_metadata_with_options<R>(
+    mut io: R,
+    extension: String,
+    options: &CreationDateOptions,
+) -> Result<MetaData>
+where
+    R: io::BufRead + io::Seek,
+{
+    let start = io.stream_position()?;
+    let heif_image = if is_heif(&extension) {
+        heif_helper::read_primary_image(&mut io)?
+    } else {
+        None
+    };
+    io.seek(io::SeekFrom::Start(start))?;
     let exifreader = exif::Reader::new();
-    let exif = exifreader.read_from_container(&mut io)?;
+    let exif = match exifreader.read_from_container(&mut io) {
+        Ok(exif) => Some(exif),
+        // the Exif item is optional in HEIF files and usually missing in AVIF files
+        Err(exif::Error::NotFound(_)) if heif_image.is_some() => None,
+        Err(e) => return Err(e.into()),
+    };
 
-    let (width, height) = get_width_and_height(&exif, io, extension);
-    let creation_date = get_creation_date(&exif);
+    // HEIF transformations are defined by item properties, the Exif orientation is informative only
+    let orientation = match (&heif_image, &exif) {
+        (Some(image), _) => image.orientation,
+        (None, Some(exif)) => get_orientation(exif),
+        (None, None) => Orientation::default(),
+    };
+    let (width, height) = match (heif_image.and_then(|image| image.size), &exif) {
+        (Some(size), _) => size,
+        (None, Some(exif)) => get_width_and_height(exif, io, extension),
+        (None, None) => (0, 0),
+    };
+    let creation_date = options
+        .select(&exif.as_ref().map(get_creation_dates).unwrap_or_default())
+        .map(|date| date.date);
 
     Ok(MetaData {
         width,
         height,
-        creation_date: creation_date.ok(),
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration: None,
+        orientation,
+        location: exif.as_ref().and_then(get_location),
+        device: exif.as_ref().and_then(get_device),
+        tracks: Vec::new(),
+        quicktime_keys: Default::default(),
+        itunes_tags: Default::default(),
+        matroska_tags: Vec::new(),
+        chapters: Vec::new(),
+        doc_type: None,
+        bit_depth: None,
+        frame_count: None,
+        png_text: Vec::new(),
     })
 }
 
-pub(crate) fn extract_exif_creation_date<R>(mut io: R) -> Result<SystemTime>
+pub(crate) fn extract_exif_creation_dates<R>(mut io: R) -> Result<Vec<CreationDate>>
 where
     R: io::BufRead + io::Seek,
 {
-    let exifreader = exif::Reader::new();
-    let exif = exifreader.read_from_container(&mut io)?;
+    let exif_reader = exif::Reader::new();
+    let exif = exif_reader.read_from_container(&mut io)?;
+    Ok(get_creation_dates(&exif))
+}
+
+/// Reads `DateTimeOriginal`, `DateTimeDigitized` and `DateTime`, in this order. Dates that fail to
+/// parse are skipped.
+pub(crate) fn get_creation_dates(exif: &exif::Exif) -> Vec<CreationDate> {
+    // every date has its own sub-second and offset tags, `OffsetTime` is used when the dedicated
+    // offset is missing
+    const DATE_TAGS: [(Tag, Tag, Tag, DateSource); 3] = [
+        (
+            Tag::DateTimeOriginal,
+            Tag::SubSecTimeOriginal,
+            Tag::OffsetTimeOriginal,
+            DateSource::ExifDateTimeOriginal,
+        ),
+        (
+            Tag::DateTimeDigitized,
+            Tag::SubSecTimeDigitized,
+            Tag::OffsetTimeDigitized,
+            DateSource::ExifDateTimeDigitized,
+        ),
+        (
+            Tag::DateTime,
+            Tag::SubSecTime,
+            Tag::OffsetTime,
+            DateSource::ExifDateTime,
+        ),
+    ];
+    let mut dates = Vec::new();
+    for (date_tag, subsec_tag, offset_tag, source) in DATE_TAGS {
+        let Some(creation_date) = exif.get_field(date_tag, exif::In::PRIMARY) else {
+            continue;
+        };
+        if let Ok(date) = parse_date(exif, creation_date, subsec_tag, offset_tag) {
+            dates.push(CreationDate::new(date, source));
+        }
+    }
+    dates
+}
+
+fn parse_date(
+    exif: &exif::Exif,
+    creation_date: &exif::Field,
+    subsec_tag: Tag,
+    offset_tag: Tag,
+) -> Result<MediaDateTime> {
+    let date_str = creation_date.display_value().with_unit(exif).to_string();
+    let mut datetime = match &creation_date.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .and_then(|value| exif::DateTime::from_ascii(value).ok()),
+        _ => None,
+    }
+    .ok_or_else(|| Error::DateTimeParseError(date_str.clone()))?;
+    if let Some(subsec) = get_string(exif, subsec_tag) {
+        let _ = datetime.parse_subsec(subsec.as_bytes());
+    }
+    if let Some(offset) = get_string(exif, offset_tag).or_else(|| get_string(exif, Tag::OffsetTime))
+    {
+        let _ = datetime.parse_offset(offset.as_bytes());
+    }
+
+    let naive = NaiveDate::from_ymd_opt(
+        datetime.year as i32,
+        datetime.month as u32,
+        datetime.day as u32,
+    )
+    .and_then(|date| {
+        date.and_hms_nano_opt(
+            datetime.hour as u32,
+            datetime.minute as u32,
+            datetime.second as u32,
+            datetime.nanosecond.unwrap_or(0),
+        )
+    })
+    .ok_or(Error::DateTimeParseError(date_str))?;
+    let offset = datetime
+        .offset
+        .and_then(|minutes| FixedOffset::east_opt(minutes as i32 * 60));
+    Ok(match offset {
+        Some(offset) => MediaDateTime::fixed(naive, offset),
+        None => MediaDateTime::floating(naive),
+    })
+}
 
-    get_creation_date(&exif)
+pub(crate) fn get_orientation(exif: &exif::Exif) -> Orientation {
+    exif.get_field(Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(Orientation::from_exif)
+        .unwrap_or_default()
 }
 
-fn get_creation_date(exif: &exif::Exif) -> Result<SystemTime> {
-    for tag in [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime] {
-        let creation_date = exif.get_field(tag, exif::In::PRIMARY);
-        if let Some(creation_date) = creation_date {
-            let date_str = creation_date.display_value().with_unit(exif).to_string();
-            let date = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S")
-                .map(|naive_datetime| SystemTime::from(Utc.from_utc_datetime(&naive_datetime)))
-                .map_err(|_| Error::DateTimeParseError(date_str))?;
-            return Ok(date);
+pub(crate) fn get_location(exif: &exif::Exif) -> Option<Location> {
+    let lat = get_gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let lon = get_gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    let alt = get_rational(exif, Tag::GPSAltitude, 0).map(|alt| {
+        let below_sea_level = exif
+            .get_field(Tag::GPSAltitudeRef, exif::In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            == Some(1);
+        if below_sea_level {
+            -alt
+        } else {
+            alt
         }
+    });
+    Some(Location {
+        lat,
+        lon,
+        alt,
+        timestamp: get_gps_timestamp(exif),
+    })
+}
+
+/// Converts degrees, minutes and seconds to decimal degrees.
+fn get_gps_coordinate(exif: &exif::Exif, tag: Tag, ref_tag: Tag, negative_ref: u8) -> Option<f64> {
+    let degrees = get_rational(exif, tag, 0)?;
+    let minutes = get_rational(exif, tag, 1).unwrap_or(0.0);
+    let seconds = get_rational(exif, tag, 2).unwrap_or(0.0);
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    let is_negative = match exif.get_field(ref_tag, exif::In::PRIMARY).map(|f| &f.value) {
+        Some(exif::Value::Ascii(values)) => {
+            values.first().and_then(|v| v.first()) == Some(&negative_ref)
+        }
+        _ => false,
+    };
+    Some(if is_negative { -value } else { value })
+}
+
+/// Combines `GPSDateStamp` and `GPSTimeStamp`, which are always in UTC.
+fn get_gps_timestamp(exif: &exif::Exif) -> Option<SystemTime> {
+    let date = get_string(exif, Tag::GPSDateStamp)?;
+    let date = NaiveDate::parse_from_str(&date, "%Y:%m:%d").ok()?;
+    let hours = get_rational(exif, Tag::GPSTimeStamp, 0)?;
+    let minutes = get_rational(exif, Tag::GPSTimeStamp, 1)?;
+    let seconds = get_rational(exif, Tag::GPSTimeStamp, 2)?;
+    let nanos = ((hours * 3600.0 + minutes * 60.0 + seconds) * 1e9).round() as i64;
+    let datetime = date.and_hms_opt(0, 0, 0)? + chrono::Duration::nanoseconds(nanos);
+    Some(Utc.from_utc_datetime(&datetime).into())
+}
+
+pub(crate) fn get_device(exif: &exif::Exif) -> Option<Device> {
+    Device {
+        make: get_string(exif, Tag::Make),
+        model: get_string(exif, Tag::Model),
+        software: get_string(exif, Tag::Software),
+        encoder: None,
+        lens_make: get_string(exif, Tag::LensMake),
+        lens_model: get_string(exif, Tag::LensModel),
+        serial_number: get_string(exif, Tag::BodySerialNumber),
+        lens_serial_number: get_string(exif, Tag::LensSerialNumber),
+    }
+    .non_empty()
+}
+
+fn get_string(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => clean_value(&String::from_utf8_lossy(values.first()?)),
+        _ => None,
+    }
+}
+
+fn get_rational(exif: &exif::Exif, tag: Tag, index: usize) -> Option<f64> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Rational(values) => values
+            .get(index)
+            .filter(|value| value.denom != 0)
+            .map(|value| value.to_f64()),
+        _ => None,
     }
-    Err(Error::CreationDateNotFound)
+}
+
+/// Finds the Exif thumbnail and the embedded previews of an image or RAW file.
+pub(crate) fn find_exif_thumbnails<R>(io: &mut R, extension: &str) -> Result<Vec<Thumbnail>>
+where
+    R: io::BufRead + io::Seek,
+{
+    let start = io.stream_position()?;
+    let end = io.seek(io::SeekFrom::End(0))?;
+    let thumbnails = match extension {
+        "jpg" | "jpeg" => match jpeg::find_exif(io, start..end)? {
+            Some(base) => tiff::find_previews(io, base, false)?,
+            None => Vec::new(),
+        },
+        extension if is_heif(extension) => {
+            io.seek(io::SeekFrom::Start(start))?;
+            heif_helper::find_heif_thumbnails(io)?
+        }
+        "raf" => find_raf_thumbnails(io, start)?,
+        "webp" => match find_webp_exif(io, start)? {
+            Some(base) => tiff::find_previews(io, base, false)?,
+            None => Vec::new(),
+        },
+        // TIFF based RAW formats store the main image in IFD0 and previews in further IFDs
+        extension => tiff::find_previews(io, start, !matches!(extension, "tif" | "tiff"))?,
+    };
+    Ok(thumbnails)
+}
+
+/// Fujifilm RAF files start with a header that points to a full-size JPEG preview, whose Exif
+/// data holds the thumbnail.
+fn find_raf_thumbnails<R: io::Read + io::Seek>(
+    io: &mut R,
+    start: u64,
+) -> io::Result<Vec<Thumbnail>> {
+    let mut header = [0u8; 92];
+    io.seek(io::SeekFrom::Start(start))?;
+    if io.read_exact(&mut header).is_err() || !header.starts_with(b"FUJIFILMCCD-RAW") {
+        return Ok(Vec::new());
+    }
+    let offset = start + u32::from_be_bytes(header[84..88].try_into().unwrap()) as u64;
+    let len = u32::from_be_bytes(header[88..92].try_into().unwrap()) as u64;
+    let range = offset..offset + len;
+    let mut thumbnails = Vec::new();
+    if let Some(base) = jpeg::find_exif(io, range.clone())? {
+        thumbnails.extend(tiff::find_previews(io, base, false)?);
+    }
+    thumbnails.push(Thumbnail::new(
+        ThumbnailSource::TiffPreview,
+        "image/jpeg",
+        range,
+    ));
+    Ok(thumbnails)
+}
+
+/// Returns the position of the TIFF header in the `EXIF` chunk of a WebP file.
+fn find_webp_exif<R: io::Read + io::Seek>(io: &mut R, start: u64) -> io::Result<Option<u64>> {
+    let mut header = [0u8; 12];
+    io.seek(io::SeekFrom::Start(start))?;
+    if io.read_exact(&mut header).is_err() || &header[0..4] != b"RIFF" || &header[8..12] != b"WEBP"
+    {
+        return Ok(None);
+    }
+    let mut position = start + 12;
+    loop {
+        let mut chunk = [0u8; 8];
+        io.seek(io::SeekFrom::Start(position))?;
+        if io.read_exact(&mut chunk).is_err() {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as u64;
+        if &chunk[0..4] == b"EXIF" {
+            // some writers keep the "Exif\0\0" prefix of the JPEG segment
+            let mut prefix = [0u8; 6];
+            let has_prefix =
+                size >= 6 && io.read_exact(&mut prefix).is_ok() && &prefix == b"Exif\0\0";
+            return Ok(Some(position + 8 + if has_prefix { 6 } else { 0 }));
+        }
+        // chunks are padded to an even size
+        position += 8 + size + (size & 1);
+    }
+}
+
+fn is_heif(extension: &str) -> bool {
+    matches!(extension, "heic" | "heif" | "avif")
 }
 
 #[allow(unused_mut, unused_variables)]
@@ -79,3 +373,103 @@ where
 
     (width, height)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::DateSource;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Rational, Tag, Value};
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn rationals(values: &[(u32, u32)]) -> Value {
+        Value::Rational(
+            values
+                .iter()
+                .map(|&(num, denom)| Rational { num, denom })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_gps_location() -> anyhow::Result<()> {
+        let fields = [
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"S".to_vec()])),
+            field(Tag::GPSLatitude, rationals(&[(33, 1), (52, 1), (768, 100)])),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"E".to_vec()])),
+            field(
+                Tag::GPSLongitude,
+                rationals(&[(151, 1), (12, 1), (3348, 100)]),
+            ),
+            field(Tag::GPSAltitudeRef, Value::Byte(vec![1])),
+            field(Tag::GPSAltitude, rationals(&[(25, 2)])),
+            field(
+                Tag::GPSDateStamp,
+                Value::Ascii(vec![b"2024:05:06".to_vec()]),
+            ),
+            field(Tag::GPSTimeStamp, rationals(&[(7, 1), (8, 1), (9, 1)])),
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false)?;
+        tiff.set_position(0);
+
+        let metadata = super::extract_exif_metadata(tiff, "tiff".to_owned())?;
+        let location = metadata.location.unwrap();
+        assert!((location.lat + 33.8688).abs() < 1e-6);
+        assert!((location.lon - 151.2093).abs() < 1e-6);
+        assert_eq!(location.alt, Some(-12.5));
+        assert_eq!(
+            location.timestamp,
+            Some(crate::parse_date("2024-05-06T07:08:09"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_creation_date_offset() -> anyhow::Result<()> {
+        let fields = [
+            field(
+                Tag::DateTimeOriginal,
+                Value::Ascii(vec![b"2024:05:06 09:08:07".to_vec()]),
+            ),
+            field(Tag::SubSecTimeOriginal, Value::Ascii(vec![b"25".to_vec()])),
+            field(Tag::OffsetTime, Value::Ascii(vec![b"+02:00".to_vec()])),
+            field(
+                Tag::DateTime,
+                Value::Ascii(vec![b"2024:05:08 10:00:00".to_vec()]),
+            ),
+        ];
+        let mut writer = Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = std::io::Cursor::new(Vec::new());
+        writer.write(&mut tiff, false)?;
+        tiff.set_position(0);
+
+        let dates = super::extract_exif_creation_dates(tiff)?;
+        let sources: Vec<_> = dates.iter().map(|date| date.source).collect();
+        assert_eq!(
+            sources,
+            [DateSource::ExifDateTimeOriginal, DateSource::ExifDateTime]
+        );
+        assert_eq!(dates[1].date.to_string(), "2024-05-08T10:00:00+02:00");
+        let date = dates[0].date;
+        assert_eq!(date.to_string(), "2024-05-06T09:08:07.250+02:00");
+        assert_eq!(
+            date.to_system_time(),
+            crate::parse_date("2024-05-06T07:08:07.250")
+        );
+        Ok(())
+    }
+}
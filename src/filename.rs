@@ -0,0 +1,128 @@
+use crate::MediaDateTime;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use std::path::Path;
+
+/// Built-in file name patterns as `chrono` formats, most specific first. Names from Google Pixel
+/// phones (`PXL_`) are in UTC, all others are local time.
+///
+/// GoPro cameras name files by sequence number, e.g. `GX010042.MP4`, so their names carry no date.
+const PATTERNS: [(&str, bool); 14] = [
+    // Google Pixel: PXL_20230101_101010123.mp4
+    ("PXL_%Y%m%d_%H%M%S%3f", true),
+    // Android camera and screenshots: IMG_20210813_180435.jpg, Screenshot_20220501-101112.png
+    ("%Y%m%d_%H%M%S", false),
+    ("%Y%m%d-%H%M%S", false),
+    // DJI drones: DJI_20230101101010_0001_D.JPG
+    ("%Y%m%d%H%M%S", false),
+    // Signal: signal-2021-08-13-18-04-35-123.jpg, signal-2021-08-13-180435.jpg
+    ("%Y-%m-%d-%H-%M-%S-%3f", false),
+    ("%Y-%m-%d-%H-%M-%S", false),
+    ("%Y-%m-%d-%H%M%S", false),
+    // Older Android screenshots and exports: Screenshot_2022-05-01-10-11-12.png
+    ("%Y-%m-%d_%H-%M-%S", false),
+    // Dropbox and iOS exports: 2021-08-13 18.04.35.jpg
+    ("%Y-%m-%d %H.%M.%S", false),
+    // macOS screenshots: Screenshot 2022-05-01 at 10.11.12.png
+    ("%Y-%m-%d at %H.%M.%S", false),
+    ("%Y-%m-%d %H-%M-%S", false),
+    ("%Y-%m-%dT%H%M%S", false),
+    // WhatsApp: IMG-20200101-WA0003.jpg, VID-20200101-WA0003.mp4
+    ("%Y%m%d", false),
+    ("%Y-%m-%d", false),
+];
+
+/// Parses the date and time encoded in a file name, e.g. `IMG_20210813_180435.jpg`.
+///
+/// `patterns` are `chrono` format strings, e.g. `scan-%Y%m%d`, which are tried before the built-in
+/// patterns of common Android, iOS, WhatsApp, Signal and DJI names. A pattern matches anywhere in
+/// the file name without extension, as long as it is not surrounded by digits. Dates without time
+/// are at midnight. The result is local time, except for Google Pixel names, which are in UTC.
+pub fn parse_filename_date<P: AsRef<Path>>(
+    file_path: P,
+    patterns: &[String],
+) -> Option<MediaDateTime> {
+    let stem = file_path.as_ref().file_stem()?.to_str()?;
+    let user_patterns = patterns.iter().map(|pattern| (pattern.as_str(), false));
+    user_patterns.chain(PATTERNS).find_map(|(pattern, utc)| {
+        let datetime = find_pattern(stem, pattern)?;
+        Some(if utc {
+            MediaDateTime::utc(datetime)
+        } else {
+            MediaDateTime::floating(datetime)
+        })
+    })
+}
+
+/// Finds the first position of `stem` that matches `pattern`.
+fn find_pattern(stem: &str, pattern: &str) -> Option<NaiveDateTime> {
+    stem.char_indices().find_map(|(start, _)| {
+        let (before, rest) = stem.split_at(start);
+        if before.ends_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        parse_prefix(rest, pattern)
+    })
+}
+
+/// Parses the beginning of `value`, which must not continue with a digit.
+fn parse_prefix(value: &str, pattern: &str) -> Option<NaiveDateTime> {
+    let (datetime, rest) = match NaiveDateTime::parse_and_remainder(value, pattern) {
+        Ok(result) => result,
+        Err(_) => NaiveDate::parse_and_remainder(value, pattern)
+            .ok()
+            .and_then(|(date, rest)| Some((date.and_hms_opt(0, 0, 0)?, rest)))?,
+    };
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    // reject counters and other numbers that happen to form a valid date
+    (1990..2100).contains(&datetime.year()).then_some(datetime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_filename_date;
+    use crate::DateTimeOffset;
+
+    fn parse(file_name: &str) -> Option<String> {
+        parse_filename_date(file_name, &[]).map(|date| date.to_string())
+    }
+
+    #[test]
+    fn test_builtin_patterns() {
+        let cases = [
+            ("IMG_20210813_180435.jpg", "2021-08-13T18:04:35"),
+            ("PXL_20230101_101010123.mp4", "2023-01-01T10:10:10.123Z"),
+            ("VID-20200101-WA0003.mp4", "2020-01-01T00:00:00"),
+            ("Screenshot_2022-05-01-10-11-12.png", "2022-05-01T10:11:12"),
+            ("Screenshot_20220501-101112.png", "2022-05-01T10:11:12"),
+            (
+                "Screenshot 2022-05-01 at 10.11.12.png",
+                "2022-05-01T10:11:12",
+            ),
+            (
+                "signal-2021-08-13-18-04-35-123.jpg",
+                "2021-08-13T18:04:35.123",
+            ),
+            ("signal-2021-08-13-180435.jpg", "2021-08-13T18:04:35"),
+            ("DJI_20230101101010_0001_D.JPG", "2023-01-01T10:10:10"),
+            ("2021-08-13 18.04.35.jpg", "2021-08-13T18:04:35"),
+        ];
+        for (file_name, expected) in cases {
+            assert_eq!(parse(file_name).as_deref(), Some(expected), "{file_name}");
+        }
+
+        assert_eq!(parse("GX010042.MP4"), None);
+        assert_eq!(parse("IMG_1234.JPG"), None);
+        assert_eq!(parse("video_120210813.mp4"), None);
+        assert_eq!(parse("12345678.mp4"), None);
+    }
+
+    #[test]
+    fn test_user_patterns() {
+        let patterns = ["scan %d.%m.%Y".to_owned()];
+        let date = parse_filename_date("/photos/scan 13.08.2021 (2).tif", &patterns).unwrap();
+        assert_eq!(date.to_string(), "2021-08-13T00:00:00");
+        assert_eq!(date.offset, DateTimeOffset::Floating);
+    }
+}
@@ -0,0 +1,46 @@
+//! Builders of synthetic container data, shared by the unit tests and the integration tests,
+//! which include this file with `#[path]`. Types are taken from the parent module, i.e. the
+//! crate root of both.
+
+use super::{MatroskaTag, MatroskaTarget, TagValue};
+
+/// Encodes an ISOBMFF box with a 32-bit size.
+pub(crate) fn make_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Text tag of the default level, applying to the given tracks or the whole file.
+pub(crate) fn tag(name: &str, value: &str, track_uids: Vec<u64>) -> MatroskaTag {
+    MatroskaTag {
+        name: name.to_owned(),
+        value: TagValue::Text(value.to_owned()),
+        language: None,
+        target: MatroskaTarget {
+            track_uids,
+            ..Default::default()
+        },
+    }
+}
+
+/// EBML elements.
+pub(crate) mod ebml {
+    /// Encodes an element ID without its leading zero bytes.
+    pub(crate) fn id_bytes(id: u32) -> Vec<u8> {
+        let id = id.to_be_bytes();
+        id[id.iter().position(|byte| *byte != 0).unwrap()..].to_vec()
+    }
+
+    /// Encodes an element with an 8-byte size.
+    pub(crate) fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+        // the length marker of an 8-byte size
+        let size = (payload.len() as u64 | 0x0100_0000_0000_0000).to_be_bytes();
+        [&id_bytes(id)[..], &size, payload].concat()
+    }
+
+    pub(crate) fn uint(id: u32, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+}
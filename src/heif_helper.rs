@@ -0,0 +1,634 @@
+use crate::isobmff::{self, read_u16, read_u32};
+use crate::{tiff, Orientation, Thumbnail, ThumbnailSource};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::ops::Range;
+
+/// Maximum size of item data that is read into memory, e.g. for grid descriptions.
+const MAX_ITEM_DATA_SIZE: u64 = 64 * 1024;
+
+/// Items and item properties of a HEIF/AVIF image parsed from the `meta` box.
+pub(crate) struct HeifMeta<'a> {
+    primary_item: Option<u32>,
+    /// Item ids with their types from `iinf`, e.g. `hvc1`, `grid` or `Exif`.
+    items: Vec<(u32, [u8; 4])>,
+    references: Vec<ItemReference>,
+    /// Item data locations from `iloc`.
+    locations: HashMap<u32, ItemLocation>,
+    /// Properties from `iprp/ipco` in their original order.
+    properties: Vec<([u8; 4], &'a [u8])>,
+    /// One-based property indices associated with every item, from `iprp/ipma`.
+    associations: HashMap<u32, Vec<u16>>,
+    /// Item data stored in the `meta` box itself, typically grid descriptions.
+    idat: Option<&'a [u8]>,
+}
+
+/// Size and orientation of the primary image of a HEIF/AVIF file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PrimaryImage {
+    /// Output size after the grid derivation and the clean aperture, `None` if the `ispe`
+    /// property is missing.
+    pub size: Option<(u64, u64)>,
+    /// Rotation and mirroring from the `irot` and `imir` properties.
+    pub orientation: Orientation,
+}
+
+impl<'a> HeifMeta<'a> {
+    /// Parses the payload of a top-level `meta` box.
+    pub(crate) fn parse(meta: &'a [u8]) -> Option<Self> {
+        let (_, _, children) = isobmff::full_box(meta)?;
+
+        let primary_item = isobmff::find_box(children, b"pitm").and_then(parse_pitm);
+        let items = isobmff::find_box(children, b"iinf")
+            .and_then(parse_iinf)
+            .unwrap_or_default();
+        let references = isobmff::find_box(children, b"iref")
+            .and_then(parse_iref)
+            .unwrap_or_default();
+        let locations = isobmff::find_box(children, b"iloc")
+            .and_then(parse_iloc)
+            .unwrap_or_default();
+        let idat = isobmff::find_box(children, b"idat");
+
+        let mut properties = Vec::new();
+        let mut associations = HashMap::new();
+        if let Some(iprp) = isobmff::find_box(children, b"iprp") {
+            if let Some(ipco) = isobmff::find_box(iprp, b"ipco") {
+                properties = isobmff::boxes(ipco).collect();
+            }
+            for (box_type, ipma) in isobmff::boxes(iprp) {
+                if &box_type == b"ipma" {
+                    parse_ipma(ipma, &mut associations);
+                }
+            }
+        }
+
+        Some(Self {
+            primary_item,
+            items,
+            references,
+            locations,
+            properties,
+            associations,
+            idat,
+        })
+    }
+
+    /// Returns the properties associated with the item, in the order they must be applied.
+    pub(crate) fn item_properties(
+        &self,
+        item: u32,
+    ) -> impl Iterator<Item = ([u8; 4], &'a [u8])> + '_ {
+        self.associations
+            .get(&item)
+            .into_iter()
+            .flatten()
+            .filter_map(|index| self.properties.get((*index as usize).checked_sub(1)?))
+            .copied()
+    }
+
+    /// Returns the orientation of the item derived from its `irot` and `imir` properties.
+    pub(crate) fn item_orientation(&self, item: u32) -> Orientation {
+        let mut orientation = Orientation::default();
+        for (box_type, payload) in self.item_properties(item) {
+            match &box_type {
+                // anti-clockwise rotation in units of 90 degrees
+                b"irot" => {
+                    if let Some(angle) = payload.first() {
+                        orientation = orientation.rotate(-90 * (angle & 0x03) as i64);
+                    }
+                }
+                // axis 0 is the vertical axis, i.e. a left-right flip
+                b"imir" => {
+                    if let Some(axis) = payload.first() {
+                        orientation = if axis & 0x01 == 0 {
+                            orientation.mirror_horizontally()
+                        } else {
+                            orientation.mirror_vertically()
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+        orientation
+    }
+
+    /// Returns the size of the item from its `ispe` property.
+    pub(crate) fn item_size(&self, item: u32) -> Option<(u32, u32)> {
+        let (_, ispe) = self
+            .item_properties(item)
+            .find(|(box_type, _)| box_type == b"ispe")?;
+        let (_, _, data) = isobmff::full_box(ispe)?;
+        Some((read_u32(data, 0)?, read_u32(data, 4)?))
+    }
+
+    /// Returns the size of an image item as it is displayed, except for rotation and mirroring.
+    ///
+    /// Grid items are composed of tiles and have the output size of the grid, the clean aperture
+    /// crops the image. The item data of grids is read from the file if it is not in `idat`.
+    pub(crate) fn item_output_size<R: io::Read + io::Seek>(
+        &self,
+        io: &mut R,
+        item: u32,
+    ) -> io::Result<Option<(u64, u64)>> {
+        let mut size = self
+            .item_size(item)
+            .map(|(width, height)| (width as u64, height as u64));
+        if self.item_type(item) == Some(*b"grid") {
+            if let Some(grid_size) = self
+                .read_item_data(io, item)?
+                .as_deref()
+                .and_then(parse_grid)
+            {
+                size = Some(grid_size);
+            }
+        }
+        Ok(size.map(|size| self.apply_clean_aperture(item, size)))
+    }
+
+    /// Crops the size to the `clap` property. The aperture is given for the image after the
+    /// transformations that precede it, so a preceding rotation by 90 degrees swaps it.
+    fn apply_clean_aperture(&self, item: u32, (width, height): (u64, u64)) -> (u64, u64) {
+        let mut size = (width, height);
+        let mut swapped = false;
+        for (box_type, payload) in self.item_properties(item) {
+            match &box_type {
+                // rotations by 90 or 270 degrees
+                b"irot" if payload.first().is_some_and(|angle| angle & 0x01 != 0) => {
+                    swapped = !swapped;
+                }
+                b"clap" => {
+                    if let Some((clap_width, clap_height)) = parse_clap(payload) {
+                        let (clap_width, clap_height) = if swapped {
+                            (clap_height, clap_width)
+                        } else {
+                            (clap_width, clap_height)
+                        };
+                        size = (clap_width.min(size.0), clap_height.min(size.1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        size
+    }
+
+    /// Reads the data of a small item like a grid description, either from the `idat` box or
+    /// from the file.
+    fn read_item_data<R: io::Read + io::Seek>(
+        &self,
+        io: &mut R,
+        item: u32,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let Some(location) = self.locations.get(&item) else {
+            return Ok(None);
+        };
+        let len = location.extents.iter().try_fold(0u64, |sum, extent| {
+            sum.checked_add(extent.end - extent.start)
+        });
+        let Some(len) = len.filter(|len| *len <= MAX_ITEM_DATA_SIZE) else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(len as usize);
+        for extent in &location.extents {
+            match location.construction_method {
+                0 => {
+                    let limit = MAX_ITEM_DATA_SIZE - data.len() as u64;
+                    io.seek(io::SeekFrom::Start(extent.start))?;
+                    io.take((extent.end - extent.start).min(limit))
+                        .read_to_end(&mut data)?;
+                }
+                1 => {
+                    let idat = self.idat.unwrap_or_default();
+                    let Some(extent) = idat.get(extent.start as usize..extent.end as usize) else {
+                        return Ok(None);
+                    };
+                    data.extend_from_slice(extent);
+                }
+                _ => return Ok(None),
+            }
+        }
+        Ok(Some(data))
+    }
+
+    pub(crate) fn primary_item(&self) -> Option<u32> {
+        self.primary_item
+    }
+
+    pub(crate) fn item_type(&self, item: u32) -> Option<[u8; 4]> {
+        self.items
+            .iter()
+            .find(|(id, _)| *id == item)
+            .map(|(_, item_type)| *item_type)
+    }
+
+    /// Returns the items that reference `item` with the given reference type, e.g. the
+    /// thumbnails of an image with `thmb`.
+    pub(crate) fn referencing_items(
+        &self,
+        reference_type: &[u8; 4],
+        item: u32,
+    ) -> impl Iterator<Item = u32> + '_ {
+        let reference_type = *reference_type;
+        self.references
+            .iter()
+            .filter(move |reference| {
+                reference.reference_type == reference_type && reference.to.contains(&item)
+            })
+            .map(|reference| reference.from)
+    }
+
+    /// Returns the byte ranges of the item data in the file. Data stored in the `idat` box is
+    /// not supported.
+    pub(crate) fn item_ranges(&self, item: u32) -> Option<&[Range<u64>]> {
+        let location = self.locations.get(&item)?;
+        (location.construction_method == 0 && !location.extents.is_empty())
+            .then_some(location.extents.as_slice())
+    }
+}
+
+/// Reference from the `iref` box, e.g. a `thmb` reference from a thumbnail to its image.
+#[derive(Debug)]
+struct ItemReference {
+    reference_type: [u8; 4],
+    from: u32,
+    to: Vec<u32>,
+}
+
+/// Data location of an item from the `iloc` box.
+#[derive(Debug, Default)]
+struct ItemLocation {
+    /// 0 for file offsets, 1 for offsets into the `idat` box, 2 for item references.
+    construction_method: u8,
+    /// Extents including the base offset.
+    extents: Vec<Range<u64>>,
+}
+
+fn parse_iinf(iinf: &[u8]) -> Option<Vec<(u32, [u8; 4])>> {
+    let (version, _, data) = isobmff::full_box(iinf)?;
+    let entries = if version == 0 {
+        data.get(2..)?
+    } else {
+        data.get(4..)?
+    };
+    let items = isobmff::boxes(entries)
+        .filter(|(box_type, _)| box_type == b"infe")
+        .filter_map(|(_, infe)| {
+            // only versions 2 and 3 have an item type
+            let (version, _, data) = isobmff::full_box(infe)?;
+            match version {
+                2 => Some((read_u16(data, 0)? as u32, data.get(4..8)?.try_into().ok()?)),
+                3 => Some((read_u32(data, 0)?, data.get(6..10)?.try_into().ok()?)),
+                _ => None,
+            }
+        })
+        .collect();
+    Some(items)
+}
+
+fn parse_iref(iref: &[u8]) -> Option<Vec<ItemReference>> {
+    let (version, _, data) = isobmff::full_box(iref)?;
+    let id_size = if version == 0 { 2 } else { 4 };
+    let read_id = |data: &[u8], offset| {
+        if id_size == 2 {
+            read_u16(data, offset).map(u32::from)
+        } else {
+            read_u32(data, offset)
+        }
+    };
+    let references = isobmff::boxes(data)
+        .filter_map(|(reference_type, reference)| {
+            let from = read_id(reference, 0)?;
+            let count = read_u16(reference, id_size)? as usize;
+            let to = (0..count)
+                .map(|index| read_id(reference, id_size + 2 + index * id_size))
+                .collect::<Option<Vec<_>>>()?;
+            Some(ItemReference {
+                reference_type,
+                from,
+                to,
+            })
+        })
+        .collect();
+    Some(references)
+}
+
+fn parse_iloc(iloc: &[u8]) -> Option<HashMap<u32, ItemLocation>> {
+    let (version, _, data) = isobmff::full_box(iloc)?;
+    let offset_size = (*data.first()? >> 4) as usize;
+    let length_size = (*data.first()? & 0x0F) as usize;
+    let base_offset_size = (*data.get(1)? >> 4) as usize;
+    let index_size = if version >= 1 {
+        (*data.get(1)? & 0x0F) as usize
+    } else {
+        0
+    };
+    let mut offset = 2;
+    let mut read = |size: usize| {
+        let value = read_uint(data, offset, size);
+        offset += size;
+        value
+    };
+    let item_count = if version < 2 { read(2)? } else { read(4)? };
+    let mut locations = HashMap::new();
+    for _ in 0..item_count {
+        let item = if version < 2 { read(2)? } else { read(4)? } as u32;
+        let construction_method = if version >= 1 {
+            (read(2)? & 0x0F) as u8
+        } else {
+            0
+        };
+        // data reference index, 0 is the file itself
+        read(2)?;
+        let base_offset = read(base_offset_size)?;
+        let extent_count = read(2)?;
+        let mut extents = Vec::new();
+        for _ in 0..extent_count {
+            read(index_size)?;
+            let start = base_offset.checked_add(read(offset_size)?)?;
+            let len = read(length_size)?;
+            extents.push(start..start.checked_add(len)?);
+        }
+        locations.insert(
+            item,
+            ItemLocation {
+                construction_method,
+                extents,
+            },
+        );
+    }
+    Some(locations)
+}
+
+/// Reads a big-endian unsigned integer of 0, 4 or 8 bytes, as used by `iloc`.
+fn read_uint(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + size)?;
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+/// Parses the description of a `grid` item and returns its output size.
+fn parse_grid(data: &[u8]) -> Option<(u64, u64)> {
+    // the flags select 16 or 32-bit sizes, after the number of rows and columns
+    let flags = *data.get(1)?;
+    if flags & 0x01 == 0 {
+        Some((read_u16(data, 4)? as u64, read_u16(data, 6)? as u64))
+    } else {
+        Some((read_u32(data, 4)? as u64, read_u32(data, 8)? as u64))
+    }
+}
+
+/// Parses the width and height of a clean aperture, which are fractions of 32-bit integers. The
+/// offset of the aperture doesn't matter for its size.
+fn parse_clap(clap: &[u8]) -> Option<(u64, u64)> {
+    let fraction = |offset| {
+        let numerator = read_u32(clap, offset)? as u64;
+        let denominator = read_u32(clap, offset + 4)? as u64;
+        numerator.checked_div(denominator)
+    };
+    Some((fraction(0)?, fraction(8)?))
+}
+
+fn parse_pitm(pitm: &[u8]) -> Option<u32> {
+    let (version, _, data) = isobmff::full_box(pitm)?;
+    if version == 0 {
+        read_u16(data, 0).map(u32::from)
+    } else {
+        read_u32(data, 0)
+    }
+}
+
+fn parse_ipma(ipma: &[u8], associations: &mut HashMap<u32, Vec<u16>>) -> Option<()> {
+    let (version, flags, data) = isobmff::full_box(ipma)?;
+    let entry_count = read_u32(data, 0)?;
+    let mut offset = 4;
+    for _ in 0..entry_count {
+        let item = if version < 1 {
+            offset += 2;
+            read_u16(data, offset - 2)? as u32
+        } else {
+            offset += 4;
+            read_u32(data, offset - 4)?
+        };
+        let count = *data.get(offset)?;
+        offset += 1;
+        let indices = associations.entry(item).or_default();
+        for _ in 0..count {
+            // the top bit of each association marks the property as essential
+            let index = if flags & 1 != 0 {
+                offset += 2;
+                read_u16(data, offset - 2)? & 0x7FFF
+            } else {
+                offset += 1;
+                (*data.get(offset - 1)? & 0x7F) as u16
+            };
+            indices.push(index);
+        }
+    }
+    Some(())
+}
+
+/// Reads the output size and the orientation of the primary image from the `meta` box of a
+/// HEIF/AVIF file.
+pub(crate) fn read_primary_image<R: io::Read + io::Seek>(
+    io: &mut R,
+) -> io::Result<Option<PrimaryImage>> {
+    let Some(meta) = isobmff::read_top_level_box(io, b"meta")? else {
+        return Ok(None);
+    };
+    let Some(heif) = HeifMeta::parse(&meta) else {
+        return Ok(None);
+    };
+    let Some(primary_item) = heif.primary_item() else {
+        return Ok(None);
+    };
+    Ok(Some(PrimaryImage {
+        size: heif.item_output_size(io, primary_item)?,
+        orientation: heif.item_orientation(primary_item),
+    }))
+}
+
+/// Finds the thumbnail items of the primary image and the thumbnail in the Exif item of a
+/// HEIF/AVIF file.
+pub(crate) fn find_heif_thumbnails<R: io::Read + io::Seek>(
+    io: &mut R,
+) -> io::Result<Vec<Thumbnail>> {
+    let Some(meta) = isobmff::read_top_level_box(io, b"meta")? else {
+        return Ok(Vec::new());
+    };
+    let Some(heif) = HeifMeta::parse(&meta) else {
+        return Ok(Vec::new());
+    };
+    let Some(primary_item) = heif.primary_item() else {
+        return Ok(Vec::new());
+    };
+
+    let mut thumbnails = Vec::new();
+    for item in heif.referencing_items(b"thmb", primary_item) {
+        let mime_type = match &heif.item_type(item).unwrap_or_default() {
+            b"jpeg" => "image/jpeg",
+            b"hvc1" => "image/heic",
+            b"av01" => "image/avif",
+            _ => continue,
+        };
+        let Some(ranges) = heif.item_ranges(item) else {
+            continue;
+        };
+        let (width, height) = heif.item_size(item).unwrap_or_default();
+        thumbnails.push(Thumbnail {
+            source: ThumbnailSource::HeifThumbnail,
+            mime_type: mime_type.to_owned(),
+            width: width as u64,
+            height: height as u64,
+            ranges: ranges.to_vec(),
+        });
+    }
+
+    let exif = heif
+        .referencing_items(b"cdsc", primary_item)
+        .filter(|item| heif.item_type(*item) == Some(*b"Exif"))
+        .find_map(|item| heif.item_ranges(item)?.first().cloned());
+    if let Some(exif) = exif {
+        // the Exif data starts with the offset of the TIFF header
+        io.seek(io::SeekFrom::Start(exif.start))?;
+        let mut header_offset = [0u8; 4];
+        io.read_exact(&mut header_offset)?;
+        let base = exif.start + 4 + u32::from_be_bytes(header_offset) as u64;
+        thumbnails.extend(tiff::find_previews(io, base, false)?);
+    }
+    Ok(thumbnails)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_heif_thumbnails, read_primary_image, HeifMeta};
+    use crate::fixtures::make_box;
+    use crate::{Orientation, ThumbnailSource};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_primary_item_orientation() {
+        let pitm = make_box(b"pitm", &[0, 0, 0, 0, 0, 1]);
+        let ipco = [
+            make_box(b"ispe", &[0; 12]),
+            make_box(b"irot", &[1]),
+            make_box(b"imir", &[0]),
+        ]
+        .concat();
+        // item 1 -> properties 1, 2 and 3; item 2 -> property 2
+        let ipma = [0, 0, 0, 0, 0, 0, 0, 2, 0, 1, 3, 1, 0x82, 3, 0, 2, 1, 2];
+        let iprp = [make_box(b"ipco", &ipco), make_box(b"ipma", &ipma)].concat();
+        let meta = [vec![0, 0, 0, 0], pitm, make_box(b"iprp", &iprp)].concat();
+
+        let heif = HeifMeta::parse(&meta).unwrap();
+        assert_eq!(heif.primary_item(), Some(1));
+        // 90 degrees anti-clockwise followed by a left-right flip
+        assert_eq!(heif.item_orientation(1), Orientation::from_exif(7).unwrap());
+        assert_eq!(heif.item_orientation(2), Orientation::from_exif(8).unwrap());
+    }
+
+    #[test]
+    fn test_thumbnail_items() -> std::io::Result<()> {
+        let infe = |id: u8, item_type: &[u8; 4]| {
+            make_box(
+                b"infe",
+                &[&[2, 0, 0, 0, 0, id, 0, 0][..], item_type, b"\0"].concat(),
+            )
+        };
+        let iinf = [vec![0, 0, 0, 0, 0, 2], infe(1, b"hvc1"), infe(2, b"jpeg")].concat();
+        // item 2 is the thumbnail of item 1
+        let iref = [vec![0, 0, 0, 0], make_box(b"thmb", &[0, 2, 0, 1, 0, 1])].concat();
+        let ispe = make_box(b"ispe", &[0, 0, 0, 0, 0, 0, 0, 160, 0, 0, 0, 120]);
+        let ipma = [0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 1, 1];
+        let iprp = [make_box(b"ipco", &ispe), make_box(b"ipma", &ipma)].concat();
+        let meta_len = 8 + 4 + 14 + (8 + iinf.len()) + (8 + iref.len()) + 32 + 8 + iprp.len();
+        // version 1 with 4-byte offsets and lengths, one extent of 100 bytes after the box
+        let mut iloc = vec![1, 0, 0, 0, 0x44, 0, 0, 1, 0, 2, 0, 0, 0, 0, 0, 1];
+        iloc.extend_from_slice(&(meta_len as u32).to_be_bytes());
+        iloc.extend_from_slice(&100u32.to_be_bytes());
+        let meta = [
+            vec![0, 0, 0, 0],
+            make_box(b"pitm", &[0, 0, 0, 0, 0, 1]),
+            make_box(b"iinf", &iinf),
+            make_box(b"iref", &iref),
+            make_box(b"iloc", &iloc),
+            make_box(b"iprp", &iprp),
+        ]
+        .concat();
+        let file = make_box(b"meta", &meta);
+        assert_eq!(file.len(), meta_len);
+
+        let thumbnails = find_heif_thumbnails(&mut Cursor::new(file))?;
+        assert_eq!(thumbnails.len(), 1);
+        let thumbnail = &thumbnails[0];
+        assert_eq!(thumbnail.source, ThumbnailSource::HeifThumbnail);
+        assert_eq!(thumbnail.mime_type, "image/jpeg");
+        assert_eq!((thumbnail.width, thumbnail.height), (160, 120));
+        assert_eq!(
+            thumbnail.ranges,
+            vec![meta_len as u64..meta_len as u64 + 100]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_primary_image_size() -> std::io::Result<()> {
+        let iinf = [
+            vec![0, 0, 0, 0, 0, 1],
+            make_box(
+                b"infe",
+                &[&[2, 0, 0, 0, 0, 1, 0, 0][..], b"grid", b"\0"].concat(),
+            ),
+        ]
+        .concat();
+        let clap = [1080u32, 1, 1920, 1, 0, 1, 0, 1]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+        let ipco = [make_box(b"irot", &[1]), make_box(b"clap", &clap)].concat();
+        // the rotation precedes the clean aperture, which is given in the rotated orientation
+        let ipma = [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 2, 0x81, 2];
+        let iprp = [make_box(b"ipco", &ipco), make_box(b"ipma", &ipma)].concat();
+        // the grid description follows the `meta` box, with 32-bit output sizes of 1920x1088
+        let make_file = |grid_offset: u32| {
+            let mut iloc = vec![1, 0, 0, 0, 0x44, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1];
+            iloc.extend_from_slice(&grid_offset.to_be_bytes());
+            iloc.extend_from_slice(&12u32.to_be_bytes());
+            let meta = [
+                vec![0, 0, 0, 0],
+                make_box(b"pitm", &[0, 0, 0, 0, 0, 1]),
+                make_box(b"iinf", &iinf),
+                make_box(b"iloc", &iloc),
+                make_box(b"iprp", &iprp),
+            ]
+            .concat();
+            let grid = [0, 1, 1, 1, 0, 0, 0x07, 0x80, 0, 0, 0x04, 0x40];
+            [make_box(b"meta", &meta), grid.to_vec()].concat()
+        };
+        let grid_offset = make_file(0).len() - 12;
+        let file = make_file(grid_offset as u32);
+
+        let image = read_primary_image(&mut Cursor::new(file))?.unwrap();
+        assert_eq!(image.size, Some((1920, 1080)));
+        assert_eq!(image.orientation, Orientation::from_exif(8).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflowing_item_extents() {
+        // two extents of 2^63 bytes, whose lengths wrap around to 0 when summed
+        let mut iloc = vec![1, 0, 0, 0, 0x88, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 2];
+        for _ in 0..2 {
+            iloc.extend_from_slice(&0u64.to_be_bytes());
+            iloc.extend_from_slice(&(1u64 << 63).to_be_bytes());
+        }
+        let meta = [vec![0, 0, 0, 0], make_box(b"iloc", &iloc)].concat();
+        let meta = HeifMeta::parse(&meta).unwrap();
+        let data = meta.read_item_data(&mut Cursor::new([0u8; 16]), 1).unwrap();
+        assert_eq!(data, None);
+    }
+}
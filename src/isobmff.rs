@@ -0,0 +1,167 @@
+//! Minimal ISOBMFF box reader for boxes that the `mp4` crate does not expose.
+
+use std::io;
+use std::io::Read;
+
+/// Upper bound for boxes that are loaded into memory.
+const MAX_BOX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Iterates over the boxes stored in `data`, yielding each type with its payload.
+///
+/// Iteration stops at the first malformed box.
+pub(crate) fn boxes(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        let (box_type, header_len, size) = parse_header(rest)?;
+        let size = match size {
+            0 => rest.len(),
+            size => usize::try_from(size).ok()?,
+        };
+        if size < header_len || size > rest.len() {
+            return None;
+        }
+        let payload = &rest[header_len..size];
+        rest = &rest[size..];
+        Some((box_type, payload))
+    })
+}
+
+/// Returns the payload of the first child box of the given type.
+pub(crate) fn find_box<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data)
+        .find(|(t, _)| t == box_type)
+        .map(|(_, payload)| payload)
+}
+
+/// Splits a full box payload into version, flags and the remaining data.
+pub(crate) fn full_box(payload: &[u8]) -> Option<(u8, u32, &[u8])> {
+    let header = payload.get(0..4)?;
+    let flags = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+    Some((header[0], flags, &payload[4..]))
+}
+
+/// Reads a top-level box with the given type into memory.
+///
+/// The stream is scanned from its current position, and the payload of the first matching box is
+/// returned. `mdat` and other large boxes are skipped without reading them. `None` is returned if
+/// the matching box is larger than [`MAX_BOX_SIZE`].
+pub(crate) fn read_top_level_box<R: io::Read + io::Seek>(
+    io: &mut R,
+    box_type: &[u8; 4],
+) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        let start = io.stream_position()?;
+        let mut header = [0u8; 16];
+        match io.read_exact(&mut header[..8]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut header_len = 8;
+        let mut size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as u64;
+        if size == 1 {
+            io.read_exact(&mut header[8..16])?;
+            header_len = 16;
+            size = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        }
+        let found = &header[4..8] == box_type;
+        if size == 0 {
+            // the box extends to the end of the stream
+            if !found {
+                return Ok(None);
+            }
+            let mut payload = Vec::new();
+            io.take(MAX_BOX_SIZE).read_to_end(&mut payload)?;
+            return Ok(Some(payload));
+        }
+        if size < header_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid box size",
+            ));
+        }
+        let payload_len = size - header_len;
+        if found {
+            if payload_len > MAX_BOX_SIZE {
+                return Ok(None);
+            }
+            let mut payload = vec![0; payload_len as usize];
+            io.read_exact(&mut payload)?;
+            return Ok(Some(payload));
+        }
+        // the size is at least the header length, so the next box always starts past this one
+        let next = start.checked_add(size).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "box size is out of range")
+        })?;
+        io.seek(io::SeekFrom::Start(next))?;
+    }
+}
+
+/// Returns the position of `child` relative to the start of `parent`, which must contain it.
+pub(crate) fn offset_in(parent: &[u8], child: &[u8]) -> u64 {
+    let offset = child.as_ptr() as usize - parent.as_ptr() as usize;
+    debug_assert!(offset + child.len() <= parent.len());
+    offset as u64
+}
+
+fn parse_header(data: &[u8]) -> Option<([u8; 4], usize, u64)> {
+    let size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?) as u64;
+    let box_type: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+    if size == 1 {
+        let size = u64::from_be_bytes(data.get(8..16)?.try_into().ok()?);
+        Some((box_type, 16, size))
+    } else {
+        Some((box_type, 8, size))
+    }
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_top_level_box, MAX_BOX_SIZE};
+    use crate::fixtures::make_box;
+    use std::io::Cursor;
+
+    /// Encodes a box header with a 64-bit size.
+    fn large_box_header(box_type: &[u8; 4], size: u64) -> Vec<u8> {
+        [&1u32.to_be_bytes()[..], box_type, &size.to_be_bytes()].concat()
+    }
+
+    #[test]
+    fn test_read_top_level_box() {
+        let data = [make_box(b"ftyp", b"heic"), make_box(b"meta", b"data")].concat();
+        let payload = read_top_level_box(&mut Cursor::new(data), b"meta").unwrap();
+        assert_eq!(payload.as_deref(), Some(&b"data"[..]));
+    }
+
+    #[test]
+    fn test_backward_box_size() {
+        // skipping the second box must not wrap around to the start of the stream
+        let data = [
+            make_box(b"free", &[0; 24]),
+            large_box_header(b"free", 0u64.wrapping_sub(32)),
+            make_box(b"meta", b"data"),
+        ]
+        .concat();
+        let error = read_top_level_box(&mut Cursor::new(data), b"meta").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_oversized_box() {
+        let data = large_box_header(b"moov", MAX_BOX_SIZE + 17);
+        let payload = read_top_level_box(&mut Cursor::new(data), b"moov").unwrap();
+        assert_eq!(payload, None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Minimal JPEG segment reader used to locate Exif data and read image dimensions without
+//! decoding.
+
+use std::io;
+
+const SOI: u8 = 0xD8;
+const SOS: u8 = 0xDA;
+const APP1: u8 = 0xE1;
+
+/// Dimensions of a JPEG image from its start of frame segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct JpegFrame {
+    pub width: u64,
+    pub height: u64,
+    /// Lossless JPEG, as used for the raw data of some camera formats, which image viewers
+    /// usually cannot display.
+    pub lossless: bool,
+}
+
+/// Reads the frame header of the JPEG image at `range`, or returns `None` if it is not a JPEG.
+pub(crate) fn read_frame<R: io::Read + io::Seek>(
+    io: &mut R,
+    range: std::ops::Range<u64>,
+) -> io::Result<Option<JpegFrame>> {
+    find_segment(io, range, |io, marker, _, _| {
+        // SOF0 to SOF15, except DHT, JPG and DAC, which share the range
+        if !(0xC0..=0xCF).contains(&marker) || matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Ok(None);
+        }
+        let mut header = [0u8; 5];
+        io.read_exact(&mut header)?;
+        Ok(Some(JpegFrame {
+            width: u16::from_be_bytes([header[3], header[4]]) as u64,
+            height: u16::from_be_bytes([header[1], header[2]]) as u64,
+            lossless: matches!(marker, 0xC3 | 0xC7 | 0xCB | 0xCF),
+        }))
+    })
+}
+
+/// Returns the position of the TIFF header of the Exif `APP1` segment of the JPEG image at
+/// `range`.
+pub(crate) fn find_exif<R: io::Read + io::Seek>(
+    io: &mut R,
+    range: std::ops::Range<u64>,
+) -> io::Result<Option<u64>> {
+    find_segment(io, range, |io, marker, position, len| {
+        if marker != APP1 || len < 6 {
+            return Ok(None);
+        }
+        let mut signature = [0u8; 6];
+        io.read_exact(&mut signature)?;
+        Ok((&signature == b"Exif\0\0").then_some(position + 6))
+    })
+}
+
+/// Calls `f` with the marker, payload position and payload length of every segment before the
+/// image data, until it returns a value. The stream is positioned at the payload when `f` is
+/// called.
+///
+/// A truncated image is treated like an image without the segment, so that a broken embedded
+/// preview doesn't prevent reading the others.
+fn find_segment<R, T, F>(io: &mut R, range: std::ops::Range<u64>, f: F) -> io::Result<Option<T>>
+where
+    R: io::Read + io::Seek,
+    F: FnMut(&mut R, u8, u64, u64) -> io::Result<Option<T>>,
+{
+    match find_segment_in(io, range, f) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        result => result,
+    }
+}
+
+fn find_segment_in<R, T, F>(
+    io: &mut R,
+    range: std::ops::Range<u64>,
+    mut f: F,
+) -> io::Result<Option<T>>
+where
+    R: io::Read + io::Seek,
+    F: FnMut(&mut R, u8, u64, u64) -> io::Result<Option<T>>,
+{
+    io.seek(io::SeekFrom::Start(range.start))?;
+    let mut soi = [0u8; 2];
+    if io.read_exact(&mut soi).is_err() || soi != [0xFF, SOI] {
+        return Ok(None);
+    }
+    let mut position = range.start + 2;
+    while position + 4 <= range.end {
+        io.seek(io::SeekFrom::Start(position))?;
+        let mut header = [0u8; 4];
+        io.read_exact(&mut header)?;
+        if header[0] != 0xFF {
+            return Ok(None);
+        }
+        let marker = header[1];
+        // fill bytes and markers without payload
+        if marker == 0xFF {
+            position += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            position += 2;
+            continue;
+        }
+        if marker == SOS {
+            return Ok(None);
+        }
+        let len = u16::from_be_bytes([header[2], header[3]]) as u64;
+        if len < 2 {
+            return Ok(None);
+        }
+        if let Some(value) = f(io, marker, position + 4, len - 2)? {
+            return Ok(Some(value));
+        }
+        position += 2 + len;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{find_exif, read_frame, JpegFrame};
+    use std::io::Cursor;
+
+    /// Builds a JPEG header with an `APP1` segment and a baseline frame, without image data.
+    pub(crate) fn make_jpeg(width: u16, height: u16, app1: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&(app1.len() as u16 + 2).to_be_bytes());
+        jpeg.extend_from_slice(app1);
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0, 11, 8]);
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.extend_from_slice(&[1, 1, 0x11, 0, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_read_frame() -> std::io::Result<()> {
+        let jpeg = make_jpeg(160, 120, b"Exif\0\0II*\0");
+        let len = jpeg.len() as u64;
+        let mut io = Cursor::new(jpeg);
+        assert_eq!(
+            read_frame(&mut io, 0..len)?,
+            Some(JpegFrame {
+                width: 160,
+                height: 120,
+                lossless: false
+            })
+        );
+        assert_eq!(find_exif(&mut io, 0..len)?, Some(12));
+        assert_eq!(read_frame(&mut Cursor::new(b"\x89PNG"), 0..4)?, None);
+        // truncated in the frame header
+        let jpeg = make_jpeg(160, 120, b"");
+        let truncated = &jpeg[..jpeg.len() - 8];
+        assert_eq!(read_frame(&mut Cursor::new(truncated), 0..len)?, None);
+        Ok(())
+    }
+}
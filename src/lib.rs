@@ -1,33 +1,131 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod avi_helper;
+mod bits;
+mod chapter;
+mod codec;
+mod date;
+mod detect;
+mod device;
+mod ebml;
 mod error;
 mod exif_helper;
+mod filename;
+#[cfg(test)]
+mod fixtures;
+mod heif_helper;
+mod isobmff;
+mod jpeg;
+mod location;
 mod mkv_helper;
 mod mp4_helper;
+mod orientation;
+mod png_helper;
+mod tag;
+mod thumbnail;
+mod tiff;
+mod track;
+mod ts_helper;
+mod xmp;
 
 #[cfg(feature = "mediainfo")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mediainfo")))]
 pub mod mediainfo;
 
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
+pub use avi_helper::extract_avi_metadata;
+pub use chapter::{Chapter, ChapterTitle};
+pub use codec::{Codec, CodecInfo};
+pub use date::{
+    BogusDate, CreationDate, CreationDateOptions, DateSource, DateTimeOffset, MediaDateTime,
+};
+pub use detect::{detect_container_type, DetectedContainer};
+pub use device::Device;
 pub use error::{Error, Result};
 pub use exif_helper::extract_exif_metadata;
-pub use mkv_helper::extract_mkv_metadata;
+pub use filename::parse_filename_date;
+pub use location::Location;
+pub use mkv_helper::{
+    extract_mkv_metadata, list_mkv_attachments, open_mkv_attachment, DocType, MatroskaAttachment,
+};
 pub use mp4_helper::extract_mp4_metadata;
+pub use orientation::Orientation;
+pub use png_helper::extract_png_metadata;
+pub use tag::{ItunesTags, MatroskaTag, MatroskaTarget, PngText, TagValue};
+pub use thumbnail::{
+    extract_file_thumbnails, extract_thumbnails, read_thumbnail, Thumbnail, ThumbnailSource,
+};
+pub use track::{
+    AudioDetails, FrameRate, SubtitleDetails, SubtitleFormat, TrackDetails, TrackInfo, TrackKind,
+    VideoDetails,
+};
+pub use ts_helper::extract_ts_metadata;
 
 /// Represents the extracted metadata for a media file.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct MetaData {
     pub width: u64,
     pub height: u64,
     pub creation_date: Option<SystemTime>,
+    /// Creation date as stored in the file, with information about its time zone.
+    /// [`MetaData::creation_date`] is the same point in time.
+    pub creation_datetime: Option<MediaDateTime>,
+    /// Playback duration. Always `None` for still images.
+    pub duration: Option<Duration>,
+    /// Transformation that must be applied to the coded `width` x `height` image to display it.
+    pub orientation: Orientation,
+    /// GPS location where the media was recorded.
+    pub location: Option<Location>,
+    /// Camera or phone that recorded the media and the software that processed it.
+    pub device: Option<Device>,
+    /// Video, audio and subtitle tracks of the container.
+    pub tracks: Vec<TrackInfo>,
+    /// QuickTime metadata keys from `moov/meta`, e.g. `com.apple.quicktime.creationdate` or
+    /// `com.apple.quicktime.content.identifier`. Empty for other containers.
+    pub quicktime_keys: BTreeMap<String, TagValue>,
+    /// iTunes-style tags like title, artist or cover art. Empty for containers other than MP4.
+    pub itunes_tags: ItunesTags,
+    /// Tags from the Matroska `Tags` element, e.g. `TITLE` or `DATE_RECORDED`. Empty for other
+    /// containers.
+    pub matroska_tags: Vec<MatroskaTag>,
+    /// Chapters of all Matroska editions or of the MP4 chapter track, ordered by edition and start.
+    pub chapters: Vec<Chapter>,
+    /// EBML `DocType` of Matroska and WebM files. `None` for other containers.
+    pub doc_type: Option<DocType>,
+    /// Bits per sample of PNG images, from the `IHDR` chunk. `None` for other containers.
+    pub bit_depth: Option<u8>,
+    /// Number of frames of animated PNG images. `None` for other containers and still images.
+    pub frame_count: Option<u64>,
+    /// Text entries of PNG images, in file order. Empty for other containers.
+    pub png_text: Vec<PngText>,
+}
+
+impl MetaData {
+    /// Returns the width as shown by a player, i.e. after applying [`MetaData::orientation`].
+    pub fn display_width(&self) -> u64 {
+        if self.orientation.swaps_dimensions() {
+            self.height
+        } else {
+            self.width
+        }
+    }
+
+    /// Returns the height as shown by a player, i.e. after applying [`MetaData::orientation`].
+    pub fn display_height(&self) -> u64 {
+        if self.orientation.swaps_dimensions() {
+            self.width
+        } else {
+            self.height
+        }
+    }
 }
 
 impl Display for MetaData {
@@ -51,19 +149,27 @@ impl Display for MetaData {
 ///
 /// This enum defines the container types that can be processed by the library. The `Exif` variant
 /// accepts a custom string to store file extensions for future use, enabling additional flexibility
-/// for Exif-based media.
-#[derive(Debug, PartialEq)]
+/// for Exif-based media. New container types can be added in minor releases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ContainerType {
     Mp4,
+    /// Matroska, including WebM.
     Mkv,
+    Avi,
+    /// MPEG transport stream, including AVCHD (`.mts`, `.m2ts`).
+    MpegTs,
+    /// PNG, including animated PNG.
+    Png,
     Exif(String),
 }
 
 /// Detects the container type of a media file based on its extension.
 ///
 /// This function determines the container type from file extension, which is required by the
-/// [`extract_metadata`] function. It can identify common types, including MP4, MKV, and Exif-based
-/// formats.
+/// [`extract_metadata`] function. It can identify common types, including MP4, MKV, WebM, AVI,
+/// MPEG-TS, PNG, and Exif-based formats. Prefer [`detect_container_type`] when the file content is
+/// available, as extensions can be missing or wrong.
 pub fn get_container_type<P: AsRef<Path>>(file_path: P) -> Result<ContainerType> {
     let file_extension = file_path
         .as_ref()
@@ -73,10 +179,13 @@ pub fn get_container_type<P: AsRef<Path>>(file_path: P) -> Result<ContainerType>
         .to_lowercase();
 
     match file_extension.as_str() {
-        "mp4" | "mov" => Ok(ContainerType::Mp4),
-        "mkv" => Ok(ContainerType::Mkv),
-        "jpg" | "jpeg" | "tiff" | "tif" | "webp" | "heif" | "heic" | "dng" | "cr2" | "cr3"
-        | "nef" | "arw" | "raf" | "rw2" | "orf" => Ok(ContainerType::Exif(file_extension)),
+        "mp4" | "mov" | "m4v" | "3gp" | "3g2" => Ok(ContainerType::Mp4),
+        "mkv" | "webm" => Ok(ContainerType::Mkv),
+        "avi" => Ok(ContainerType::Avi),
+        "ts" | "mts" | "m2ts" | "m2t" => Ok(ContainerType::MpegTs),
+        "png" | "apng" => Ok(ContainerType::Png),
+        "jpg" | "jpeg" | "tiff" | "tif" | "webp" | "heif" | "heic" | "avif" | "dng" | "cr2"
+        | "cr3" | "nef" | "arw" | "raf" | "rw2" | "orf" => Ok(ContainerType::Exif(file_extension)),
         _ => Err(Error::UnsupportedContainerType(file_extension)),
     }
 }
@@ -89,7 +198,20 @@ pub fn get_container_type<P: AsRef<Path>>(file_path: P) -> Result<ContainerType>
 #[cfg(feature = "mediainfo")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mediainfo")))]
 pub fn extract_combined_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaData> {
-    let result1 = crate::extract_file_metadata(&file_path);
+    extract_combined_metadata_with_options(file_path, &CreationDateOptions::default())
+}
+
+/// Same as [`extract_combined_metadata`], but selects the creation date according to `options`.
+///
+/// If the native extraction finds no acceptable creation date, e.g. because it was rejected with
+/// [`CreationDateOptions::reject_bogus`], the date is taken from the mediainfo tool.
+#[cfg(feature = "mediainfo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mediainfo")))]
+pub fn extract_combined_metadata_with_options<P: AsRef<Path>>(
+    file_path: P,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let result1 = crate::extract_file_metadata_with_options(&file_path, options);
     match &result1 {
         Ok(meta) => {
             if meta.height > 0 && meta.width > 0 && meta.creation_date.is_some() {
@@ -104,7 +226,7 @@ pub fn extract_combined_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaDat
             log::debug!("Failed to collect metadata for the file. Falling back to MediaInfo. Path: {}, Error: {e}", file_path.as_ref().to_string_lossy());
         }
     }
-    let result2 = crate::mediainfo::extract_metadata(&file_path);
+    let result2 = crate::mediainfo::extract_metadata_with_options(&file_path, options);
     if result1.is_err() {
         return result2;
     }
@@ -130,19 +252,49 @@ pub fn extract_combined_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaDat
         } else {
             meta2.creation_date
         },
+        creation_datetime: if meta1.creation_date.is_some() {
+            meta1.creation_datetime
+        } else {
+            meta2.creation_datetime
+        },
+        duration: meta1.duration.or(meta2.duration),
+        orientation: meta1.orientation,
+        location: meta1.location.or(meta2.location),
+        device: meta1.device.or(meta2.device),
+        tracks: if meta1.tracks.is_empty() {
+            meta2.tracks
+        } else {
+            meta1.tracks
+        },
+        quicktime_keys: meta1.quicktime_keys,
+        itunes_tags: meta1.itunes_tags,
+        matroska_tags: meta1.matroska_tags,
+        chapters: meta1.chapters,
+        doc_type: meta1.doc_type,
+        bit_depth: meta1.bit_depth,
+        frame_count: meta1.frame_count,
+        png_text: meta1.png_text,
     })
 }
 
 /// Extracts metadata from a file.
 ///
-/// This function opens a file using [BufReader], and then calls
-/// [`extract_metadata`].
+/// This function opens a file using [BufReader], detects its container type from the content
+/// (falling back to the file extension), and then calls [`extract_metadata`].
 pub fn extract_file_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaData> {
-    let container_type = get_container_type(&file_path)?;
+    extract_file_metadata_with_options(file_path, &CreationDateOptions::default())
+}
+
+/// Same as [`extract_file_metadata`], but selects the creation date according to `options`.
+pub fn extract_file_metadata_with_options<P: AsRef<Path>>(
+    file_path: P,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
     let file = File::open(&file_path)?;
     let size = file.metadata()?.len();
-    let reader = BufReader::new(file);
-    extract_metadata(reader, size, container_type)
+    let mut reader = BufReader::new(file);
+    let container_type = detect::detect_file_container_type(&mut reader, &file_path)?;
+    extract_metadata_with_options(reader, size, container_type, options)
 }
 
 /// Extracts metadata from a buffered stream.
@@ -150,16 +302,60 @@ pub fn extract_file_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaData> {
 /// This function is useful when user code has already opened the file. It avoids unnecessary
 /// additional file access, making metadata extraction more efficient.
 pub fn extract_metadata<R>(io: R, file_size: u64, container_type: ContainerType) -> Result<MetaData>
+where
+    R: io::BufRead + io::Seek,
+{
+    extract_metadata_with_options(
+        io,
+        file_size,
+        container_type,
+        &CreationDateOptions::default(),
+    )
+}
+
+fn extract_metadata_with_options<R>(
+    io: R,
+    file_size: u64,
+    container_type: ContainerType,
+    options: &CreationDateOptions,
+) -> Result<MetaData>
 where
     R: io::BufRead + io::Seek,
 {
     match container_type {
-        ContainerType::Mp4 => extract_mp4_metadata(io, file_size),
-        ContainerType::Mkv => extract_mkv_metadata(io),
-        ContainerType::Exif(extension) => extract_exif_metadata(io, extension),
+        ContainerType::Mp4 => mp4_helper::extract_mp4_metadata_with_options(io, file_size, options),
+        ContainerType::Mkv => mkv_helper::extract_mkv_metadata_with_options(io, options),
+        ContainerType::Avi => avi_helper::extract_avi_metadata_with_options(io, options),
+        ContainerType::MpegTs => ts_helper::extract_ts_metadata_with_options(io, options),
+        ContainerType::Png => png_helper::extract_png_metadata_with_options(io, options),
+        ContainerType::Exif(extension) => {
+            exif_helper::extract_exif_metadata_with_options(io, extension, options)
+        }
     }
 }
 
+/// Extracts metadata from a buffered stream of unknown type.
+///
+/// The container type is detected from the first bytes of the stream with
+/// [`detect_container_type`], so no file name is needed. This is useful for upload handlers or
+/// archive entries. If `file_size` is `None`, it is computed by seeking to the end of the stream.
+pub fn extract_stream_metadata<R>(mut io: R, file_size: Option<u64>) -> Result<MetaData>
+where
+    R: io::BufRead + io::Seek,
+{
+    let file_size = match file_size {
+        Some(file_size) => file_size,
+        None => {
+            let start = io.stream_position()?;
+            let end = io.seek(io::SeekFrom::End(0))?;
+            io.seek(io::SeekFrom::Start(start))?;
+            end
+        }
+    };
+    let detected = detect_container_type(&mut io)?;
+    extract_metadata(io, file_size, detected.container_type)
+}
+
 /// Extracts the creation date from a media file.
 ///
 /// This function attempts to retrieve the creation date of a media file using Rust's native
@@ -168,32 +364,91 @@ where
 ///
 /// Since it only extracts the creation date, this function is more efficient than
 /// [`extract_combined_metadata`], which gathers additional metadata fields.
-pub fn extract_file_creation_date<P: AsRef<Path>>(file_path: P) -> Result<SystemTime> {
-    let creation_date = extract_creation_date_native(&file_path);
+///
+/// Sources are tried in the order of [`DateSource::DEFAULT_ORDER`]. Use
+/// [`extract_file_creation_date_with_options`] to change it.
+pub fn extract_file_creation_date<P: AsRef<Path>>(file_path: P) -> Result<CreationDate> {
+    extract_file_creation_date_with_options(file_path, &CreationDateOptions::default())
+}
+
+/// Extracts the creation date from a media file, reading only the sources listed in `options`.
+///
+/// Among the dates found in the file, the one whose source comes first in
+/// [`CreationDateOptions::sources`] is returned. `mediainfo` is only consulted if no native source
+/// yields a date, and the file name and file system only if `mediainfo` does not yield one either.
+pub fn extract_file_creation_date_with_options<P: AsRef<Path>>(
+    file_path: P,
+    options: &CreationDateOptions,
+) -> Result<CreationDate> {
+    let creation_date = extract_creation_dates_native(&file_path)
+        .and_then(|dates| options.select(&dates).ok_or(Error::CreationDateNotFound));
 
     #[cfg(feature = "mediainfo")]
-    if creation_date.is_err() {
-        log::debug!("Failed to extract creation date for the file, failing back to MediaInfo. Path: {}, Error: {}",
-            file_path.as_ref().to_string_lossy(), creation_date.as_ref().unwrap_err().to_string());
-        if let Ok(meta) = mediainfo::extract_metadata(file_path) {
-            if let Some(creation_date) = meta.creation_date {
-                return Ok(creation_date);
+    if let Err(e) = &creation_date {
+        if options.sources.iter().any(DateSource::is_mediainfo) {
+            log::debug!("Failed to extract creation date for the file, failing back to MediaInfo. Path: {}, Error: {e}",
+                file_path.as_ref().to_string_lossy());
+            if let Ok(dates) = mediainfo::extract_creation_dates(&file_path) {
+                if let Some(creation_date) = options.select(&dates) {
+                    return Ok(creation_date);
+                }
             }
         }
     }
 
+    if creation_date.is_err() && file_path.as_ref().is_file() {
+        let dates = extract_creation_dates_fallback(&file_path, options);
+        if let Some(creation_date) = options.select(&dates) {
+            return Ok(creation_date);
+        }
+    }
+
     creation_date
 }
 
-fn extract_creation_date_native<P: AsRef<Path>>(file_path: P) -> Result<SystemTime> {
-    let container_type = get_container_type(&file_path)?;
+/// Returns the dates that are not read from metadata.
+fn extract_creation_dates_fallback<P: AsRef<Path>>(
+    file_path: P,
+    options: &CreationDateOptions,
+) -> Vec<CreationDate> {
+    let mut dates = Vec::new();
+    if options.sources.contains(&DateSource::FileName) {
+        if let Some(date) = parse_filename_date(&file_path, &options.filename_patterns) {
+            dates.push(CreationDate::new(date, DateSource::FileName));
+        }
+    }
+    if let Ok(metadata) = std::fs::metadata(&file_path) {
+        let times = [
+            (DateSource::FileSystemCreated, metadata.created()),
+            (DateSource::FileSystemModified, metadata.modified()),
+        ];
+        for (source, time) in times {
+            if !options.sources.contains(&source) {
+                continue;
+            }
+            if let Ok(time) = time {
+                dates.push(CreationDate::new(
+                    MediaDateTime::from_system_time(time),
+                    source,
+                ));
+            }
+        }
+    }
+    dates
+}
+
+fn extract_creation_dates_native<P: AsRef<Path>>(file_path: P) -> Result<Vec<CreationDate>> {
     let file = File::open(&file_path)?;
     let file_size = file.metadata()?.len();
-    let io = BufReader::new(file);
+    let mut io = BufReader::new(file);
+    let container_type = detect::detect_file_container_type(&mut io, &file_path)?;
     match container_type {
-        ContainerType::Mp4 => mp4_helper::extract_mp4_creation_date(io, file_size),
-        ContainerType::Mkv => mkv_helper::extract_mkv_creation_date(io),
-        ContainerType::Exif(_) => exif_helper::extract_exif_creation_date(io),
+        ContainerType::Mp4 => mp4_helper::extract_mp4_creation_dates(io, file_size),
+        ContainerType::Mkv => mkv_helper::extract_mkv_creation_dates(io),
+        ContainerType::Avi => avi_helper::extract_avi_creation_dates(io),
+        ContainerType::MpegTs => ts_helper::extract_ts_creation_dates(io),
+        ContainerType::Png => png_helper::extract_png_creation_dates(io),
+        ContainerType::Exif(_) => exif_helper::extract_exif_creation_dates(io),
     }
 }
 
@@ -0,0 +1,117 @@
+use std::time::SystemTime;
+
+/// Geographic location where the media was recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    /// Latitude in decimal degrees, positive values are north of the equator.
+    pub lat: f64,
+    /// Longitude in decimal degrees, positive values are east of the prime meridian.
+    pub lon: f64,
+    /// Altitude in meters above sea level.
+    pub alt: Option<f64>,
+    /// Time of the GPS fix, if recorded separately (EXIF `GPSDateStamp`/`GPSTimeStamp`).
+    pub timestamp: Option<SystemTime>,
+}
+
+impl Location {
+    /// Parses an ISO 6709 location string, as used by QuickTime and MediaInfo, e.g.
+    /// `+37.3317-122.0302+030.000/`.
+    ///
+    /// Degrees may also be written as `±DDMM.MMM` or `±DDMMSS.SS`.
+    pub fn from_iso6709(value: &str) -> Option<Self> {
+        let value = value.trim().trim_end_matches('/');
+        let mut parts = split_signed(value);
+        let lat = parse_iso6709_angle(parts.next()?, 2)?;
+        let lon = parse_iso6709_angle(parts.next()?, 3)?;
+        let alt = match parts.next() {
+            // altitude may be followed by a coordinate reference system, e.g. `CRSWGS_84`
+            Some(alt) => Some(
+                alt.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    .parse()
+                    .ok()?,
+            ),
+            None => None,
+        };
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return None;
+        }
+        Some(Self {
+            lat,
+            lon,
+            alt,
+            timestamp: None,
+        })
+    }
+}
+
+/// Splits a string like `+12.3-45.6+7` into `+12.3`, `-45.6`, `+7`.
+fn split_signed(value: &str) -> impl Iterator<Item = &str> {
+    let mut rest = value;
+    std::iter::from_fn(move || {
+        // the first character is the sign, which may be any character in malformed values
+        let sign_len = rest.chars().next()?.len_utf8();
+        let end = rest[sign_len..]
+            .find(['+', '-'])
+            .map(|pos| pos + sign_len)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        rest = tail;
+        Some(part)
+    })
+}
+
+/// Parses `±DD.DD`, `±DDMM.MM` or `±DDMMSS.SS`, where `degree_digits` is 2 for latitude and 3
+/// for longitude.
+fn parse_iso6709_angle(value: &str, degree_digits: usize) -> Option<f64> {
+    let (sign, digits) = if let Some(digits) = value.strip_prefix('+') {
+        (1.0, digits)
+    } else {
+        (-1.0, value.strip_prefix('-')?)
+    };
+    let integer_len = digits.find('.').unwrap_or(digits.len());
+    let number: f64 = digits.parse().ok()?;
+    let degrees = if integer_len == degree_digits {
+        number
+    } else if integer_len == degree_digits + 2 {
+        let degrees = (number / 100.0).trunc();
+        degrees + (number - degrees * 100.0) / 60.0
+    } else if integer_len == degree_digits + 4 {
+        let degrees = (number / 10000.0).trunc();
+        let minutes = ((number - degrees * 10000.0) / 100.0).trunc();
+        let seconds = number - degrees * 10000.0 - minutes * 100.0;
+        degrees + minutes / 60.0 + seconds / 3600.0
+    } else {
+        return None;
+    };
+    Some(sign * degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Location;
+
+    #[test]
+    fn test_iso6709() {
+        let location = Location::from_iso6709("+37.3317-122.0302+030.000/").unwrap();
+        assert_eq!(location.lat, 37.3317);
+        assert_eq!(location.lon, -122.0302);
+        assert_eq!(location.alt, Some(30.0));
+
+        let location = Location::from_iso6709("-33.8688+151.2093/").unwrap();
+        assert_eq!(
+            (location.lat, location.lon, location.alt),
+            (-33.8688, 151.2093, None)
+        );
+
+        let location = Location::from_iso6709("+4845.500+00220.000/").unwrap();
+        assert!((location.lat - 48.758333).abs() < 1e-6);
+        assert!((location.lon - 2.333333).abs() < 1e-6);
+
+        assert_eq!(Location::from_iso6709(""), None);
+        assert_eq!(Location::from_iso6709("+95.0+10.0/"), None);
+        // non-ASCII characters in place of the signs or digits
+        assert_eq!(Location::from_iso6709("é"), None);
+        assert_eq!(Location::from_iso6709("+37.3317é122.0302/"), None);
+        assert_eq!(Location::from_iso6709("+3é-122.0302/"), None);
+    }
+}
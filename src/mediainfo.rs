@@ -4,3 +4,4 @@
 
 mod helper;
 pub use helper::extract_metadata;
+pub(crate) use helper::{extract_creation_dates, extract_metadata_with_options};
@@ -1,10 +1,14 @@
+use crate::device::clean_value;
 use crate::error::MediainfoError;
-use crate::{Error, MetaData, Result};
-use chrono::prelude::*;
+use crate::{
+    AudioDetails, CodecInfo, CreationDate, CreationDateOptions, DateSource, DateTimeOffset, Device,
+    Error, FrameRate, Location, MediaDateTime, MetaData, Result, TrackDetails, TrackInfo,
+    TrackKind, VideoDetails,
+};
 use cmd_lib::run_fun;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use std::time::SystemTime;
+use std::time::Duration;
 
 static MEDIAINFO_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(|| which::which("mediainfo").ok());
 
@@ -17,41 +21,111 @@ static MEDIAINFO_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(|| which::which
 /// This function can provide comprehensive metadata, but usually it is slower than
 /// [`extract_file_metadata`](crate::extract_file_metadata).
 pub fn extract_metadata<P: AsRef<Path>>(file_path: P) -> Result<MetaData> {
+    extract_metadata_with_options(file_path, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_metadata_with_options<P: AsRef<Path>>(
+    file_path: P,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let json = run_mediainfo(file_path.as_ref())?;
+    extract_metadata_from_json(&json, options)
+}
+
+/// Extracts the creation dates reported by the `mediainfo` tool, most reliable first.
+pub(crate) fn extract_creation_dates<P: AsRef<Path>>(file_path: P) -> Result<Vec<CreationDate>> {
+    let json = run_mediainfo(file_path.as_ref())?;
+    let root: Root = serde_json::from_str(&json).map_err(MediainfoError::from)?;
+    for track in root.media.track {
+        if let Track::General(general) = track {
+            return Ok(get_creation_dates(&general));
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn run_mediainfo(file_path: &Path) -> Result<String> {
     let mediainfo = (*MEDIAINFO_PATH)
         .as_ref()
         .ok_or(MediainfoError::ToolNotFound)?;
-    let file_path = file_path.as_ref();
     if !file_path.exists() {
         return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
     }
 
     let result = run_fun!($mediainfo --Output=JSON $file_path)
         .map_err(|e| MediainfoError::CommandError(e.to_string()))?;
-    extract_metadata_from_json(&result)
+    Ok(result)
 }
 
-fn extract_metadata_from_json(json: &str) -> Result<MetaData> {
+fn extract_metadata_from_json(json: &str, options: &CreationDateOptions) -> Result<MetaData> {
     let root: Root = serde_json::from_str(json).map_err(MediainfoError::from)?;
     let mut metadata = MetaData {
         width: 0,
         height: 0,
         creation_date: None,
+        creation_datetime: None,
+        duration: None,
+        orientation: Default::default(),
+        location: None,
+        device: None,
+        tracks: Vec::new(),
+        quicktime_keys: Default::default(),
+        itunes_tags: Default::default(),
+        matroska_tags: Vec::new(),
+        chapters: Vec::new(),
+        doc_type: None,
+        bit_depth: None,
+        frame_count: None,
+        png_text: Vec::new(),
     };
     let mut is_media = false;
     for track in root.media.track {
         match track {
             Track::General(general) => {
-                if let Some(recorded_date) = general.recorded_date {
-                    metadata.creation_date = Some(parse_datetime(&recorded_date)?);
-                } else if let Some(encoded_date) = general.encoded_date {
-                    metadata.creation_date = Some(parse_datetime(&encoded_date)?);
+                metadata.duration = general.duration.as_deref().and_then(parse_duration);
+                metadata.location = general.xyz.as_deref().and_then(Location::from_iso6709);
+                metadata.device = Device {
+                    make: general.make.as_deref().and_then(clean_value),
+                    model: general.model.as_deref().and_then(clean_value),
+                    software: general.encoded_application.as_deref().and_then(clean_value),
+                    encoder: general.encoded_library.as_deref().and_then(clean_value),
+                    ..Default::default()
+                }
+                .non_empty();
+                if let Some(date) = options.select(&get_creation_dates(&general)) {
+                    metadata.creation_date = Some(date.date.to_system_time());
+                    metadata.creation_datetime = Some(date.date);
                 }
             }
             Track::Video(video) => {
                 metadata.width = video.width;
                 metadata.height = video.height;
+                let mut track = video.stream.track_info(TrackKind::Video);
+                track.details = TrackDetails::Video(VideoDetails {
+                    width: video.width,
+                    height: video.height,
+                    frame_rate: video.frame_rate(),
+                    alpha: false,
+                });
+                metadata.tracks.push(track);
                 is_media = true;
             }
+            Track::Audio(audio) => {
+                let mut track = audio.stream.track_info(TrackKind::Audio);
+                track.details = TrackDetails::Audio(AudioDetails {
+                    sample_rate: audio
+                        .sampling_rate
+                        .and_then(|rate| rate.parse::<f64>().ok())
+                        .map(|rate| rate.round() as u32),
+                    channels: audio.channels.and_then(|channels| channels.parse().ok()),
+                });
+                metadata.tracks.push(track);
+            }
+            Track::Text(text) => {
+                metadata
+                    .tracks
+                    .push(text.stream.track_info(TrackKind::Subtitle));
+            }
             Track::Image(image) => {
                 metadata.width = image.width;
                 metadata.height = image.height;
@@ -74,6 +148,8 @@ enum Track {
     General(GeneralTrack),
     Video(VideoTrack),
     Image(ImageTrack),
+    Audio(AudioTrack),
+    Text(TextTrack),
     #[serde(other)]
     Other,
 }
@@ -84,14 +160,106 @@ struct GeneralTrack {
     recorded_date: Option<String>,
     #[serde(rename = "Encoded_Date")]
     encoded_date: Option<String>,
+    #[serde(rename = "Duration")]
+    duration: Option<String>,
+    /// ISO 6709 location, e.g. `+37.3317-122.0302/`.
+    xyz: Option<String>,
+    #[serde(rename = "Make")]
+    make: Option<String>,
+    #[serde(rename = "Model")]
+    model: Option<String>,
+    #[serde(rename = "Encoded_Application")]
+    encoded_application: Option<String>,
+    #[serde(rename = "Encoded_Library")]
+    encoded_library: Option<String>,
 }
 
 #[derive(serde::Deserialize, Debug)]
 struct VideoTrack {
+    #[serde(flatten)]
+    stream: StreamProperties,
     #[serde(rename = "Width", deserialize_with = "parse_string_to_u64")]
     width: u64,
     #[serde(rename = "Height", deserialize_with = "parse_string_to_u64")]
     height: u64,
+    #[serde(rename = "FrameRate")]
+    frame_rate: Option<String>,
+    #[serde(rename = "FrameRate_Num")]
+    frame_rate_num: Option<String>,
+    #[serde(rename = "FrameRate_Den")]
+    frame_rate_den: Option<String>,
+    /// `CFR` or `VFR`.
+    #[serde(rename = "FrameRate_Mode")]
+    frame_rate_mode: Option<String>,
+}
+
+impl VideoTrack {
+    /// Returns the exact frame rate if mediainfo reports it as a fraction, otherwise the decimal
+    /// rate, e.g. `29.970`.
+    fn frame_rate(&self) -> Option<FrameRate> {
+        let variable = self.frame_rate_mode.as_deref() == Some("VFR");
+        let num = self
+            .frame_rate_num
+            .as_deref()
+            .and_then(|num| num.parse().ok());
+        let den = self
+            .frame_rate_den
+            .as_deref()
+            .and_then(|den| den.parse().ok());
+        if let (Some(num), Some(den)) = (num, den) {
+            return FrameRate::from_ratio(num, den, variable);
+        }
+        let fps = self.frame_rate.as_deref()?.parse().ok()?;
+        FrameRate::from_fps(fps, variable)
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct AudioTrack {
+    #[serde(flatten)]
+    stream: StreamProperties,
+    #[serde(rename = "SamplingRate")]
+    sampling_rate: Option<String>,
+    #[serde(rename = "Channels")]
+    channels: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct TextTrack {
+    #[serde(flatten)]
+    stream: StreamProperties,
+}
+
+/// Properties shared by video, audio and text tracks.
+#[derive(serde::Deserialize, Debug)]
+struct StreamProperties {
+    #[serde(rename = "Format")]
+    format: Option<String>,
+    #[serde(rename = "Format_Profile")]
+    format_profile: Option<String>,
+    #[serde(rename = "Language")]
+    language: Option<String>,
+    #[serde(rename = "Title")]
+    title: Option<String>,
+    #[serde(rename = "Default")]
+    default: Option<String>,
+    #[serde(rename = "Forced")]
+    forced: Option<String>,
+}
+
+impl StreamProperties {
+    fn track_info(&self, kind: TrackKind) -> TrackInfo {
+        let codec = CodecInfo::from_mediainfo_format(
+            self.format.as_deref().unwrap_or_default(),
+            self.format_profile.as_deref(),
+        );
+        let mut track = TrackInfo::new(kind, codec);
+        track.language = self.language.as_deref().and_then(clean_value);
+        track.name = self.title.as_deref().and_then(clean_value);
+        track.default = self.default.as_deref() != Some("No");
+        track.forced = self.forced.as_deref() == Some("Yes");
+        track
+    }
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -122,25 +290,75 @@ where
         .map_err(|_| Error::invalid_value(Unexpected::Str(s), &"int"))
 }
 
-fn parse_datetime(datetime: &str) -> Result<SystemTime> {
-    let datetime = datetime.trim_start_matches("UTC ").trim_end_matches(" UTC");
-    let format = if datetime.contains('.') {
-        "%Y-%m-%d %H:%M:%S%.f"
-    } else {
-        "%Y-%m-%d %H:%M:%S"
+/// Parses a duration in seconds, e.g. `30.526`.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    duration
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+/// Reads `Recorded_Date` and `Encoded_Date`, in this order. Unparsable dates are skipped.
+fn get_creation_dates(general: &GeneralTrack) -> Vec<CreationDate> {
+    let mut dates = Vec::new();
+    if let Some(date) = &general.recorded_date {
+        if let Ok(date) = parse_datetime(date) {
+            dates.push(CreationDate::new(date, DateSource::MediainfoRecordedDate));
+        }
+    }
+    if let Some(date) = &general.encoded_date {
+        if let Ok(date) = parse_datetime(date) {
+            dates.push(CreationDate::new(date, DateSource::MediainfoEncodedDate));
+        }
+    }
+    dates
+}
+
+/// Parses a mediainfo date, e.g. `2013-11-09 15:07:11 UTC`, `UTC 2013-11-09 15:07:11` or
+/// `2021-08-13 20:04:35+02:00`. Dates without a time zone are local time.
+fn parse_datetime(datetime: &str) -> Result<MediaDateTime> {
+    let datetime = datetime.trim();
+    let utc = datetime
+        .strip_prefix("UTC ")
+        .or_else(|| datetime.strip_suffix(" UTC"));
+    let parsed = match utc {
+        Some(utc) => MediaDateTime::parse_iso8601(utc)
+            .filter(|date| date.offset == DateTimeOffset::Floating)
+            .map(|date| MediaDateTime::utc(date.datetime)),
+        None => MediaDateTime::parse_iso8601(datetime),
     };
-    let datetime: DateTime<Utc> = NaiveDateTime::parse_from_str(datetime, format)
-        .map_err(|e| {
-            log::debug!("Failed to parse datetime '{datetime}': {e}");
-            Error::DateTimeParseError(datetime.to_string())
-        })?
-        .and_utc();
-    Ok(datetime.into())
+    parsed.ok_or_else(|| {
+        log::debug!("Failed to parse datetime '{datetime}'");
+        Error::DateTimeParseError(datetime.to_string())
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::MetaData;
+    use crate::{
+        AudioDetails, Codec, CodecInfo, CreationDateOptions, DateTimeOffset, FrameRate,
+        MediaDateTime, MetaData, TrackDetails, TrackInfo, TrackKind, VideoDetails,
+    };
+
+    fn track(kind: TrackKind, codec: Codec, id: &str) -> TrackInfo {
+        let codec = CodecInfo {
+            codec,
+            id: id.to_owned(),
+        };
+        TrackInfo::new(kind, codec)
+    }
+
+    fn video_track(codec: Codec, id: &str, width: u64, height: u64) -> TrackInfo {
+        TrackInfo {
+            details: TrackDetails::Video(VideoDetails {
+                width,
+                height,
+                frame_rate: None,
+                alpha: false,
+            }),
+            ..track(TrackKind::Video, codec, id)
+        }
+    }
 
     #[test]
     fn test_mp4() -> anyhow::Result<()> {
@@ -175,11 +393,15 @@ mod tests {
 }
 "#;
 
-        let metadata = super::extract_metadata_from_json(json_str)?;
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
         let expected = MetaData {
             width: 640,
             height: 360,
             creation_date: None,
+            duration: None,
+            tracks: vec![video_track(Codec::H264, "AVC", 640, 360)],
+            ..Default::default()
         };
         assert_eq!(metadata, expected);
         Ok(())
@@ -205,6 +427,7 @@ mod tests {
                 "TextCount": "1",
                 "FileExtension": "MTS",
                 "Recorded_Date": "2013-11-09 15:07:11 UTC",
+                "Duration": "20.021",
                 "...": "..."
             },
             {
@@ -223,6 +446,9 @@ mod tests {
                 "ID": "4352",
                 "MenuID": "1",
                 "Format": "AC-3",
+                "Channels": "2",
+                "SamplingRate": "48000",
+                "Language": "en",
                 "...": "..."
             },
             {
@@ -237,11 +463,27 @@ mod tests {
 }
 "#;
 
-        let metadata = super::extract_metadata_from_json(json_str)?;
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
         let expected = MetaData {
             width: 1280,
             height: 720,
             creation_date: Some(crate::parse_date("2013-11-09T15:07:11")),
+            creation_datetime: MediaDateTime::parse_iso8601("2013-11-09T15:07:11Z"),
+            duration: Some(std::time::Duration::from_millis(20021)),
+            tracks: vec![
+                video_track(Codec::H264, "AVC", 1280, 720),
+                TrackInfo {
+                    language: Some("en".to_owned()),
+                    details: TrackDetails::Audio(AudioDetails {
+                        sample_rate: Some(48000),
+                        channels: Some(2),
+                    }),
+                    ..track(TrackKind::Audio, Codec::Ac3, "AC-3")
+                },
+                track(TrackKind::Subtitle, Codec::Unknown, ""),
+            ],
+            ..Default::default()
         };
         assert_eq!(metadata, expected);
         Ok(())
@@ -287,13 +529,164 @@ mod tests {
 }
 "#;
 
-        let metadata = super::extract_metadata_from_json(json_str)?;
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
         let expected = MetaData {
             width: 1200,
             height: 800,
             creation_date: None,
+            duration: None,
+            ..Default::default()
+        };
+        assert_eq!(metadata, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_location() -> anyhow::Result<()> {
+        let json_str = r#"
+{
+    "media": {
+        "@ref": "IMG_0001.MOV",
+        "track": [
+            {
+                "@type": "General",
+                "FileExtension": "MOV",
+                "xyz": "+37.3317-122.0302+030.000/"
+            },
+            {
+                "@type": "Video",
+                "Width": "1920",
+                "Height": "1080"
+            }
+        ]
+    }
+}
+"#;
+
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
+        let expected = MetaData {
+            width: 1920,
+            height: 1080,
+            location: Some(crate::Location {
+                lat: 37.3317,
+                lon: -122.0302,
+                alt: Some(30.0),
+                timestamp: None,
+            }),
+            tracks: vec![video_track(Codec::Unknown, "", 1920, 1080)],
+            ..Default::default()
         };
         assert_eq!(metadata, expected);
         Ok(())
     }
+
+    #[test]
+    fn test_frame_rate() -> anyhow::Result<()> {
+        let json_str = r#"
+{
+    "media": {
+        "@ref": "PXL_20240101_120000000.mp4",
+        "track": [
+            {
+                "@type": "General",
+                "FileExtension": "mp4"
+            },
+            {
+                "@type": "Video",
+                "Format": "HEVC",
+                "Width": "1920",
+                "Height": "1080",
+                "FrameRate_Mode": "VFR",
+                "FrameRate": "29.970"
+            },
+            {
+                "@type": "Video",
+                "Format": "AVC",
+                "Width": "1920",
+                "Height": "1080",
+                "FrameRate_Mode": "CFR",
+                "FrameRate": "23.976",
+                "FrameRate_Num": "24000",
+                "FrameRate_Den": "1001"
+            }
+        ]
+    }
+}
+"#;
+
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
+        let frame_rates: Vec<_> = metadata
+            .tracks
+            .iter()
+            .map(|track| match &track.details {
+                TrackDetails::Video(video) => video.frame_rate,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            frame_rates,
+            [
+                Some(FrameRate {
+                    numerator: 30000,
+                    denominator: 1001,
+                    variable: true,
+                }),
+                Some(FrameRate {
+                    numerator: 24000,
+                    denominator: 1001,
+                    variable: false,
+                }),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_encoded_date() -> anyhow::Result<()> {
+        let json_str = r#"
+{
+    "media": {
+        "@ref": "MOV_0001.mp4",
+        "track": [
+            {
+                "@type": "General",
+                "FileExtension": "mp4",
+                "Recorded_Date": "2013-11-09 15:07:11 UTC",
+                "Encoded_Date": "0000-00-00 00:00:00"
+            },
+            {
+                "@type": "Video",
+                "Width": "1920",
+                "Height": "1080"
+            }
+        ]
+    }
+}
+"#;
+
+        let metadata =
+            super::extract_metadata_from_json(json_str, &CreationDateOptions::default())?;
+        assert_eq!(
+            metadata.creation_datetime.map(|date| date.to_string()),
+            Some("2013-11-09T15:07:11Z".to_owned())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_datetime() -> anyhow::Result<()> {
+        let date = super::parse_datetime("UTC 2013-11-09 15:07:11")?;
+        assert_eq!(date.offset, DateTimeOffset::Utc);
+        let date = super::parse_datetime("2013-11-09 15:07:11 UTC")?;
+        assert_eq!(date.to_string(), "2013-11-09T15:07:11Z");
+        let date = super::parse_datetime("2013-11-09 15:07:11+09:00")?;
+        assert_eq!(date.to_string(), "2013-11-09T15:07:11+09:00");
+        let date = super::parse_datetime("2015-07-16 13:34:48.620")?;
+        assert_eq!(date.offset, DateTimeOffset::Floating);
+        assert!(super::parse_datetime("2013-11-09").is_err());
+        Ok(())
+    }
 }
@@ -1,53 +1,465 @@
-use crate::{MetaData, Result};
+mod attachments;
+mod chapters;
+mod clusters;
+mod frame_size;
+
+use crate::device::clean_value;
+use crate::{
+    ebml, CodecInfo, CreationDate, CreationDateOptions, DateSource, Device, FrameRate, MatroskaTag,
+    MatroskaTarget, MediaDateTime, MetaData, Orientation, Result, TagValue, Thumbnail,
+    ThumbnailSource, TrackDetails, TrackInfo, TrackKind,
+};
 use matroska::Settings;
 use std::io;
-use std::time::SystemTime;
+use std::time::Duration;
+
+pub use attachments::{list_mkv_attachments, open_mkv_attachment, MatroskaAttachment};
+
+/// `DocType` of the EBML header, which tells Matroska and WebM files apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DocType {
+    /// `matroska` or `webm`.
+    pub name: String,
+    /// Version of the specification that the writer followed, 1 if missing.
+    pub version: u64,
+}
 
-/// Extracts metadata from an MKV (Matroska) container.
+/// Extracts metadata from an MKV (Matroska) or WebM container.
 ///
 /// This function uses the `Matroska` crate to retrieve metadata, such as video dimensions and creation date,
 /// from an MKV container.
 pub fn extract_mkv_metadata<R: io::Read + io::Seek>(io: R) -> Result<MetaData> {
-    let matroska = matroska::Matroska::open(io)?;
-    let video_track = matroska.video_tracks().next();
-    let (width, height) = if let Some(video_track) = video_track {
-        if let Settings::Video(video) = &video_track.settings {
-            (video.pixel_width, video.pixel_height)
-        } else {
-            (0, 0)
+    extract_mkv_metadata_with_options(io, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_mkv_metadata_with_options<R: io::Read + io::Seek>(
+    mut io: R,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let start = io.stream_position()?;
+    let doc_type = ebml::read_doc_type(&mut io)?.map(|(name, version)| DocType { name, version });
+    io.seek(io::SeekFrom::Start(start))?;
+    let matroska = open_matroska(&mut io)?;
+
+    let tags = convert_tags(&matroska);
+    let creation_date = options
+        .select(&get_creation_dates(&matroska, &tags))
+        .map(|date| date.date);
+
+    io.seek(io::SeekFrom::Start(start))?;
+    let track_entries = ebml::read_segment_child(&mut io, ebml::TRACKS)?.unwrap_or_default();
+    let orientation = get_video_orientation(&track_entries);
+    let alpha_tracks = get_alpha_tracks(&track_entries);
+    let mut tracks: Vec<TrackInfo> = matroska
+        .tracks
+        .iter()
+        .map(|track| {
+            let mut info = convert_track(track);
+            if let TrackDetails::Video(details) = &mut info.details {
+                details.alpha = alpha_tracks.contains(&track.number);
+            }
+            info
+        })
+        .collect();
+    io.seek(io::SeekFrom::Start(start))?;
+    complete_frame_sizes(&mut io, &matroska, &mut tracks)?;
+    let (width, height) = tracks
+        .iter()
+        .find_map(|track| match &track.details {
+            TrackDetails::Video(details) => Some((details.width, details.height)),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let duration = match matroska.info.duration {
+        Some(duration) => Some(duration),
+        None => {
+            io.seek(io::SeekFrom::Start(start))?;
+            get_cluster_duration(&mut io, &matroska)?
         }
-    } else {
-        (0, 0)
     };
-
-    let creation_date = matroska
-        .info
-        .date_utc
-        .as_ref()
-        .map(convert_mkv_time_to_system_time);
+    io.seek(io::SeekFrom::Start(start))?;
+    // chapters are optional, so a broken `Chapters` element doesn't fail the extraction
+    let chapters = ebml::read_segment_child(&mut io, ebml::CHAPTERS)
+        .ok()
+        .flatten()
+        .map(|data| chapters::parse_chapters(&data, duration))
+        .unwrap_or_default();
 
     Ok(MetaData {
         width,
         height,
-        creation_date,
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration,
+        orientation,
+        location: None,
+        device: get_device(&matroska, &tags),
+        tracks,
+        quicktime_keys: Default::default(),
+        itunes_tags: Default::default(),
+        matroska_tags: tags,
+        chapters,
+        doc_type,
+        bit_depth: None,
+        frame_count: None,
+        png_text: Vec::new(),
     })
 }
 
-pub(crate) fn extract_mkv_creation_date<R: io::Read + io::Seek>(io: R) -> Result<SystemTime> {
-    let matroska = matroska::Matroska::open(io)?;
+pub(crate) fn extract_mkv_creation_dates<R: io::Read + io::Seek>(
+    mut io: R,
+) -> Result<Vec<CreationDate>> {
+    let matroska = open_matroska(&mut io)?;
+    Ok(get_creation_dates(&matroska, &convert_tags(&matroska)))
+}
 
-    matroska
+/// Finds the cover art attachments, which are named like `cover.jpg` or `small_cover.png` by
+/// convention.
+pub(crate) fn find_mkv_thumbnails<R: io::Read + io::Seek>(io: &mut R) -> Result<Vec<Thumbnail>> {
+    let thumbnails = list_mkv_attachments(io)?
+        .into_iter()
+        .filter(|attachment| attachment.mime_type.starts_with("image/"))
+        .filter(|attachment| attachment.file_name.to_lowercase().contains("cover"))
+        .map(|attachment| {
+            let range = attachment.offset..attachment.offset + attachment.size;
+            Thumbnail::new(ThumbnailSource::MatroskaCover, &attachment.mime_type, range)
+        })
+        .collect();
+    Ok(thumbnails)
+}
+
+/// Opens the file with the `matroska` crate, only passing the headers before the first cluster
+/// for files written live.
+fn open_matroska<R: io::Read + io::Seek>(io: &mut R) -> Result<matroska::Matroska> {
+    let start = io.stream_position()?;
+    if let Some(head) = ebml::read_live_head(io)? {
+        return Ok(matroska::Matroska::open(io::Cursor::new(head))?);
+    }
+    io.seek(io::SeekFrom::Start(start))?;
+    Ok(matroska::Matroska::open(io)?)
+}
+
+/// Reads the `DateUTC` muxing date in UTC and the global `DATE_RECORDED` tag, in this order.
+fn get_creation_dates(matroska: &matroska::Matroska, tags: &[MatroskaTag]) -> Vec<CreationDate> {
+    let date_utc = matroska
         .info
         .date_utc
         .as_ref()
-        .map(convert_mkv_time_to_system_time)
-        .ok_or(crate::Error::CreationDateNotFound)
+        .map(|date| CreationDate::new(convert_mkv_time(date), DateSource::MatroskaDateUtc));
+    let date_recorded = get_date_recorded(tags)
+        .map(|date| CreationDate::new(date, DateSource::MatroskaDateRecorded));
+    date_utc.into_iter().chain(date_recorded).collect()
 }
 
-fn convert_mkv_time_to_system_time(mkv_time: &matroska::DateTime) -> SystemTime {
-    use chrono::{Duration, TimeZone, Utc};
-    // MKV creation time is based on seconds since 2001-01-01
+fn get_date_recorded(tags: &[MatroskaTag]) -> Option<MediaDateTime> {
+    tags.iter()
+        .filter(|tag| tag.target.is_global() && tag.name.eq_ignore_ascii_case("DATE_RECORDED"))
+        .find_map(|tag| parse_tag_date(tag.value.as_text()?))
+}
+
+/// Parses a Matroska tag date like `2021-08-13 18:04:35`, which is local time unless it has an
+/// offset. Dates without time are at midnight.
+fn parse_tag_date(value: &str) -> Option<MediaDateTime> {
+    use chrono::NaiveDate;
+    MediaDateTime::parse_iso8601(value).or_else(|| {
+        let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+        Some(MediaDateTime::floating(date.and_hms_opt(0, 0, 0)?))
+    })
+}
+
+fn convert_mkv_time(mkv_time: &matroska::DateTime) -> MediaDateTime {
+    use chrono::{Duration, NaiveDate};
+    // MKV creation time is based on nanoseconds since 2001-01-01 in UTC
     let mkv_time: i64 = mkv_time.clone().into();
-    let utc = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap() + Duration::nanoseconds(mkv_time);
-    utc.into()
+    let epoch = NaiveDate::from_ymd_opt(2001, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap();
+    MediaDateTime::utc(epoch + Duration::nanoseconds(mkv_time))
+}
+
+fn convert_track(track: &matroska::Track) -> TrackInfo {
+    use matroska::Tracktype;
+    let kind = match track.tracktype {
+        Tracktype::Video => TrackKind::Video,
+        Tracktype::Audio => TrackKind::Audio,
+        Tracktype::Subtitle => TrackKind::Subtitle,
+        _ => TrackKind::Other,
+    };
+    let mut info = TrackInfo::new(kind, CodecInfo::from_matroska_id(&track.codec_id));
+    match (&mut info.details, &track.settings) {
+        (TrackDetails::Video(details), Settings::Video(video)) => {
+            details.width = video.pixel_width;
+            details.height = video.pixel_height;
+            details.frame_rate = track
+                .default_duration
+                .and_then(|duration| FrameRate::from_fps(1.0 / duration.as_secs_f64(), false));
+        }
+        (TrackDetails::Audio(details), Settings::Audio(audio)) => {
+            details.sample_rate = Some(audio.sample_rate.round() as u32).filter(|rate| *rate > 0);
+            details.channels = Some(audio.channels as u32).filter(|channels| *channels > 0);
+        }
+        _ => {}
+    }
+    // English is the default when the language element is missing
+    let language = track
+        .language
+        .as_ref()
+        .map_or_else(|| "eng".to_owned(), |language| language.to_string());
+    info.language = (language != "und").then_some(language);
+    info.name = track.name.as_deref().and_then(clean_value);
+    info.enabled = track.enabled;
+    info.default = track.default;
+    info.forced = track.forced;
+    info
+}
+
+/// Flattens the simple tags of all `Tag` elements, each with the targets of its parent.
+fn convert_tags(matroska: &matroska::Matroska) -> Vec<MatroskaTag> {
+    matroska
+        .tags
+        .iter()
+        .flat_map(|tag| {
+            let target = tag.targets.as_ref().map(convert_target).unwrap_or_default();
+            tag.simple.iter().filter_map(move |simple| {
+                let value = match simple.value.as_ref()? {
+                    // ffmpeg pads some strings with NUL characters
+                    matroska::TagValue::String(text) => {
+                        TagValue::Text(text.trim_end_matches('\0').to_owned())
+                    }
+                    matroska::TagValue::Binary(data) => TagValue::Binary(data.clone()),
+                };
+                Some(MatroskaTag {
+                    name: simple.name.clone(),
+                    value,
+                    language: simple
+                        .language
+                        .as_ref()
+                        .map(|language| language.to_string()),
+                    target: target.clone(),
+                })
+            })
+        })
+        .collect()
+}
+
+fn convert_target(target: &matroska::Target) -> MatroskaTarget {
+    use matroska::TargetTypeValue;
+    // the element defaults to 50 when missing
+    let type_value = match target.target_type_value {
+        Some(TargetTypeValue::Collection) => Some(70),
+        Some(TargetTypeValue::Season) => Some(60),
+        Some(TargetTypeValue::Episode) | None => Some(50),
+        Some(TargetTypeValue::Part) => Some(40),
+        Some(TargetTypeValue::Chapter) => Some(30),
+        Some(TargetTypeValue::Scene) => Some(20),
+        Some(TargetTypeValue::Shot) => Some(10),
+        Some(TargetTypeValue::Unknown) => None,
+    };
+    MatroskaTarget {
+        type_value,
+        target_type: target.target_type.clone(),
+        track_uids: target.track_uids.clone(),
+        edition_uids: target.edition_uids.clone(),
+        chapter_uids: target.chapter_uids.clone(),
+        attachment_uids: target.attachment_uids.clone(),
+    }
+}
+
+/// Collects the device from global tags, which are written when converting from other containers,
+/// and the applications recorded in the segment info.
+fn get_device(matroska: &matroska::Matroska, tags: &[MatroskaTag]) -> Option<Device> {
+    let mut device = Device::default();
+    for tag in tags.iter().filter(|tag| tag.target.is_global()) {
+        let Some(value) = tag.value.as_text() else {
+            continue;
+        };
+        let field = match tag.name.to_ascii_uppercase().as_str() {
+            "MAKE" | "COM.APPLE.QUICKTIME.MAKE" | "COM.ANDROID.MANUFACTURER" => &mut device.make,
+            "MODEL" | "COM.APPLE.QUICKTIME.MODEL" | "COM.ANDROID.MODEL" => &mut device.model,
+            "COM.APPLE.QUICKTIME.SOFTWARE" | "COM.ANDROID.VERSION" => &mut device.software,
+            "ENCODER" => &mut device.encoder,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = clean_value(value);
+        }
+    }
+    if device.software.is_none() {
+        device.software = clean_value(&matroska.info.writing_app);
+    }
+    if device.encoder.is_none() {
+        device.encoder = clean_value(&matroska.info.muxing_app);
+    }
+    device.non_empty()
+}
+
+/// Reads the orientation of the first video track from its `Projection` element.
+///
+/// The `matroska` crate does not expose projection, so it is read from the raw `Tracks` element.
+fn get_video_orientation(track_entries: &[u8]) -> Orientation {
+    let roll = ebml::children(track_entries)
+        .filter(|(id, _)| *id == ebml::TRACK_ENTRY)
+        .find(|(_, entry)| {
+            ebml::children(entry)
+                .any(|(id, value)| id == ebml::TRACK_TYPE && ebml::read_uint(value) == 1)
+        })
+        .and_then(|(_, entry)| {
+            let (_, video) = ebml::children(entry).find(|(id, _)| *id == ebml::VIDEO)?;
+            let (_, projection) = ebml::children(video).find(|(id, _)| *id == ebml::PROJECTION)?;
+            let (_, roll) =
+                ebml::children(projection).find(|(id, _)| *id == ebml::PROJECTION_POSE_ROLL)?;
+            ebml::read_float(roll)
+        });
+
+    // the roll is a counter-clockwise rotation in degrees
+    roll.map(|roll| Orientation::default().rotate(-roll.round() as i64))
+        .unwrap_or_default()
+}
+
+/// Returns the numbers of the video tracks with an `AlphaMode` of 1, whose blocks carry the alpha
+/// channel as additional data, like transparent VP8 and VP9 in WebM.
+fn get_alpha_tracks(track_entries: &[u8]) -> Vec<u64> {
+    ebml::children(track_entries)
+        .filter(|(id, _)| *id == ebml::TRACK_ENTRY)
+        .filter_map(|(_, entry)| {
+            let (_, number) = ebml::children(entry).find(|(id, _)| *id == ebml::TRACK_NUMBER)?;
+            let (_, video) = ebml::children(entry).find(|(id, _)| *id == ebml::VIDEO)?;
+            let (_, alpha_mode) = ebml::children(video).find(|(id, _)| *id == ebml::ALPHA_MODE)?;
+            (ebml::read_uint(alpha_mode) == 1).then(|| ebml::read_uint(number))
+        })
+        .collect()
+}
+
+/// Fills in the size of video tracks without `PixelWidth` and `PixelHeight` from the codec
+/// private data or the first key frame, which is supported for VP8, VP9 and AV1.
+fn complete_frame_sizes<R: io::Read + io::Seek>(
+    io: &mut R,
+    matroska: &matroska::Matroska,
+    tracks: &mut [TrackInfo],
+) -> Result<()> {
+    let mut first_cluster = None;
+    for (track, info) in matroska.tracks.iter().zip(tracks) {
+        let TrackDetails::Video(details) = &mut info.details else {
+            continue;
+        };
+        if details.width > 0 && details.height > 0 {
+            continue;
+        }
+        let mut size = track
+            .codec_private
+            .as_deref()
+            .and_then(|data| frame_size::from_codec_private(&track.codec_id, data));
+        if size.is_none() {
+            if first_cluster.is_none() {
+                first_cluster = Some(clusters::read_first_cluster(io)?.unwrap_or_default());
+            }
+            size = clusters::blocks(first_cluster.as_deref().unwrap_or_default())
+                .iter()
+                .filter(|block| block.track == track.number && block.key_frame)
+                .find_map(|block| frame_size::from_key_frame(&track.codec_id, block.frame?));
+        }
+        if let Some((width, height)) = size {
+            details.width = width;
+            details.height = height;
+        }
+    }
+    Ok(())
+}
+
+/// Computes the duration from the blocks of the first and the last cluster, for files that were
+/// written live and lack the duration in `Info`, like browser screen recordings. The end of the
+/// last block is known from its `BlockDuration` or the `DefaultDuration` of its track.
+fn get_cluster_duration<R: io::Read + io::Seek>(
+    io: &mut R,
+    matroska: &matroska::Matroska,
+) -> Result<Option<Duration>> {
+    let start = io.stream_position()?;
+    let timestamp_scale = ebml::read_segment_child(io, ebml::INFO)?
+        .and_then(|info| {
+            ebml::children(&info)
+                .find(|(id, _)| *id == ebml::TIMESTAMP_SCALE)
+                .map(|(_, value)| ebml::read_uint(value))
+        })
+        // a scale of 0 is invalid
+        .filter(|timestamp_scale| *timestamp_scale != 0)
+        .unwrap_or(1_000_000);
+    io.seek(io::SeekFrom::Start(start))?;
+    let Some(first_cluster) = clusters::read_first_cluster(io)? else {
+        return Ok(None);
+    };
+    let Some(last_cluster) = clusters::read_last_cluster(io)? else {
+        return Ok(None);
+    };
+
+    let Some(first) = clusters::blocks(&first_cluster)
+        .iter()
+        .map(|block| block.timestamp)
+        .min()
+    else {
+        return Ok(None);
+    };
+    let end = clusters::blocks(&last_cluster)
+        .iter()
+        .filter_map(|block| {
+            let default_duration = matroska
+                .tracks
+                .iter()
+                .find(|track| track.number == block.track)
+                .and_then(|track| track.default_duration)
+                .map_or(0, |duration| duration.as_nanos() / timestamp_scale as u128);
+            let duration = block.duration.map_or(default_duration, u128::from);
+            block.timestamp.checked_add(i64::try_from(duration).ok()?)
+        })
+        .max();
+    Ok(end
+        .filter(|end| *end > first)
+        .and_then(|end| u64::try_from(end.checked_sub(first)?).ok())
+        .and_then(|ticks| ticks.checked_mul(timestamp_scale))
+        .map(Duration::from_nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_date_recorded, parse_tag_date};
+    use crate::fixtures::tag;
+    use crate::{MatroskaTag, MatroskaTarget};
+
+    #[test]
+    fn test_parse_tag_date() {
+        let parse = |value| parse_tag_date(value).map(|date| date.to_string());
+        assert_eq!(
+            parse("2021-08-13 18:04:35").as_deref(),
+            Some("2021-08-13T18:04:35")
+        );
+        assert_eq!(
+            parse("2021-08-13T18:04:35+02:00").as_deref(),
+            Some("2021-08-13T18:04:35+02:00")
+        );
+        assert_eq!(parse("2021-08-13").as_deref(), Some("2021-08-13T00:00:00"));
+        assert_eq!(parse("2021"), None);
+    }
+
+    #[test]
+    fn test_date_recorded() {
+        let tags = [
+            tag("DATE_RECORDED", "2020-01-01 10:00:00", vec![1]),
+            tag("TITLE", "Holiday", vec![]),
+            MatroskaTag {
+                target: MatroskaTarget {
+                    type_value: Some(70),
+                    ..Default::default()
+                },
+                ..tag("DATE_RECORDED", "2019-05-01 12:00:00", vec![])
+            },
+            tag("DATE_RECORDED", "2021-08-13 18:04:35", vec![]),
+        ];
+        assert_eq!(
+            get_date_recorded(&tags)
+                .map(|date| date.to_string())
+                .as_deref(),
+            Some("2021-08-13T18:04:35")
+        );
+        assert_eq!(get_date_recorded(&tags[..3]), None);
+    }
 }
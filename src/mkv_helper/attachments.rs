@@ -0,0 +1,176 @@
+//! Files attached to a Matroska segment, e.g. cover images or fonts.
+//!
+//! The `matroska` crate loads the data of every attachment into memory, so the `Attachments`
+//! element is scanned directly and the data is only located.
+
+use crate::ebml::{self, read_element_header};
+use crate::Result;
+use std::io;
+
+/// Upper bound for attachment fields other than the data that are loaded into memory.
+const MAX_FIELD_SIZE: u64 = 64 * 1024;
+
+/// File attached to a Matroska segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatroskaAttachment {
+    pub uid: u64,
+    pub file_name: String,
+    /// Media type of the file, e.g. `image/jpeg` or `font/ttf`.
+    pub mime_type: String,
+    pub description: Option<String>,
+    /// Position of the file data in the stream.
+    pub offset: u64,
+    /// Size of the file data in bytes.
+    pub size: u64,
+}
+
+/// Lists the files attached to an MKV (Matroska) container without reading their data.
+///
+/// The stream must be positioned at the start of the file.
+pub fn list_mkv_attachments<R: io::Read + io::Seek>(mut io: R) -> Result<Vec<MatroskaAttachment>> {
+    let Some((position, size)) = ebml::find_segment_child(&mut io, ebml::ATTACHMENTS)? else {
+        return Ok(Vec::new());
+    };
+    let mut attachments = Vec::new();
+    for_each_child(&mut io, position, size, |io, id, position, size| {
+        if id == ebml::ATTACHED_FILE {
+            attachments.push(read_attached_file(io, position, size)?);
+        }
+        Ok(())
+    })?;
+    Ok(attachments)
+}
+
+/// Returns a reader over the data of an attachment listed by [`list_mkv_attachments`] for the
+/// same stream.
+pub fn open_mkv_attachment<R: io::Read + io::Seek>(
+    mut io: R,
+    attachment: &MatroskaAttachment,
+) -> Result<io::Take<R>> {
+    io.seek(io::SeekFrom::Start(attachment.offset))?;
+    Ok(io.take(attachment.size))
+}
+
+fn read_attached_file<R: io::Read + io::Seek>(
+    io: &mut R,
+    position: u64,
+    size: u64,
+) -> io::Result<MatroskaAttachment> {
+    let mut attachment = MatroskaAttachment {
+        uid: 0,
+        file_name: String::new(),
+        mime_type: String::new(),
+        description: None,
+        offset: 0,
+        size: 0,
+    };
+    for_each_child(io, position, size, |io, id, position, size| {
+        if id == ebml::FILE_DATA {
+            attachment.offset = position;
+            attachment.size = size;
+            return Ok(());
+        }
+        if size > MAX_FIELD_SIZE {
+            return Ok(());
+        }
+        let mut payload = vec![0; size as usize];
+        io.read_exact(&mut payload)?;
+        match id {
+            ebml::FILE_UID => attachment.uid = ebml::read_uint(&payload),
+            ebml::FILE_NAME => attachment.file_name = ebml::read_string(&payload),
+            ebml::FILE_MIME_TYPE => attachment.mime_type = ebml::read_string(&payload),
+            ebml::FILE_DESCRIPTION => attachment.description = Some(ebml::read_string(&payload)),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(attachment)
+}
+
+/// Calls `f` with the id, payload position and size of every child of the element whose payload
+/// is at `position`. The stream is positioned at the payload when `f` is called.
+fn for_each_child<R, F>(io: &mut R, position: u64, size: u64, mut f: F) -> io::Result<()>
+where
+    R: io::Read + io::Seek,
+    F: FnMut(&mut R, u32, u64, u64) -> io::Result<()>,
+{
+    let end = position.saturating_add(size);
+    let mut position = position;
+    while position < end {
+        io.seek(io::SeekFrom::Start(position))?;
+        let header = match read_element_header(io) {
+            Ok(header) => header,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let Some(size) = header.size else {
+            break;
+        };
+        let payload = position + header.header_len;
+        f(io, header.id, payload, size)?;
+        position = payload + size;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_mkv_attachments, open_mkv_attachment, MatroskaAttachment};
+    use crate::ebml;
+    use crate::fixtures::ebml::{element, uint};
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_attachments() -> crate::Result<()> {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xD9];
+        let attachments = [
+            element(
+                ebml::ATTACHED_FILE,
+                &[
+                    element(ebml::FILE_NAME, b"cover.jpg"),
+                    element(ebml::FILE_MIME_TYPE, b"image/jpeg"),
+                    element(ebml::FILE_DATA, &jpeg),
+                    uint(ebml::FILE_UID, 42),
+                ]
+                .concat(),
+            ),
+            element(
+                ebml::ATTACHED_FILE,
+                &[
+                    element(ebml::FILE_DESCRIPTION, b"Subtitle font"),
+                    element(ebml::FILE_NAME, b"font.ttf"),
+                    element(ebml::FILE_MIME_TYPE, b"font/ttf"),
+                    element(ebml::FILE_DATA, b"\0\x01\0\0"),
+                    uint(ebml::FILE_UID, 7),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let header = element(ebml::EBML_HEADER, &element(ebml::DOC_TYPE, b"matroska"));
+        let segment = element(ebml::SEGMENT, &element(ebml::ATTACHMENTS, &attachments));
+        let file = [header, segment].concat();
+
+        let attachments = list_mkv_attachments(Cursor::new(&file))?;
+        assert_eq!(attachments.len(), 2);
+        let cover = &attachments[0];
+        assert_eq!(
+            cover,
+            &MatroskaAttachment {
+                uid: 42,
+                file_name: "cover.jpg".to_owned(),
+                mime_type: "image/jpeg".to_owned(),
+                description: None,
+                offset: cover.offset,
+                size: 4,
+            }
+        );
+        assert_eq!(attachments[1].uid, 7);
+        assert_eq!(attachments[1].description.as_deref(), Some("Subtitle font"));
+
+        let mut data = Vec::new();
+        open_mkv_attachment(Cursor::new(&file), cover)?.read_to_end(&mut data)?;
+        assert_eq!(data, jpeg);
+        Ok(())
+    }
+}
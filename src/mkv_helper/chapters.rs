@@ -0,0 +1,180 @@
+//! Chapters from the Matroska `Chapters` element.
+//!
+//! The `matroska` crate drops nested chapter atoms, so the element is parsed directly.
+
+use crate::chapter::fill_end_times;
+use crate::ebml::{self, read_uint};
+use crate::{Chapter, ChapterTitle};
+use std::time::Duration;
+
+/// Parses the chapters of all editions. `duration` is the end of the last chapter of every
+/// edition that has no end time.
+pub(crate) fn parse_chapters(chapters: &[u8], duration: Option<Duration>) -> Vec<Chapter> {
+    ebml::children(chapters)
+        .filter(|(id, _)| *id == ebml::EDITION_ENTRY)
+        .enumerate()
+        .flat_map(|(edition, (_, entry))| {
+            let hidden = ebml::children(entry)
+                .any(|(id, value)| id == ebml::EDITION_FLAG_HIDDEN && read_uint(value) != 0);
+            parse_atoms(entry, edition, hidden, duration)
+        })
+        .collect()
+}
+
+/// Parses the chapter atoms of an edition or a parent atom that ends at `end`.
+fn parse_atoms(parent: &[u8], edition: usize, hidden: bool, end: Option<Duration>) -> Vec<Chapter> {
+    let atoms: Vec<_> = ebml::children(parent)
+        .filter(|(id, _)| *id == ebml::CHAPTER_ATOM)
+        .map(|(_, atom)| atom)
+        .collect();
+    let mut chapters: Vec<_> = atoms
+        .iter()
+        .map(|atom| parse_atom(atom, edition, hidden))
+        .collect();
+    // disabled chapters are skipped by players, but still end the previous chapter
+    fill_end_times(&mut chapters, end);
+    chapters
+        .into_iter()
+        .zip(atoms)
+        .filter(|(_, atom)| is_enabled(atom))
+        .map(|(mut chapter, atom)| {
+            chapter.children = parse_atoms(atom, edition, chapter.hidden, chapter.end);
+            chapter
+        })
+        .collect()
+}
+
+fn parse_atom(atom: &[u8], edition: usize, hidden: bool) -> Chapter {
+    let mut chapter = Chapter {
+        start: Duration::ZERO,
+        end: None,
+        titles: Vec::new(),
+        edition,
+        hidden,
+        children: Vec::new(),
+    };
+    for (id, value) in ebml::children(atom) {
+        // chapter times are in nanoseconds, independent of the timestamp scale
+        match id {
+            ebml::CHAPTER_TIME_START => chapter.start = Duration::from_nanos(read_uint(value)),
+            ebml::CHAPTER_TIME_END => chapter.end = Some(Duration::from_nanos(read_uint(value))),
+            ebml::CHAPTER_FLAG_HIDDEN => chapter.hidden |= read_uint(value) != 0,
+            ebml::CHAPTER_DISPLAY => chapter.titles.extend(parse_display(value)),
+            _ => {}
+        }
+    }
+    chapter
+}
+
+fn is_enabled(atom: &[u8]) -> bool {
+    !ebml::children(atom)
+        .any(|(id, value)| id == ebml::CHAPTER_FLAG_ENABLED && read_uint(value) == 0)
+}
+
+fn parse_display(display: &[u8]) -> Option<ChapterTitle> {
+    let mut title = None;
+    let mut language = None;
+    let mut language_bcp47 = None;
+    for (id, value) in ebml::children(display) {
+        match id {
+            ebml::CHAP_STRING => title = Some(ebml::read_string(value)),
+            ebml::CHAP_LANGUAGE if language.is_none() => language = Some(ebml::read_string(value)),
+            ebml::CHAP_LANGUAGE_BCP47 if language_bcp47.is_none() => {
+                language_bcp47 = Some(ebml::read_string(value))
+            }
+            _ => {}
+        }
+    }
+    // the BCP 47 tag takes precedence, English is the default when both are missing
+    let language = language_bcp47
+        .or(language)
+        .unwrap_or_else(|| "eng".to_owned());
+    Some(ChapterTitle {
+        title: title?,
+        language: (language != "und").then_some(language),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_chapters;
+    use crate::ebml;
+    use crate::fixtures::ebml::{element, uint};
+    use std::time::Duration;
+
+    fn display(title: &str, language: Option<&str>) -> Vec<u8> {
+        let mut display = element(ebml::CHAP_STRING, title.as_bytes());
+        if let Some(language) = language {
+            display.extend(element(ebml::CHAP_LANGUAGE, language.as_bytes()));
+        }
+        element(ebml::CHAPTER_DISPLAY, &display)
+    }
+
+    fn atom(start_ms: u64, children: &[Vec<u8>]) -> Vec<u8> {
+        let start = uint(ebml::CHAPTER_TIME_START, start_ms * 1_000_000);
+        element(
+            ebml::CHAPTER_ATOM,
+            &[&[start][..], children].concat().concat(),
+        )
+    }
+
+    #[test]
+    fn test_parse_chapters() {
+        let edition = [
+            atom(
+                0,
+                &[
+                    display("Intro", None),
+                    display("Einleitung", Some("ger")),
+                    atom(0, &[display("Opening", None)]),
+                    atom(2000, &[display("Credits", None)]),
+                ],
+            ),
+            atom(5000, &[display("Main", None)]),
+            atom(
+                6000,
+                &[
+                    display("Deleted", None),
+                    uint(ebml::CHAPTER_FLAG_ENABLED, 0),
+                ],
+            ),
+        ]
+        .concat();
+        let hidden_edition = [
+            uint(ebml::EDITION_FLAG_HIDDEN, 1),
+            atom(1000, &[display("Director's cut", Some("und"))]),
+        ]
+        .concat();
+        let chapters = [
+            element(ebml::EDITION_ENTRY, &edition),
+            element(ebml::EDITION_ENTRY, &hidden_edition),
+        ]
+        .concat();
+
+        let chapters = parse_chapters(&chapters, Some(Duration::from_secs(10)));
+        assert_eq!(chapters.len(), 3);
+
+        let intro = &chapters[0];
+        assert_eq!(intro.title(), Some("Intro"));
+        assert_eq!(intro.titles[0].language.as_deref(), Some("eng"));
+        assert_eq!(intro.titles[1].title, "Einleitung");
+        assert_eq!(intro.titles[1].language.as_deref(), Some("ger"));
+        assert_eq!(intro.end, Some(Duration::from_secs(5)));
+        assert_eq!(intro.children.len(), 2);
+        assert_eq!(intro.children[0].end, Some(Duration::from_secs(2)));
+        assert_eq!(intro.children[1].title(), Some("Credits"));
+        assert_eq!(intro.children[1].end, Some(Duration::from_secs(5)));
+
+        let main = &chapters[1];
+        assert_eq!(main.title(), Some("Main"));
+        assert_eq!(main.start, Duration::from_secs(5));
+        // the disabled chapter is dropped, but still ends the main chapter
+        assert_eq!(main.end, Some(Duration::from_secs(6)));
+        assert_eq!((main.edition, main.hidden), (0, false));
+
+        let cut = &chapters[2];
+        assert_eq!((cut.edition, cut.hidden), (1, true));
+        assert_eq!(cut.titles[0].language, None);
+        assert_eq!(cut.end, Some(Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,184 @@
+//! Reading of the blocks in the first and the last cluster, which is needed for files written
+//! live, like WebM screen recordings, that lack the duration or the frame size in their headers.
+
+use crate::ebml;
+use std::io::{self, Read, Seek};
+
+/// Amount of data read from the start of the first cluster.
+const FIRST_CLUSTER_SIZE: u64 = 1024 * 1024;
+/// Amount of data at the end of the file that is searched for the start of the last cluster.
+const TAIL_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A frame or a group of laced frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Block<'a> {
+    pub track: u64,
+    /// Start in timestamp ticks, i.e. in units of the `TimestampScale`.
+    pub timestamp: i64,
+    /// Duration in ticks from the `BlockDuration` of a block group.
+    pub duration: Option<u64>,
+    pub key_frame: bool,
+    /// Data of a single frame, `None` for laced frames.
+    pub frame: Option<&'a [u8]>,
+}
+
+/// Reads the beginning of the first cluster. The stream must be positioned at the start of the
+/// file.
+pub(super) fn read_first_cluster<R: Read + Seek>(io: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let Some((position, size)) = ebml::find_segment_child(io, ebml::CLUSTER)? else {
+        return Ok(None);
+    };
+    io.seek(io::SeekFrom::Start(position))?;
+    let mut cluster = Vec::new();
+    io.take(size.min(FIRST_CLUSTER_SIZE))
+        .read_to_end(&mut cluster)?;
+    Ok(Some(cluster))
+}
+
+/// Searches the end of the file for the start of the last cluster and reads it up to the end of
+/// the file.
+pub(super) fn read_last_cluster<R: Read + Seek>(io: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let len = io.seek(io::SeekFrom::End(0))?;
+    io.seek(io::SeekFrom::Start(len.saturating_sub(TAIL_SIZE)))?;
+    let mut tail = Vec::new();
+    io.take(TAIL_SIZE).read_to_end(&mut tail)?;
+
+    let id = ebml::CLUSTER.to_be_bytes();
+    let cluster = tail
+        .windows(id.len())
+        .enumerate()
+        .rev()
+        .filter(|(_, window)| *window == id)
+        .find_map(|(offset, _)| {
+            let (element_id, payload) = ebml::children(&tail[offset..]).next()?;
+            // the cluster timestamp is expected first, which rules out random matches
+            let (child_id, _) = ebml::children(payload).next()?;
+            (element_id == ebml::CLUSTER && child_id == ebml::TIMESTAMP).then_some(payload)
+        });
+    Ok(cluster.map(|cluster| cluster.to_vec()))
+}
+
+/// Returns the blocks of a cluster payload, which may be truncated.
+pub(super) fn blocks(cluster: &[u8]) -> Vec<Block<'_>> {
+    let mut cluster_timestamp = 0;
+    let mut blocks = Vec::new();
+    for (id, payload) in ebml::children(cluster) {
+        match id {
+            ebml::TIMESTAMP => cluster_timestamp = ebml::read_uint(payload) as i64,
+            ebml::SIMPLE_BLOCK => {
+                blocks.extend(parse_block(payload, cluster_timestamp));
+            }
+            ebml::BLOCK_GROUP => {
+                let mut block = None;
+                let mut duration = None;
+                let mut referenced = false;
+                for (id, payload) in ebml::children(payload) {
+                    match id {
+                        ebml::BLOCK => block = parse_block(payload, cluster_timestamp),
+                        ebml::BLOCK_DURATION => duration = Some(ebml::read_uint(payload)),
+                        ebml::REFERENCE_BLOCK => referenced = true,
+                        _ => {}
+                    }
+                }
+                if let Some(mut block) = block {
+                    // blocks without references to other blocks are key frames
+                    block.duration = duration;
+                    block.key_frame = !referenced;
+                    blocks.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Parses the track number, the timestamp relative to the cluster and the flags of a block.
+fn parse_block(payload: &[u8], cluster_timestamp: i64) -> Option<Block<'_>> {
+    let mut cursor = io::Cursor::new(payload);
+    let (track, _) = ebml::read_vint(&mut cursor, true).ok()?;
+    let start = cursor.position() as usize;
+    let header = payload.get(start..start + 3)?;
+    let timestamp = i16::from_be_bytes([header[0], header[1]]);
+    // frames are laced if either of the lacing bits is set
+    let laced = header[2] & 0x06 != 0;
+    Some(Block {
+        track,
+        timestamp: cluster_timestamp.checked_add(timestamp as i64)?,
+        duration: None,
+        // only set for simple blocks
+        key_frame: header[2] & 0x80 != 0,
+        frame: (!laced).then(|| &payload[start + 3..]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blocks, Block};
+    use crate::ebml::{BLOCK, BLOCK_DURATION, BLOCK_GROUP, SIMPLE_BLOCK, TIMESTAMP};
+    use crate::fixtures::ebml::{element, uint};
+
+    #[test]
+    fn test_blocks() {
+        let cluster = [
+            uint(TIMESTAMP, 1000),
+            element(SIMPLE_BLOCK, &[0x81, 0x00, 0x00, 0x80, 0xAA]),
+            element(SIMPLE_BLOCK, &[0x82, 0xFF, 0xF6, 0x06, 0x01, 0xBB]),
+            element(
+                BLOCK_GROUP,
+                &[
+                    element(BLOCK, &[0x81, 0x00, 0x21, 0x00, 0xCC]),
+                    uint(BLOCK_DURATION, 33),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        assert_eq!(
+            blocks(&cluster),
+            [
+                Block {
+                    track: 1,
+                    timestamp: 1000,
+                    duration: None,
+                    key_frame: true,
+                    frame: Some(&[0xAA]),
+                },
+                Block {
+                    track: 2,
+                    timestamp: 990,
+                    duration: None,
+                    key_frame: false,
+                    frame: None,
+                },
+                Block {
+                    track: 1,
+                    timestamp: 1033,
+                    duration: Some(33),
+                    key_frame: true,
+                    frame: Some(&[0xCC]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overflowing_timestamp() {
+        let cluster = [
+            uint(TIMESTAMP, i64::MAX as u64),
+            element(SIMPLE_BLOCK, &[0x81, 0x00, 0x01, 0x80, 0xAA]),
+            element(SIMPLE_BLOCK, &[0x81, 0xFF, 0xFF, 0x00, 0xBB]),
+        ]
+        .concat();
+        assert_eq!(
+            blocks(&cluster),
+            [Block {
+                track: 1,
+                timestamp: i64::MAX - 1,
+                duration: None,
+                key_frame: false,
+                frame: Some(&[0xBB]),
+            }]
+        );
+    }
+}
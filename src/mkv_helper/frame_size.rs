@@ -0,0 +1,209 @@
+//! Coded frame size of VP8, VP9 and AV1 video, read from the codec private data or the first key
+//! frame of a track, for files whose `PixelWidth` and `PixelHeight` are missing.
+
+use crate::bits::BitReader;
+
+/// AV1 OBU type of a sequence header.
+const OBU_SEQUENCE_HEADER: u32 = 1;
+
+/// Returns the frame size from the codec private data of a track, which is only available for
+/// AV1, whose `av1C` configuration may contain the sequence header.
+pub(super) fn from_codec_private(codec_id: &str, codec_private: &[u8]) -> Option<(u64, u64)> {
+    match codec_id {
+        // the configuration OBUs follow the 4-byte header
+        "V_AV1" => av1_frame_size(codec_private.get(4..)?),
+        _ => None,
+    }
+}
+
+/// Returns the frame size from a key frame.
+pub(super) fn from_key_frame(codec_id: &str, frame: &[u8]) -> Option<(u64, u64)> {
+    match codec_id {
+        "V_VP8" => vp8_frame_size(frame),
+        "V_VP9" => vp9_frame_size(frame),
+        // key frames start with a temporal unit that repeats the sequence header
+        "V_AV1" => av1_frame_size(frame),
+        _ => None,
+    }
+}
+
+/// Reads the frame tag and the start code of a VP8 key frame, followed by 14-bit dimensions and
+/// 2-bit scaling factors.
+fn vp8_frame_size(frame: &[u8]) -> Option<(u64, u64)> {
+    let header = frame.get(..10)?;
+    // the lowest bit of the frame tag is 0 for key frames
+    if header[0] & 0x01 != 0 || header[3..6] != [0x9D, 0x01, 0x2A] {
+        return None;
+    }
+    let width = u16::from_le_bytes([header[6], header[7]]) & 0x3FFF;
+    let height = u16::from_le_bytes([header[8], header[9]]) & 0x3FFF;
+    Some((width as u64, height as u64))
+}
+
+/// Reads the uncompressed header of a VP9 key frame up to the frame size.
+fn vp9_frame_size(frame: &[u8]) -> Option<(u64, u64)> {
+    let mut reader = BitReader::new(frame);
+    if reader.bits(2)? != 2 {
+        return None; // frame_marker
+    }
+    let profile_low = reader.bits(1)?;
+    let profile = reader.bits(1)? << 1 | profile_low;
+    if profile == 3 {
+        reader.skip(1)?; // reserved_zero
+    }
+    if reader.flag()? {
+        return None; // show_existing_frame
+    }
+    // frame_type is 0 for key frames
+    if reader.flag()? {
+        return None;
+    }
+    reader.skip(2)?; // show_frame and error_resilient_mode
+    if reader.bits(24)? != 0x49_8342 {
+        return None; // frame_sync_code
+    }
+    // color_config
+    if profile >= 2 {
+        reader.skip(1)?; // ten_or_twelve_bit
+    }
+    let color_space = reader.bits(3)?;
+    let subsampling = profile == 1 || profile == 3;
+    // sRGB has no color range, and only 4:4:4 sampling
+    if color_space != 7 {
+        reader.skip(1)?; // color_range
+        if subsampling {
+            reader.skip(3)?; // subsampling_x, subsampling_y and reserved_zero
+        }
+    } else if subsampling {
+        reader.skip(1)?; // reserved_zero
+    }
+    let width = reader.bits(16)? as u64 + 1;
+    let height = reader.bits(16)? as u64 + 1;
+    Some((width, height))
+}
+
+/// Finds a sequence header in a sequence of OBUs and returns its maximum frame size.
+fn av1_frame_size(data: &[u8]) -> Option<(u64, u64)> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let header = rest[0];
+        let obu_type = (header >> 3 & 0x0F) as u32;
+        let extension = header & 0x04 != 0;
+        // OBUs without a size field extend to the end of the data
+        let has_size = header & 0x02 != 0;
+        let mut start = if extension { 2 } else { 1 };
+        let size = if has_size {
+            let (size, len) = read_leb128(rest.get(start..)?)?;
+            start += len;
+            usize::try_from(size).ok()?
+        } else {
+            rest.len().checked_sub(start)?
+        };
+        let end = start.checked_add(size)?;
+        let obu = rest.get(start..end)?;
+        if obu_type == OBU_SEQUENCE_HEADER {
+            return parse_av1_sequence_header(obu);
+        }
+        rest = &rest[end..];
+    }
+    None
+}
+
+fn read_leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0;
+    for (index, &byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7F) as u64) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+/// Reads an AV1 sequence header OBU up to the maximum frame size.
+fn parse_av1_sequence_header(obu: &[u8]) -> Option<(u64, u64)> {
+    let mut reader = BitReader::new(obu);
+    reader.skip(4)?; // seq_profile and still_picture
+    let reduced_still_picture_header = reader.flag()?;
+    if reduced_still_picture_header {
+        reader.skip(5)?; // seq_level_idx
+    } else {
+        let mut decoder_model_info_present = false;
+        let mut buffer_delay_length = 0;
+        if reader.flag()? {
+            // timing_info
+            reader.skip(64)?; // num_units_in_display_tick and time_scale
+            if reader.flag()? {
+                reader.ue()?; // num_ticks_per_picture_minus_1
+            }
+            decoder_model_info_present = reader.flag()?;
+            if decoder_model_info_present {
+                buffer_delay_length = reader.bits(5)? as usize + 1;
+                // num_units_in_decoding_tick, buffer_removal_time_length_minus_1 and
+                // frame_presentation_time_length_minus_1
+                reader.skip(42)?;
+            }
+        }
+        let initial_display_delay_present = reader.flag()?;
+        let operating_points = reader.bits(5)? + 1;
+        for _ in 0..operating_points {
+            reader.skip(12)?; // operating_point_idc
+            if reader.bits(5)? > 7 {
+                reader.skip(1)?; // seq_tier
+            }
+            if decoder_model_info_present && reader.flag()? {
+                // decoder and encoder buffer delays and low_delay_mode_flag
+                reader.skip(2 * buffer_delay_length + 1)?;
+            }
+            if initial_display_delay_present && reader.flag()? {
+                reader.skip(4)?; // initial_display_delay_minus_1
+            }
+        }
+    }
+    let width_bits = reader.bits(4)? as usize + 1;
+    let height_bits = reader.bits(4)? as usize + 1;
+    let width = reader.bits(width_bits)? as u64 + 1;
+    let height = reader.bits(height_bits)? as u64 + 1;
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_codec_private, from_key_frame};
+
+    #[test]
+    fn test_vp8() {
+        let frame = [
+            0x50, 0x42, 0x00, 0x9D, 0x01, 0x2A, 0x80, 0x07, 0x38, 0x04, 0x00,
+        ];
+        assert_eq!(from_key_frame("V_VP8", &frame), Some((1920, 1080)));
+        // inter frame
+        let frame = [
+            0x51, 0x42, 0x00, 0x9D, 0x01, 0x2A, 0x80, 0x07, 0x38, 0x04, 0x00,
+        ];
+        assert_eq!(from_key_frame("V_VP8", &frame), None);
+    }
+
+    #[test]
+    fn test_vp9() {
+        // profile 0 key frame with BT.709 colors, 1280x720
+        let frame = [0x82, 0x49, 0x83, 0x42, 0x40, 0x4F, 0xF0, 0x2C, 0xF8];
+        assert_eq!(from_key_frame("V_VP9", &frame), Some((1280, 720)));
+        assert_eq!(from_key_frame("V_VP9", &[0x86, 0x00]), None);
+    }
+
+    #[test]
+    fn test_av1() {
+        // av1C with a sequence header OBU of a 1920x1080 Main profile stream
+        let codec_private = [
+            0x81, 0x08, 0x0C, 0x00, 0x0A, 0x09, 0x00, 0x00, 0x00, 0x42, 0xAB, 0xBF, 0xC3, 0x76,
+            0x80,
+        ];
+        assert_eq!(
+            from_codec_private("V_AV1", &codec_private),
+            Some((1920, 1080))
+        );
+        assert_eq!(from_codec_private("V_AV1", &codec_private[..4]), None);
+        assert_eq!(from_codec_private("V_VP9", &[1, 1, 0]), None);
+    }
+}
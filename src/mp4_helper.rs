@@ -1,15 +1,36 @@
+mod chapters;
+mod quicktime;
+mod tracks;
+
+use crate::device::clean_value;
+use crate::isobmff;
 use crate::Result;
-use crate::{Error, MetaData};
+use crate::{
+    CreationDate, CreationDateOptions, DateSource, Device, ItunesTags, Location, MediaDateTime,
+    MetaData, Orientation, TagValue, Thumbnail, ThumbnailSource,
+};
 use mp4::Track;
+use std::collections::BTreeMap;
 use std::io;
-use std::time::SystemTime;
+use std::time::Duration;
 
 /// Extracts metadata from an MP4 container.
 ///
 /// This function utilizes the `mp4` crate to obtain metadata, including dimensions and creation
 /// date, from an MP4 container.
 pub fn extract_mp4_metadata<R: io::Read + io::Seek>(io: R, file_size: u64) -> Result<MetaData> {
-    let mp4 = mp4::Mp4::read(io, file_size)?;
+    extract_mp4_metadata_with_options(io, file_size, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_mp4_metadata_with_options<R: io::Read + io::Seek>(
+    mut io: R,
+    file_size: u64,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let start = io.stream_position()?;
+    let mp4 = mp4::Mp4::read(&mut io, file_size)?;
+    io.seek(io::SeekFrom::Start(start))?;
+    let moov = isobmff::read_top_level_box(&mut io, b"moov")?.unwrap_or_default();
 
     let video_track = find_video_track(mp4.tracks());
     let (width, height) = if let Some(track) = video_track {
@@ -17,35 +38,208 @@ pub fn extract_mp4_metadata<R: io::Read + io::Seek>(io: R, file_size: u64) -> Re
     } else {
         (0, 0)
     };
+    let orientation = video_track
+        .map(|track| {
+            let matrix = &track.trak(&mp4).tkhd.matrix;
+            Orientation::from_matrix(matrix.a, matrix.b, matrix.c, matrix.d)
+        })
+        .unwrap_or_default();
 
-    let creation_date = convert_mp4_time_to_system_time(mp4.moov.mvhd.creation_time);
+    let creation_date = options
+        .select(&get_creation_dates(&mp4, &moov))
+        .map(|date| date.date);
+    let duration = get_duration(&mp4);
+    // chapters are optional, so truncated chapter samples don't fail the extraction
+    let chapters = chapters::read_chapters(&mut io, &mp4, &moov, duration).unwrap_or_default();
 
     Ok(MetaData {
         width,
         height,
-        creation_date,
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration,
+        orientation,
+        location: get_location(&moov),
+        device: get_device(&moov),
+        tracks: tracks::read_tracks(&moov),
+        quicktime_keys: quicktime::metadata_items(&moov)
+            .map(|(key, value)| (key, value.to_tag_value()))
+            .collect(),
+        itunes_tags: get_itunes_tags(&moov),
+        matroska_tags: Vec::new(),
+        chapters,
+        doc_type: None,
+        bit_depth: None,
+        frame_count: None,
+        png_text: Vec::new(),
     })
 }
 
-pub(crate) fn extract_mp4_creation_date<R: io::Read + io::Seek>(
-    io: R,
+pub(crate) fn extract_mp4_creation_dates<R: io::Read + io::Seek>(
+    mut io: R,
     file_size: u64,
-) -> Result<SystemTime> {
-    let mp4 = mp4::Mp4::read(io, file_size)?;
+) -> Result<Vec<CreationDate>> {
+    let start = io.stream_position()?;
+    let mp4 = mp4::Mp4::read(&mut io, file_size)?;
+    io.seek(io::SeekFrom::Start(start))?;
+    let moov = isobmff::read_top_level_box(&mut io, b"moov")?.unwrap_or_default();
+
+    Ok(get_creation_dates(&mp4, &moov))
+}
+
+/// Finds the `covr` cover art of the iTunes-style metadata.
+pub(crate) fn find_mp4_thumbnails<R: io::Read + io::Seek>(io: &mut R) -> Result<Vec<Thumbnail>> {
+    let Some(moov) = isobmff::read_top_level_box(io, b"moov")? else {
+        return Ok(Vec::new());
+    };
+    // the stream is left at the end of the payload
+    let position = io.stream_position()? - moov.len() as u64;
+    let thumbnails = quicktime::item_list(&moov)
+        .filter(|(key, _)| key == "covr")
+        .filter_map(|(_, value)| {
+            let mime_type = match value.data_type {
+                13 => "image/jpeg",
+                14 => "image/png",
+                27 => "image/bmp",
+                _ => return None,
+            };
+            let start = position + isobmff::offset_in(&moov, value.value);
+            let range = start..start + value.value.len() as u64;
+            Some(Thumbnail::new(ThumbnailSource::Mp4Cover, mime_type, range))
+        })
+        .collect();
+    Ok(thumbnails)
+}
 
-    convert_mp4_time_to_system_time(mp4.moov.mvhd.creation_time).ok_or(Error::CreationDateNotFound)
+/// Reads the QuickTime `creationdate` key, which is local time with an offset, and the `mvhd`
+/// creation time in UTC, in this order.
+fn get_creation_dates(mp4: &mp4::Mp4, moov: &[u8]) -> Vec<CreationDate> {
+    let creation_date = quicktime::metadata_items(moov)
+        .find(|(key, _)| key == "com.apple.quicktime.creationdate")
+        .and_then(|(_, value)| value.as_text())
+        .and_then(|value| MediaDateTime::parse_iso8601(&value))
+        .map(|date| CreationDate::new(date, DateSource::QuickTimeCreationDate));
+    let creation_time = convert_mp4_time(mp4.moov.mvhd.creation_time)
+        .map(|date| CreationDate::new(date, DateSource::Mp4CreationTime));
+    creation_date.into_iter().chain(creation_time).collect()
 }
 
-fn convert_mp4_time_to_system_time(mp4_time: u64) -> Option<SystemTime> {
-    use chrono::{Duration, TimeZone, Utc};
+fn convert_mp4_time(mp4_time: u64) -> Option<MediaDateTime> {
+    use chrono::{NaiveDate, TimeDelta};
 
     if mp4_time == 0 {
         return None;
     }
-    // MP4 creation time is based on seconds since 1904-01-01
-    let utc =
-        Utc.with_ymd_and_hms(1904, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(mp4_time as i64);
-    Some(utc.into())
+    // MP4 creation time is based on seconds since 1904-01-01, values out of the range of chrono
+    // are written by broken muxers
+    let epoch = NaiveDate::from_ymd_opt(1904, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    let seconds = TimeDelta::try_seconds(i64::try_from(mp4_time).ok()?)?;
+    Some(MediaDateTime::utc(epoch.checked_add_signed(seconds)?))
+}
+
+/// Reads the recording location from the QuickTime metadata, falling back to the `©xyz` user data
+/// atom written by older devices.
+fn get_location(moov: &[u8]) -> Option<Location> {
+    quicktime::metadata_items(moov)
+        .find(|(key, _)| key == "com.apple.quicktime.location.ISO6709")
+        .and_then(|(_, value)| value.as_text())
+        .or_else(|| quicktime::user_data_text(moov, b"\xA9xyz"))
+        .and_then(|value| Location::from_iso6709(&value))
+}
+
+/// Reads the iTunes-style items, which take precedence over user data text atoms with the same key.
+fn get_itunes_tags(moov: &[u8]) -> ItunesTags {
+    let mut tags = BTreeMap::new();
+    for (key, value) in quicktime::item_list(moov) {
+        tags.entry(key).or_insert_with(|| value.to_tag_value());
+    }
+    for (key, text) in quicktime::user_data_texts(moov) {
+        tags.entry(key).or_insert(TagValue::Text(text));
+    }
+    ItunesTags { tags }
+}
+
+/// Reads the device from the QuickTime metadata keys, falling back to the `©mak`, `©mod` and
+/// `©swr` user data atoms and the iTunes-style `©too` item.
+fn get_device(moov: &[u8]) -> Option<Device> {
+    let mut device = Device::default();
+    for (key, value) in quicktime::metadata_items(moov) {
+        let field = match key.as_str() {
+            "com.apple.quicktime.make" | "com.android.manufacturer" => &mut device.make,
+            "com.apple.quicktime.model" | "com.android.model" => &mut device.model,
+            "com.apple.quicktime.software" | "com.android.version" => &mut device.software,
+            "com.apple.quicktime.camera.lens_model" => &mut device.lens_model,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = value.as_text().as_deref().and_then(clean_value);
+        }
+    }
+    for (atom, field) in [
+        (b"\xA9mak", &mut device.make),
+        (b"\xA9mod", &mut device.model),
+        (b"\xA9swr", &mut device.software),
+    ] {
+        if field.is_none() {
+            *field = quicktime::user_data_text(moov, atom)
+                .as_deref()
+                .and_then(clean_value);
+        }
+    }
+    if device.software.is_none() {
+        device.software = quicktime::item_list(moov)
+            .find(|(key, _)| key == "©too")
+            .and_then(|(_, value)| value.as_text())
+            .as_deref()
+            .and_then(clean_value);
+    }
+    device.non_empty()
+}
+
+/// Returns the presentation duration of the movie.
+///
+/// Edit lists define the presentation timeline, so they take priority over the `mvhd` duration.
+/// Fragmented files usually have no duration in `mvhd`, in which case the longest track is used.
+fn get_duration(mp4: &mp4::Mp4) -> Option<Duration> {
+    let movie_timescale = mp4.moov.mvhd.timescale as u64;
+
+    let edited_duration = mp4
+        .moov
+        .traks
+        .iter()
+        .filter_map(|trak| {
+            let elst = trak.edts.as_ref()?.elst.as_ref()?;
+            Some(
+                elst.entries
+                    .iter()
+                    .map(|entry| entry.segment_duration)
+                    .sum::<u64>(),
+            )
+        })
+        .max()
+        .filter(|duration| *duration > 0);
+    if let Some(duration) = edited_duration {
+        return convert_to_duration(duration, movie_timescale);
+    }
+
+    if mp4.moov.mvhd.duration > 0 {
+        return convert_to_duration(mp4.moov.mvhd.duration, movie_timescale);
+    }
+
+    mp4.tracks()
+        .values()
+        .filter_map(|track| convert_to_duration(track.duration, track.timescale))
+        .max()
+        .filter(|duration| !duration.is_zero())
+}
+
+fn convert_to_duration(value: u64, timescale: u64) -> Option<Duration> {
+    if timescale == 0 {
+        return None;
+    }
+    let secs = value / timescale;
+    let nanos = (value % timescale) as u128 * 1_000_000_000 / timescale as u128;
+    Some(Duration::new(secs, nanos as u32))
 }
 
 fn find_video_track<'a>(
@@ -58,3 +252,20 @@ fn find_video_track<'a>(
         .find(|(_, track)| TrackKind::Video == track.kind.unwrap_or(NOT_VIDEO))
         .map(|(_, track)| track)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::convert_mp4_time;
+    use crate::MediaDateTime;
+
+    #[test]
+    fn test_convert_mp4_time() {
+        assert_eq!(
+            convert_mp4_time(3_711_398_400),
+            MediaDateTime::parse_iso8601("2021-08-10T00:00:00Z")
+        );
+        assert_eq!(convert_mp4_time(0), None);
+        assert_eq!(convert_mp4_time(1 << 50), None);
+        assert_eq!(convert_mp4_time(u64::MAX), None);
+    }
+}
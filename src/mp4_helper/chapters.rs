@@ -0,0 +1,194 @@
+//! Chapters from QuickTime chapter tracks and the Nero `chpl` atom.
+
+use super::convert_to_duration;
+use crate::chapter::fill_end_times;
+use crate::isobmff::{self, read_u16, read_u32};
+use crate::{Chapter, ChapterTitle};
+use std::io;
+use std::time::Duration;
+
+/// Upper bound for the size of a chapter title sample.
+const MAX_SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// Reads the chapters from the text tracks referenced by `tref/chap`, falling back to the Nero
+/// `chpl` atom written by ffmpeg and Nero.
+///
+/// Every chapter track usually holds the titles in one language, so they are merged into the
+/// chapters of the first track.
+pub(crate) fn read_chapters<R: io::Read + io::Seek>(
+    io: &mut R,
+    mp4: &mp4::Mp4,
+    moov: &[u8],
+    duration: Option<Duration>,
+) -> io::Result<Vec<Chapter>> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for track_id in chapter_track_ids(moov) {
+        let Some(track) = mp4.tracks().get(&track_id) else {
+            continue;
+        };
+        let language = Some(track.trak(mp4).mdia.mdhd.language.clone())
+            .filter(|language| language.len() == 3 && language != "und");
+        let merge = !chapters.is_empty();
+        for (index, sample) in track.samples.iter().enumerate() {
+            io.seek(io::SeekFrom::Start(sample.offset))?;
+            let mut data = vec![0; sample.size.min(MAX_SAMPLE_SIZE) as usize];
+            io.read_exact(&mut data)?;
+            let Some(title) = parse_text_sample(&data) else {
+                continue;
+            };
+            let title = ChapterTitle {
+                title,
+                language: language.clone(),
+            };
+            if merge {
+                if let Some(chapter) = chapters.get_mut(index) {
+                    chapter.titles.push(title);
+                }
+                continue;
+            }
+            let start =
+                convert_to_duration(sample.composition_timestamp.max(0) as u64, sample.timescale);
+            let length = convert_to_duration(sample.duration, sample.timescale);
+            chapters.push(Chapter {
+                start: start.unwrap_or_default(),
+                end: start.zip(length).map(|(start, length)| start + length),
+                titles: vec![title],
+                edition: 0,
+                hidden: false,
+                children: Vec::new(),
+            });
+        }
+    }
+    if chapters.is_empty() {
+        chapters = isobmff::find_box(moov, b"udta")
+            .and_then(|udta| isobmff::find_box(udta, b"chpl"))
+            .map(parse_chpl)
+            .unwrap_or_default();
+        fill_end_times(&mut chapters, duration);
+    }
+    Ok(chapters)
+}
+
+/// Returns the ids of the tracks that any track references as its chapter track.
+fn chapter_track_ids(moov: &[u8]) -> Vec<u32> {
+    let mut ids = Vec::new();
+    let chap = isobmff::boxes(moov)
+        .filter(|(box_type, _)| box_type == b"trak")
+        .filter_map(|(_, trak)| isobmff::find_box(trak, b"tref"))
+        .filter_map(|tref| isobmff::find_box(tref, b"chap"));
+    for chap in chap {
+        for id in chap.chunks_exact(4) {
+            let id = u32::from_be_bytes(id.try_into().unwrap());
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Decodes a QuickTime text sample: a 16-bit length followed by UTF-8 text, or UTF-16 text with a
+/// byte order mark. Style and encoding atoms may follow the text.
+fn parse_text_sample(data: &[u8]) -> Option<String> {
+    let len = read_u16(data, 0)? as usize;
+    let text = data.get(2..2 + len)?;
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|unit| from_bytes([unit[0], unit[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let text = match text {
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        text => String::from_utf8_lossy(text).into_owned(),
+    };
+    Some(text.trim_end_matches('\0').to_owned())
+}
+
+/// Parses the Nero `chpl` atom. Start times are in units of 100 nanoseconds, titles are UTF-8
+/// without language.
+fn parse_chpl(chpl: &[u8]) -> Vec<Chapter> {
+    let Some((version, _, data)) = isobmff::full_box(chpl) else {
+        return Vec::new();
+    };
+    // version 1 has 4 reserved bytes before the chapter count
+    let mut offset = if version == 1 { 4 } else { 0 };
+    let Some(&count) = data.get(offset) else {
+        return Vec::new();
+    };
+    offset += 1;
+    let mut chapters = Vec::new();
+    for _ in 0..count {
+        let Some(start) = read_u32(data, offset)
+            .zip(read_u32(data, offset + 4))
+            .map(|(high, low)| (high as u64) << 32 | low as u64)
+        else {
+            break;
+        };
+        let Some(&len) = data.get(offset + 8) else {
+            break;
+        };
+        let Some(title) = data.get(offset + 9..offset + 9 + len as usize) else {
+            break;
+        };
+        offset += 9 + len as usize;
+        chapters.push(Chapter {
+            start: Duration::from_nanos(start.saturating_mul(100)),
+            end: None,
+            titles: vec![ChapterTitle {
+                title: String::from_utf8_lossy(title).into_owned(),
+                language: None,
+            }],
+            edition: 0,
+            hidden: false,
+            children: Vec::new(),
+        });
+    }
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chapter_track_ids, parse_chpl, parse_text_sample};
+    use crate::fixtures::make_box;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_chpl() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Intro"), (50_000_000, "Main")] {
+            chpl.extend_from_slice(&start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend_from_slice(title.as_bytes());
+        }
+        let chapters = parse_chpl(&chpl);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title(), Some("Intro"));
+        assert_eq!(chapters[1].title(), Some("Main"));
+        assert_eq!(chapters[1].start, Duration::from_secs(5));
+        assert_eq!(chapters[1].titles[0].language, None);
+    }
+
+    #[test]
+    fn test_parse_text_sample() {
+        let sample = [&[0, 5][..], b"Intro", &make_box(b"encd", &[0, 0, 1, 0])].concat();
+        assert_eq!(parse_text_sample(&sample).as_deref(), Some("Intro"));
+        let sample = [0, 6, 0xFE, 0xFF, 0, b'H', 0, b'i'];
+        assert_eq!(parse_text_sample(&sample).as_deref(), Some("Hi"));
+        assert_eq!(parse_text_sample(&[0, 10, b'a']), None);
+    }
+
+    #[test]
+    fn test_chapter_track_ids() {
+        let trak = |tref: &[u8]| make_box(b"trak", &make_box(b"tref", tref));
+        let moov = [
+            trak(&make_box(b"chap", &[0, 0, 0, 3, 0, 0, 0, 4])),
+            trak(&make_box(b"chap", &[0, 0, 0, 3])),
+            trak(&make_box(b"hint", &[0, 0, 0, 1])),
+        ]
+        .concat();
+        assert_eq!(chapter_track_ids(&moov), [3, 4]);
+    }
+}
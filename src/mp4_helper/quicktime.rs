@@ -0,0 +1,279 @@
+//! QuickTime user data (`udta`) and metadata (`meta`) atoms, which the `mp4` crate does not parse.
+
+use crate::isobmff::{self, read_u16, read_u32};
+use crate::TagValue;
+
+/// Returns the children of a `meta` box.
+///
+/// QuickTime writes `meta` as a plain box, while ISO base media files use a full box with version
+/// and flags, so both layouts are accepted.
+pub(crate) fn meta_children(meta: &[u8]) -> &[u8] {
+    if meta.get(4..8) == Some(b"hdlr") {
+        meta
+    } else {
+        meta.get(4..).unwrap_or_default()
+    }
+}
+
+/// Iterates over the `mdta` metadata items of `moov/meta`, yielding each key with the payload of
+/// its first `data` box.
+pub(crate) fn metadata_items(moov: &[u8]) -> impl Iterator<Item = (String, DataValue<'_>)> {
+    let children = isobmff::find_box(moov, b"meta")
+        .map(meta_children)
+        .unwrap_or_default();
+    let keys = isobmff::find_box(children, b"keys")
+        .map(parse_keys)
+        .unwrap_or_default();
+    let ilst = isobmff::find_box(children, b"ilst").unwrap_or_default();
+    isobmff::boxes(ilst).filter_map(move |(index, item)| {
+        // items reference keys by their one-based index
+        let index = (u32::from_be_bytes(index) as usize).checked_sub(1)?;
+        let key = keys.get(index)?.clone();
+        let value = DataValue::parse(isobmff::find_box(item, b"data")?)?;
+        Some((key, value))
+    })
+}
+
+/// Iterates over the iTunes-style items of `moov/udta/meta/ilst`, yielding each key with the
+/// payload of its first `data` box.
+///
+/// Keys are atom types like `©nam`, or `----:mean:name` for freeform items.
+pub(crate) fn item_list(moov: &[u8]) -> impl Iterator<Item = (String, DataValue<'_>)> {
+    let ilst = isobmff::find_box(moov, b"udta")
+        .and_then(|udta| isobmff::find_box(udta, b"meta"))
+        .and_then(|meta| isobmff::find_box(meta_children(meta), b"ilst"))
+        .unwrap_or_default();
+    isobmff::boxes(ilst).filter_map(|(atom, item)| {
+        let key = match &atom {
+            b"----" => freeform_key(item)?,
+            atom => atom_name(atom),
+        };
+        let value = DataValue::parse(isobmff::find_box(item, b"data")?)?;
+        Some((key, value))
+    })
+}
+
+/// Returns the key of a freeform item from its `mean` and `name` boxes, e.g.
+/// `----:com.apple.iTunes:iTunSMPB`.
+fn freeform_key(item: &[u8]) -> Option<String> {
+    let text = |box_type| {
+        let (_, _, text) = isobmff::full_box(isobmff::find_box(item, box_type)?)?;
+        Some(String::from_utf8_lossy(text).into_owned())
+    };
+    Some(format!("----:{}:{}", text(b"mean")?, text(b"name")?))
+}
+
+/// Converts an atom type to a string. The `©` prefix is stored as the Latin-1 byte `0xA9`.
+pub(crate) fn atom_name(atom: &[u8; 4]) -> String {
+    atom.iter().map(|&byte| byte as char).collect()
+}
+
+/// Returns the value of a QuickTime user data text atom such as `©xyz` from `moov/udta`.
+pub(crate) fn user_data_text(moov: &[u8], atom: &[u8; 4]) -> Option<String> {
+    let udta = isobmff::find_box(moov, b"udta")?;
+    international_text(isobmff::find_box(udta, atom)?)
+}
+
+/// Iterates over the QuickTime user data text atoms of `moov/udta`, e.g. `©day` or `©mak`.
+pub(crate) fn user_data_texts(moov: &[u8]) -> impl Iterator<Item = (String, String)> + '_ {
+    let udta = isobmff::find_box(moov, b"udta").unwrap_or_default();
+    isobmff::boxes(udta)
+        .filter(|(atom, _)| atom[0] == 0xA9)
+        .filter_map(|(atom, payload)| Some((atom_name(&atom), international_text(payload)?)))
+}
+
+fn international_text(payload: &[u8]) -> Option<String> {
+    // international text: 16-bit length, 16-bit language code, followed by the text
+    let len = read_u16(payload, 0)? as usize;
+    let text = payload.get(4..4 + len)?;
+    let text = String::from_utf8_lossy(text);
+    Some(text.trim_end_matches('\0').to_owned())
+}
+
+fn parse_keys(keys: &[u8]) -> Vec<String> {
+    let Some((_, _, data)) = isobmff::full_box(keys) else {
+        return Vec::new();
+    };
+    let count = read_u32(data, 0).unwrap_or(0);
+    // every key is stored like a box with the namespace in place of the box type
+    isobmff::boxes(data.get(4..).unwrap_or_default())
+        .take(count as usize)
+        .map(|(_, name)| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+/// Payload of a metadata `data` box.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DataValue<'a> {
+    /// Well-known type, e.g. 1 for UTF-8 text.
+    pub data_type: u32,
+    pub value: &'a [u8],
+}
+
+impl<'a> DataValue<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        // the type indicator is followed by a locale indicator
+        let data_type = read_u32(data, 0)? & 0x00FF_FFFF;
+        Some(Self {
+            data_type,
+            value: data.get(8..)?,
+        })
+    }
+
+    /// Returns the value as text, if it is stored as UTF-8 or UTF-16.
+    pub(crate) fn as_text(&self) -> Option<String> {
+        match self.data_type {
+            1 => Some(String::from_utf8_lossy(self.value).into_owned()),
+            2 => {
+                let units: Vec<u16> = self
+                    .value
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts the value according to its well-known type.
+    pub(crate) fn to_tag_value(self) -> TagValue {
+        let value = self.value;
+        match self.data_type {
+            1 | 2 => TagValue::Text(self.as_text().unwrap_or_default()),
+            13 => TagValue::Jpeg(value.to_vec()),
+            14 => TagValue::Png(value.to_vec()),
+            27 => TagValue::Bmp(value.to_vec()),
+            // big-endian signed and unsigned integers of 1 to 8 bytes
+            21 | 22 if (1..=8).contains(&value.len()) => {
+                let fill = if self.data_type == 21 && value[0] & 0x80 != 0 {
+                    0xFF
+                } else {
+                    0
+                };
+                let mut bytes = [fill; 8];
+                bytes[8 - value.len()..].copy_from_slice(value);
+                match (self.data_type, i64::from_be_bytes(bytes)) {
+                    (22, integer) if integer < 0 => TagValue::Binary(value.to_vec()),
+                    (_, integer) => TagValue::Integer(integer),
+                }
+            }
+            23 if value.len() == 4 => {
+                TagValue::Float(f32::from_be_bytes(value.try_into().unwrap()) as f64)
+            }
+            24 if value.len() == 8 => {
+                TagValue::Float(f64::from_be_bytes(value.try_into().unwrap()))
+            }
+            _ => TagValue::Binary(value.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{item_list, metadata_items, user_data_text, DataValue};
+    use crate::fixtures::make_box;
+    use crate::TagValue;
+
+    pub(crate) fn make_key(name: &str) -> Vec<u8> {
+        make_box(b"mdta", name.as_bytes())
+    }
+
+    pub(crate) fn make_item(index: u32, data_type: u32, value: &[u8]) -> Vec<u8> {
+        let data = [&data_type.to_be_bytes()[..], &[0; 4], value].concat();
+        make_box(&index.to_be_bytes(), &make_box(b"data", &data))
+    }
+
+    #[test]
+    fn test_metadata_items() {
+        let keys = [
+            vec![0, 0, 0, 0, 0, 0, 0, 2],
+            make_key("com.apple.quicktime.make"),
+            make_key("com.apple.quicktime.location.ISO6709"),
+        ]
+        .concat();
+        let ilst = [
+            make_item(2, 1, b"+37.3317-122.0302/"),
+            make_item(1, 1, b"Apple"),
+        ]
+        .concat();
+        let hdlr = make_box(b"hdlr", &[0; 25]);
+        // QuickTime style meta without version and flags
+        let meta = [hdlr, make_box(b"keys", &keys), make_box(b"ilst", &ilst)].concat();
+        let moov = make_box(b"meta", &meta);
+
+        let items: Vec<_> = metadata_items(&moov)
+            .map(|(key, value)| (key, value.as_text().unwrap()))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (
+                    "com.apple.quicktime.location.ISO6709".to_owned(),
+                    "+37.3317-122.0302/".to_owned()
+                ),
+                ("com.apple.quicktime.make".to_owned(), "Apple".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_list() {
+        let full_box =
+            |box_type, text: &str| make_box(box_type, &[&[0; 4], text.as_bytes()].concat());
+        let freeform = [
+            full_box(b"mean", "com.apple.iTunes"),
+            full_box(b"name", "iTunSMPB"),
+            make_box(
+                b"data",
+                &[&[0, 0, 0, 1, 0, 0, 0, 0][..], b" 00000000"].concat(),
+            ),
+        ]
+        .concat();
+        let ilst = [
+            make_item(u32::from_be_bytes(*b"\xA9too"), 1, b"Lavf57.83.100"),
+            make_box(b"----", &freeform),
+        ]
+        .concat();
+        let meta = [vec![0, 0, 0, 0], make_box(b"ilst", &ilst)].concat();
+        let moov = make_box(b"udta", &make_box(b"meta", &meta));
+
+        let items: Vec<_> = item_list(&moov)
+            .map(|(key, value)| (key, value.as_text().unwrap()))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                ("©too".to_owned(), "Lavf57.83.100".to_owned()),
+                (
+                    "----:com.apple.iTunes:iTunSMPB".to_owned(),
+                    " 00000000".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_data_text() {
+        let xyz = [&[0, 18, 0x15, 0xC7][..], b"+48.8577+002.2950/"].concat();
+        let moov = make_box(b"udta", &make_box(b"\xA9xyz", &xyz));
+        assert_eq!(
+            user_data_text(&moov, b"\xA9xyz").as_deref(),
+            Some("+48.8577+002.2950/")
+        );
+        assert_eq!(user_data_text(&moov, b"\xA9mak"), None);
+    }
+
+    #[test]
+    fn test_tag_value() {
+        fn value(data_type: u32, value: &[u8]) -> TagValue {
+            DataValue { data_type, value }.to_tag_value()
+        }
+        assert_eq!(value(1, b"Apple"), TagValue::Text("Apple".to_owned()));
+        assert_eq!(value(21, &[0xFF, 0xFE]), TagValue::Integer(-2));
+        assert_eq!(value(22, &[0xFF, 0xFE]), TagValue::Integer(0xFFFE));
+        assert_eq!(value(23, &1.5f32.to_be_bytes()), TagValue::Float(1.5));
+        assert_eq!(value(13, &[0xFF, 0xD8]), TagValue::Jpeg(vec![0xFF, 0xD8]));
+        assert_eq!(value(0, &[0, 1]), TagValue::Binary(vec![0, 1]));
+    }
+}
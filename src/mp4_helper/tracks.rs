@@ -0,0 +1,333 @@
+//! Track descriptions read from `moov/trak` boxes.
+//!
+//! The `mp4` crate only understands a fixed set of sample entries, so the boxes are parsed here to
+//! preserve the original FourCC of every track.
+
+use crate::device::clean_value;
+use crate::isobmff::{self, read_u16, read_u32};
+use crate::{AudioDetails, CodecInfo, FrameRate, TrackDetails, TrackInfo, TrackKind};
+
+/// Size of the fields of `VisualSampleEntry` that precede its child boxes.
+const VISUAL_SAMPLE_ENTRY_LEN: usize = 78;
+/// Size of the fields of a version 0 `AudioSampleEntry` that precede its child boxes.
+const AUDIO_SAMPLE_ENTRY_LEN: usize = 28;
+
+pub(crate) fn read_tracks(moov: &[u8]) -> Vec<TrackInfo> {
+    isobmff::boxes(moov)
+        .filter(|(box_type, _)| box_type == b"trak")
+        .filter_map(|(_, trak)| read_track(trak))
+        .collect()
+}
+
+fn read_track(trak: &[u8]) -> Option<TrackInfo> {
+    let mdia = isobmff::find_box(trak, b"mdia")?;
+    let handler = isobmff::find_box(mdia, b"hdlr")
+        .and_then(isobmff::full_box)
+        .and_then(|(_, _, hdlr)| hdlr.get(4..8));
+    let kind = match handler {
+        Some(b"vide") => TrackKind::Video,
+        Some(b"soun") => TrackKind::Audio,
+        Some(b"text" | b"sbtl" | b"subt" | b"clcp") => TrackKind::Subtitle,
+        _ => TrackKind::Other,
+    };
+
+    let stbl = isobmff::find_box(mdia, b"minf").and_then(|minf| isobmff::find_box(minf, b"stbl"));
+    let (fourcc, entry) = stbl
+        .and_then(|stbl| isobmff::find_box(stbl, b"stsd"))
+        .and_then(isobmff::full_box)
+        // the entry count is followed by the sample entries
+        .and_then(|(_, _, stsd)| isobmff::boxes(stsd.get(4..)?).next())?;
+    let object_type = match kind {
+        TrackKind::Video => entry.get(VISUAL_SAMPLE_ENTRY_LEN..),
+        TrackKind::Audio => entry.get(audio_sample_entry_len(entry)..),
+        _ => None,
+    }
+    .and_then(|children| isobmff::find_box(children, b"esds"))
+    .and_then(parse_esds_object_type);
+
+    let mut track = TrackInfo::new(kind, CodecInfo::from_fourcc(&fourcc, object_type));
+    match &mut track.details {
+        TrackDetails::Video(video) => {
+            video.width = read_u16(entry, 24).unwrap_or(0) as u64;
+            video.height = read_u16(entry, 26).unwrap_or(0) as u64;
+            let timescale = isobmff::find_box(mdia, b"mdhd").and_then(parse_mdhd_timescale);
+            let stts = stbl.and_then(|stbl| isobmff::find_box(stbl, b"stts"));
+            video.frame_rate = timescale
+                .zip(stts)
+                .and_then(|(timescale, stts)| parse_frame_rate(stts, timescale));
+        }
+        TrackDetails::Audio(audio) => *audio = parse_audio_sample_entry(entry),
+        _ => {}
+    }
+    if &fourcc == b"tx3g" {
+        // display flags: 0x40000000 some samples are forced, 0x80000000 all samples are forced
+        track.forced = read_u32(entry, 8).is_some_and(|flags| flags & 0x8000_0000 != 0);
+    }
+
+    // tkhd flags: 0x1 enabled, 0x2 used in the presentation
+    let tkhd_flags = isobmff::find_box(trak, b"tkhd")
+        .and_then(isobmff::full_box)
+        .map_or(0x3, |(_, flags, _)| flags);
+    track.enabled = tkhd_flags & 0x1 != 0;
+    track.default = track.enabled;
+
+    track.language = isobmff::find_box(mdia, b"elng")
+        .and_then(isobmff::full_box)
+        .and_then(|(_, _, elng)| clean_value(&String::from_utf8_lossy(elng)))
+        .or_else(|| isobmff::find_box(mdia, b"mdhd").and_then(parse_mdhd_language));
+    track.name = isobmff::find_box(trak, b"udta")
+        .and_then(|udta| isobmff::find_box(udta, b"name"))
+        .and_then(|name| clean_value(&String::from_utf8_lossy(name)));
+
+    Some(track)
+}
+
+fn parse_mdhd_timescale(mdhd: &[u8]) -> Option<u32> {
+    let (version, _, data) = isobmff::full_box(mdhd)?;
+    read_u32(data, if version == 1 { 16 } else { 8 })
+}
+
+/// Calculates the frame rate from the sample durations of the decoding time-to-sample table.
+///
+/// The last sample often has a different duration because it is cut to the track length, so it
+/// is ignored when checking whether the frame rate is constant.
+fn parse_frame_rate(stts: &[u8], timescale: u32) -> Option<FrameRate> {
+    let (_, _, data) = isobmff::full_box(stts)?;
+    let entry_count = read_u32(data, 0)? as usize;
+    let entries: Vec<(u32, u32)> = data
+        .get(4..)?
+        .chunks_exact(8)
+        .take(entry_count)
+        .map(|entry| {
+            let count = u32::from_be_bytes(entry[0..4].try_into().unwrap());
+            let delta = u32::from_be_bytes(entry[4..8].try_into().unwrap());
+            (count, delta)
+        })
+        .collect();
+
+    let mut regular = entries.as_slice();
+    if let [rest @ .., (1, _)] = regular {
+        if !rest.is_empty() {
+            regular = rest;
+        }
+    }
+    let (_, first_delta) = *regular.first()?;
+    let variable = regular.iter().any(|(_, delta)| *delta != first_delta);
+
+    if variable {
+        let samples: u64 = entries.iter().map(|(count, _)| *count as u64).sum();
+        let duration: u64 = entries
+            .iter()
+            .map(|(count, delta)| *count as u64 * *delta as u64)
+            .sum();
+        // the average of a variable frame rate is usually close to a standard rate
+        FrameRate::from_fps(samples as f64 * timescale as f64 / duration as f64, true)
+    } else {
+        FrameRate::from_ratio(timescale as u64, first_delta as u64, false)
+    }
+}
+
+/// Returns the packed ISO 639-2/T language code of the media header.
+fn parse_mdhd_language(mdhd: &[u8]) -> Option<String> {
+    let (version, _, data) = isobmff::full_box(mdhd)?;
+    let offset = if version == 1 { 28 } else { 16 };
+    let packed = read_u16(data, offset)?;
+    // values below 0x400 are Macintosh language codes, which are not mapped
+    if packed < 0x400 {
+        return None;
+    }
+    let language: String = [10, 5, 0]
+        .iter()
+        .map(|shift| char::from((((packed >> shift) & 0x1F) as u8) + 0x60))
+        .collect();
+    (language != "und").then_some(language)
+}
+
+fn parse_audio_sample_entry(entry: &[u8]) -> AudioDetails {
+    let (sample_rate, channels) = if read_u16(entry, 8) == Some(2) {
+        let sample_rate = entry
+            .get(32..40)
+            .map(|rate| f64::from_be_bytes(rate.try_into().unwrap()).round() as u32);
+        (sample_rate, read_u32(entry, 40))
+    } else {
+        // 16.16 fixed-point sample rate
+        let sample_rate = read_u32(entry, 24).map(|rate| rate >> 16);
+        (sample_rate, read_u16(entry, 16).map(u32::from))
+    };
+    AudioDetails {
+        sample_rate: sample_rate.filter(|rate| *rate > 0),
+        channels: channels.filter(|channels| *channels > 0),
+    }
+}
+
+/// QuickTime sound sample descriptions version 1 and 2 have additional fields.
+fn audio_sample_entry_len(entry: &[u8]) -> usize {
+    match read_u16(entry, 8) {
+        Some(1) => AUDIO_SAMPLE_ENTRY_LEN + 16,
+        Some(2) => AUDIO_SAMPLE_ENTRY_LEN + 36,
+        _ => AUDIO_SAMPLE_ENTRY_LEN,
+    }
+}
+
+/// Returns the object type indication of the decoder configuration in an `esds` box.
+fn parse_esds_object_type(esds: &[u8]) -> Option<u8> {
+    let (_, _, data) = isobmff::full_box(esds)?;
+    let (tag, es) = read_descriptor(data)?;
+    if tag != 0x03 {
+        return None;
+    }
+    let flags = *es.get(2)?;
+    let mut offset = 3;
+    if flags & 0x80 != 0 {
+        offset += 2;
+    }
+    if flags & 0x40 != 0 {
+        offset += 1 + *es.get(offset)? as usize;
+    }
+    if flags & 0x20 != 0 {
+        offset += 2;
+    }
+    let (tag, decoder_config) = read_descriptor(es.get(offset..)?)?;
+    if tag != 0x04 {
+        return None;
+    }
+    decoder_config.first().copied()
+}
+
+/// Reads an MPEG-4 descriptor with its variable-length size.
+fn read_descriptor(data: &[u8]) -> Option<(u8, &[u8])> {
+    let tag = *data.first()?;
+    let mut len = 0usize;
+    let mut offset = 1;
+    for _ in 0..4 {
+        let byte = *data.get(offset)?;
+        offset += 1;
+        len = (len << 7) | (byte & 0x7F) as usize;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let end = (offset + len).min(data.len());
+    Some((tag, &data[offset..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_esds_object_type, parse_frame_rate, read_tracks};
+    use crate::fixtures::make_box;
+    use crate::{AudioDetails, Codec, TrackDetails, TrackKind};
+
+    /// ES_Descriptor with a DecoderConfigDescriptor for AAC, sizes in the extended form.
+    const AAC_ESDS: [u8; 34] = [
+        0, 0, 0, 0, 0x03, 0x80, 0x80, 0x80, 0x19, 0, 1, 0, 0x04, 0x80, 0x80, 0x80, 0x11, 0x40,
+        0x15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    /// Creates a `trak` box, `mdia_boxes` and `trak_boxes` are appended to the respective boxes.
+    fn make_trak(
+        handler: &[u8; 4],
+        sample_entry: &[u8],
+        mdia_boxes: &[u8],
+        trak_boxes: &[u8],
+    ) -> Vec<u8> {
+        let hdlr = [&[0; 8][..], handler, &[0; 13]].concat();
+        let stsd = [&[0, 0, 0, 0, 0, 0, 0, 1][..], sample_entry].concat();
+        let stbl = make_box(b"stbl", &make_box(b"stsd", &stsd));
+        let minf = make_box(b"minf", &stbl);
+        let mdia = [&make_box(b"hdlr", &hdlr), &minf, mdia_boxes].concat();
+        make_box(b"trak", &[&make_box(b"mdia", &mdia), trak_boxes].concat())
+    }
+
+    #[test]
+    fn test_read_tracks() {
+        let mp4a = [&[0; 28][..], &make_box(b"esds", &AAC_ESDS)].concat();
+        let moov = [
+            make_trak(b"vide", &make_box(b"hvc1", &[0; 78]), &[], &[]),
+            make_trak(b"soun", &make_box(b"mp4a", &mp4a), &[], &[]),
+            make_trak(b"sbtl", &make_box(b"tx3g", &[0; 8]), &[], &[]),
+        ]
+        .concat();
+
+        let tracks = read_tracks(&moov);
+        let codecs: Vec<_> = tracks
+            .iter()
+            .map(|track| (track.kind, track.codec.codec, track.codec.id.as_str()))
+            .collect();
+        assert_eq!(
+            codecs,
+            [
+                (TrackKind::Video, Codec::Hevc, "hvc1"),
+                (TrackKind::Audio, Codec::Aac, "mp4a"),
+                (TrackKind::Subtitle, Codec::TimedText, "tx3g"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_track_properties() {
+        // two channels at 44.1 kHz
+        let mut mp4a = [0; 28];
+        mp4a[16..18].copy_from_slice(&2u16.to_be_bytes());
+        mp4a[24..28].copy_from_slice(&(44100u32 << 16).to_be_bytes());
+        // version 0 mdhd with the packed language code of "deu"
+        let mut mdhd = [0; 24];
+        mdhd[20..22].copy_from_slice(&0x10B5u16.to_be_bytes());
+        let tkhd = make_box(b"tkhd", &[0; 84]);
+        let udta = make_box(b"udta", &make_box(b"name", b"Commentary"));
+
+        let trak = make_trak(
+            b"soun",
+            &make_box(b"mp4a", &mp4a),
+            &make_box(b"mdhd", &mdhd),
+            &[tkhd, udta].concat(),
+        );
+        let track = read_tracks(&trak).remove(0);
+
+        assert_eq!(track.language.as_deref(), Some("deu"));
+        assert_eq!(track.name.as_deref(), Some("Commentary"));
+        // disabled in tkhd
+        assert!(!track.enabled);
+        assert_eq!(
+            track.details,
+            TrackDetails::Audio(AudioDetails {
+                sample_rate: Some(44100),
+                channels: Some(2),
+            })
+        );
+    }
+
+    fn make_stts(entries: &[(u32, u32)]) -> Vec<u8> {
+        let mut stts = vec![0, 0, 0, 0];
+        stts.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (count, delta) in entries {
+            stts.extend_from_slice(&count.to_be_bytes());
+            stts.extend_from_slice(&delta.to_be_bytes());
+        }
+        stts
+    }
+
+    #[test]
+    fn test_frame_rate() {
+        let frame_rate = |entries: &[(u32, u32)], timescale| {
+            let rate = parse_frame_rate(&make_stts(entries), timescale).unwrap();
+            (rate.numerator, rate.denominator, rate.variable)
+        };
+        // the shorter last sample does not make the rate variable
+        assert_eq!(
+            frame_rate(&[(399, 3003), (1, 1001)], 90000),
+            (30000, 1001, false)
+        );
+        assert_eq!(frame_rate(&[(300, 512)], 12800), (25, 1, false));
+        // a phone recording with jittering frame durations averaging 30 fps
+        assert_eq!(
+            frame_rate(&[(10, 19), (10, 21), (10, 20)], 600),
+            (30, 1, true)
+        );
+        assert_eq!(parse_frame_rate(&make_stts(&[]), 600), None);
+    }
+
+    #[test]
+    fn test_esds_object_type() {
+        assert_eq!(parse_esds_object_type(&AAC_ESDS), Some(0x40));
+    }
+}
@@ -0,0 +1,144 @@
+/// Describes how the coded image must be transformed to be displayed upright.
+///
+/// The transformation is applied as an optional horizontal flip followed by a clockwise rotation.
+/// This covers all eight EXIF orientations as well as video rotation matrices.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Orientation {
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270.
+    pub rotation: u16,
+    /// Whether the image is flipped horizontally before rotating.
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    /// Creates an orientation from an EXIF `Orientation` tag value (1–8).
+    pub fn from_exif(value: u32) -> Option<Self> {
+        let (rotation, mirrored) = match value {
+            1 => (0, false),
+            2 => (0, true),
+            3 => (180, false),
+            4 => (180, true),
+            5 => (270, true),
+            6 => (90, false),
+            7 => (90, true),
+            8 => (270, false),
+            _ => return None,
+        };
+        Some(Self { rotation, mirrored })
+    }
+
+    /// Returns the matching EXIF `Orientation` tag value (1–8).
+    pub fn to_exif(&self) -> u16 {
+        match (self.rotation, self.mirrored) {
+            (0, false) => 1,
+            (0, true) => 2,
+            (180, false) => 3,
+            (180, true) => 4,
+            (270, true) => 5,
+            (90, false) => 6,
+            (90, true) => 7,
+            (270, false) => 8,
+            _ => 1,
+        }
+    }
+
+    /// Returns `true` if width and height are swapped when displayed.
+    pub fn swaps_dimensions(&self) -> bool {
+        self.rotation % 180 == 90
+    }
+
+    /// Applies an additional clockwise rotation after this transformation.
+    pub(crate) fn rotate(self, degrees: i64) -> Self {
+        Self {
+            rotation: normalize_degrees(self.rotation as i64 + degrees),
+            mirrored: self.mirrored,
+        }
+    }
+
+    /// Applies a horizontal flip (around the vertical axis) after this transformation.
+    pub(crate) fn mirror_horizontally(self) -> Self {
+        Self {
+            rotation: normalize_degrees(-(self.rotation as i64)),
+            mirrored: !self.mirrored,
+        }
+    }
+
+    /// Applies a vertical flip (around the horizontal axis) after this transformation.
+    pub(crate) fn mirror_vertically(self) -> Self {
+        self.mirror_horizontally().rotate(180)
+    }
+
+    /// Creates an orientation from the `a`, `b`, `c`, `d` coefficients of a QuickTime/ISOBMFF
+    /// transformation matrix.
+    pub(crate) fn from_matrix(a: i32, b: i32, c: i32, d: i32) -> Self {
+        let determinant = a as i64 * d as i64 - b as i64 * c as i64;
+        let mirrored = determinant < 0;
+        // remove the flip to get the pure rotation part
+        let (a, b) = if mirrored { (-a, -b) } else { (a, b) };
+        let degrees = (b as f64).atan2(a as f64).to_degrees().round() as i64;
+        Self {
+            rotation: normalize_degrees(degrees),
+            mirrored,
+        }
+    }
+}
+
+/// Rounds to the nearest multiple of 90 degrees in the range 0..360.
+fn normalize_degrees(degrees: i64) -> u16 {
+    let quarter_turns = ((degrees as f64) / 90.0).round() as i64;
+    (quarter_turns.rem_euclid(4) * 90) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Orientation;
+
+    #[test]
+    fn test_exif_roundtrip() {
+        for value in 1..=8 {
+            let orientation = Orientation::from_exif(value).unwrap();
+            assert_eq!(orientation.to_exif() as u32, value);
+        }
+        assert_eq!(Orientation::from_exif(0), None);
+    }
+
+    #[test]
+    fn test_matrix() {
+        const ONE: i32 = 0x10000;
+        assert_eq!(
+            Orientation::from_matrix(ONE, 0, 0, ONE),
+            Orientation::default()
+        );
+        // typical portrait video recorded by a phone
+        assert_eq!(
+            Orientation::from_matrix(0, ONE, -ONE, 0),
+            Orientation::from_exif(6).unwrap()
+        );
+        assert_eq!(
+            Orientation::from_matrix(-ONE, 0, 0, -ONE),
+            Orientation::from_exif(3).unwrap()
+        );
+        assert_eq!(
+            Orientation::from_matrix(0, -ONE, ONE, 0),
+            Orientation::from_exif(8).unwrap()
+        );
+        assert_eq!(
+            Orientation::from_matrix(-ONE, 0, 0, ONE),
+            Orientation::from_exif(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_composition() {
+        let identity = Orientation::default();
+        assert_eq!(
+            identity.mirror_vertically(),
+            Orientation::from_exif(4).unwrap()
+        );
+        assert_eq!(
+            identity.rotate(90).mirror_horizontally(),
+            Orientation::from_exif(5).unwrap()
+        );
+        assert_eq!(identity.rotate(-90), Orientation::from_exif(8).unwrap());
+    }
+}
@@ -0,0 +1,385 @@
+//! Native support for PNG images, including animated PNG (APNG).
+//!
+//! All chunks up to `IEND` are visited, as text and time chunks may follow the image data, but
+//! only the ancillary chunks with metadata are read.
+
+use crate::device::clean_value;
+use crate::{
+    exif_helper, tiff, xmp, CreationDate, CreationDateOptions, DateSource, Device, MediaDateTime,
+    MetaData, PngText, Result, Thumbnail,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use miniz_oxide::inflate::TINFLStatus;
+use std::io;
+use std::time::Duration;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Upper bound for the size of a metadata chunk that is loaded into memory.
+const MAX_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+/// Upper bound for the total size of decompressed text, as small chunks can expand to a lot.
+const MAX_TEXT_SIZE: usize = 16 * 1024 * 1024;
+/// Keyword of the international text entry with the XMP packet.
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Extracts metadata from a PNG image.
+///
+/// Dimensions and bit depth are read from the `IHDR` chunk, the number of frames and the play
+/// time of animated images from the `acTL` and `fcTL` chunks. The creation date is read from the
+/// `eXIf` chunk, the embedded XMP packet, the `Creation Time` text or the `tIME` chunk.
+pub fn extract_png_metadata<R: io::Read + io::Seek>(io: R) -> Result<MetaData> {
+    extract_png_metadata_with_options(io, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_png_metadata_with_options<R: io::Read + io::Seek>(
+    mut io: R,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let png = read_png(&mut io)?;
+    let exif = png.parse_exif();
+    let creation_date = options
+        .select(&get_creation_dates(&png, exif.as_ref()))
+        .map(|date| date.date);
+
+    Ok(MetaData {
+        width: png.width as u64,
+        height: png.height as u64,
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration: png.frame_count.map(|_| png.play_time),
+        orientation: exif
+            .as_ref()
+            .map(exif_helper::get_orientation)
+            .unwrap_or_default(),
+        location: exif.as_ref().and_then(exif_helper::get_location),
+        device: get_device(exif.as_ref(), &png.text),
+        bit_depth: png.bit_depth,
+        frame_count: png.frame_count,
+        png_text: png.text,
+        ..Default::default()
+    })
+}
+
+pub(crate) fn extract_png_creation_dates<R: io::Read + io::Seek>(
+    mut io: R,
+) -> Result<Vec<CreationDate>> {
+    let png = read_png(&mut io)?;
+    Ok(get_creation_dates(&png, png.parse_exif().as_ref()))
+}
+
+/// Finds the thumbnail in the Exif data of the `eXIf` chunk.
+pub(crate) fn find_png_thumbnails<R: io::Read + io::Seek>(io: &mut R) -> Result<Vec<Thumbnail>> {
+    let png = read_png(io)?;
+    match png.exif {
+        Some((position, _)) => Ok(tiff::find_previews(io, position, false)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Metadata chunks of a PNG file.
+#[derive(Debug, Default)]
+struct Png {
+    width: u32,
+    height: u32,
+    /// Bits per sample or palette index.
+    bit_depth: Option<u8>,
+    /// Position of the TIFF header in the file and the Exif data of the `eXIf` chunk.
+    exif: Option<(u64, Vec<u8>)>,
+    /// Time of the last modification from the `tIME` chunk.
+    modification_time: Option<MediaDateTime>,
+    text: Vec<PngText>,
+    /// Total size of the decompressed text so far.
+    inflated_size: usize,
+    /// Number of frames from the `acTL` chunk, only set for animated images.
+    frame_count: Option<u64>,
+    /// Sum of the frame delays of all `fcTL` chunks.
+    play_time: Duration,
+}
+
+impl Png {
+    fn parse_exif(&self) -> Option<exif::Exif> {
+        let (_, data) = self.exif.as_ref()?;
+        exif::Reader::new().read_raw(data.clone()).ok()
+    }
+
+    fn text(&self, keyword: &str) -> Option<&str> {
+        self.text
+            .iter()
+            .find(|entry| entry.keyword == keyword)
+            .map(|entry| entry.text.as_str())
+    }
+}
+
+fn read_png<R: io::Read + io::Seek>(io: &mut R) -> io::Result<Png> {
+    let mut signature = [0u8; 8];
+    io.read_exact(&mut signature)?;
+    if signature != SIGNATURE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a PNG file"));
+    }
+
+    let mut png = Png::default();
+    let mut position = io.stream_position()?;
+    loop {
+        io.seek(io::SeekFrom::Start(position))?;
+        let mut header = [0u8; 8];
+        match io.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let size = read_u32(&header, 0).unwrap_or_default();
+        let chunk_type = &header[4..8];
+        if chunk_type == b"IEND" {
+            break;
+        }
+        let wanted = matches!(
+            chunk_type,
+            b"IHDR" | b"eXIf" | b"tIME" | b"tEXt" | b"zTXt" | b"iTXt" | b"acTL" | b"fcTL"
+        );
+        if wanted && size <= MAX_CHUNK_SIZE {
+            let mut data = vec![0; size as usize];
+            match io.read_exact(&mut data) {
+                Ok(()) => {}
+                // a truncated chunk ends the file, like a truncated chunk header
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            parse_chunk(chunk_type, data, position + 8, &mut png);
+        }
+        // the data is followed by a CRC
+        position += 12 + size as u64;
+    }
+    Ok(png)
+}
+
+fn parse_chunk(chunk_type: &[u8], data: Vec<u8>, position: u64, png: &mut Png) {
+    match chunk_type {
+        b"IHDR" => {
+            png.width = read_u32(&data, 0).unwrap_or_default();
+            png.height = read_u32(&data, 4).unwrap_or_default();
+            png.bit_depth = data.get(8).copied();
+        }
+        b"eXIf" => {
+            // some writers keep the "Exif\0\0" prefix of the JPEG segment
+            let prefix = if data.starts_with(b"Exif\0\0") { 6 } else { 0 };
+            png.exif = Some((position + prefix as u64, data[prefix..].to_vec()));
+        }
+        b"tIME" => png.modification_time = parse_time(&data),
+        b"tEXt" | b"zTXt" | b"iTXt" => {
+            let text = parse_text(chunk_type, &data, &mut png.inflated_size);
+            png.text.extend(text);
+        }
+        b"acTL" => png.frame_count = read_u32(&data, 0).map(u64::from),
+        b"fcTL" => {
+            let delay_num = read_u16(&data, 20).unwrap_or_default() as u64;
+            // a denominator of 0 means hundredths of a second
+            let delay_den = match read_u16(&data, 22).unwrap_or_default() {
+                0 => 100,
+                den => den as u64,
+            };
+            png.play_time += Duration::from_nanos(delay_num * 1_000_000_000 / delay_den);
+        }
+        _ => {}
+    }
+}
+
+/// Parses a text chunk. `tEXt` and `zTXt` are Latin-1, `iTXt` is UTF-8 with a language tag and a
+/// translated keyword. `zTXt` and compressed `iTXt` text is zlib compressed, `inflated_size` is
+/// the total size of the text decompressed so far.
+fn parse_text(chunk_type: &[u8], data: &[u8], inflated_size: &mut usize) -> Option<PngText> {
+    let (keyword, rest) = split_null(data)?;
+    let keyword = latin1(keyword);
+    match chunk_type {
+        b"tEXt" => Some(PngText {
+            keyword,
+            text: latin1(rest),
+            language: None,
+            translated_keyword: None,
+        }),
+        b"zTXt" => {
+            // the compression method is always 0 for zlib
+            let text = inflate(rest.get(1..)?, inflated_size)?;
+            Some(PngText {
+                keyword,
+                text: latin1(&text),
+                language: None,
+                translated_keyword: None,
+            })
+        }
+        b"iTXt" => {
+            let compressed = *rest.first()? != 0;
+            let (language, rest) = split_null(rest.get(2..)?)?;
+            let (translated_keyword, text) = split_null(rest)?;
+            let text = if compressed {
+                inflate(text, inflated_size)?
+            } else {
+                text.to_vec()
+            };
+            let non_empty = |value: &[u8]| {
+                (!value.is_empty()).then(|| String::from_utf8_lossy(value).into_owned())
+            };
+            Some(PngText {
+                keyword,
+                text: String::from_utf8_lossy(&text).into_owned(),
+                language: non_empty(language),
+                translated_keyword: non_empty(translated_keyword),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn split_null(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let index = data.iter().position(|byte| *byte == 0)?;
+    Some((&data[..index], &data[index + 1..]))
+}
+
+fn latin1(data: &[u8]) -> String {
+    data.iter().map(|byte| *byte as char).collect()
+}
+
+/// Decompresses text as long as the total stays within [`MAX_TEXT_SIZE`]. Once the limit is
+/// exceeded, no more text is decompressed.
+fn inflate(data: &[u8], inflated_size: &mut usize) -> Option<Vec<u8>> {
+    let limit = MAX_TEXT_SIZE - *inflated_size;
+    match miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, limit) {
+        Ok(text) => {
+            *inflated_size += text.len();
+            Some(text)
+        }
+        Err(e) if e.status == TINFLStatus::HasMoreOutput => {
+            *inflated_size = MAX_TEXT_SIZE;
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Parses the `tIME` chunk, which is always in UTC.
+fn parse_time(data: &[u8]) -> Option<MediaDateTime> {
+    let year = read_u16(data, 0)? as i32;
+    let [month, day, hour, minute, second] = data.get(2..7)?.try_into().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, month as u32, day as u32)?;
+    // a second of 60 is allowed for leap seconds
+    let datetime = date.and_hms_opt(hour as u32, minute as u32, second.min(59) as u32)?;
+    Some(MediaDateTime::utc(datetime))
+}
+
+/// Parses the `Creation Time` text, which should be in the RFC 1123 format like
+/// `Fri, 13 Aug 2021 18:04:35 +0200`, but is often written in ISO 8601 or the Exif format.
+fn parse_creation_time(value: &str) -> Option<MediaDateTime> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc2822(value) {
+        return Some(MediaDateTime::fixed(
+            datetime.naive_local(),
+            *datetime.offset(),
+        ));
+    }
+    MediaDateTime::parse_iso8601(value).or_else(|| {
+        let datetime = NaiveDateTime::parse_from_str(value, "%Y:%m:%d %H:%M:%S").ok()?;
+        Some(MediaDateTime::floating(datetime))
+    })
+}
+
+/// Returns the Exif dates followed by the XMP date, the `Creation Time` text and the `tIME`
+/// chunk.
+fn get_creation_dates(png: &Png, exif: Option<&exif::Exif>) -> Vec<CreationDate> {
+    let mut dates = exif
+        .map(exif_helper::get_creation_dates)
+        .unwrap_or_default();
+    let xmp_date = png.text(XMP_KEYWORD).and_then(xmp::get_date_created);
+    dates.extend(xmp_date.map(|date| CreationDate::new(date, DateSource::XmpDateCreated)));
+    let creation_time = png.text("Creation Time").and_then(parse_creation_time);
+    dates.extend(creation_time.map(|date| CreationDate::new(date, DateSource::PngCreationTime)));
+    let modification_time = png.modification_time;
+    dates.extend(
+        modification_time.map(|date| CreationDate::new(date, DateSource::PngModificationTime)),
+    );
+    dates
+}
+
+/// Reads the camera from the Exif data, and the software from the `Software` text if Exif has
+/// none.
+fn get_device(exif: Option<&exif::Exif>, text: &[PngText]) -> Option<Device> {
+    let mut device = exif.and_then(exif_helper::get_device).unwrap_or_default();
+    if device.software.is_none() {
+        device.software = text
+            .iter()
+            .find(|entry| entry.keyword == "Software")
+            .and_then(|entry| clean_value(&entry.text));
+    }
+    device.non_empty()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_creation_time, parse_text, parse_time, MAX_TEXT_SIZE};
+    use crate::{MediaDateTime, PngText};
+
+    #[test]
+    fn test_parse_creation_time() {
+        let parse = |value| parse_creation_time(value).map(|date| date.to_string());
+        assert_eq!(
+            parse("Fri, 13 Aug 2021 18:04:35 +0200").as_deref(),
+            Some("2021-08-13T18:04:35+02:00")
+        );
+        assert_eq!(
+            parse("2021-08-13T18:04:35Z").as_deref(),
+            Some("2021-08-13T18:04:35Z")
+        );
+        assert_eq!(
+            parse("2021:08:13 18:04:35").as_deref(),
+            Some("2021-08-13T18:04:35")
+        );
+        assert_eq!(parse("yesterday"), None);
+        assert_eq!(
+            parse_time(&[0x07, 0xE5, 8, 13, 16, 4, 35]),
+            MediaDateTime::parse_iso8601("2021-08-13T16:04:35Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_text() {
+        assert_eq!(
+            parse_text(b"tEXt", b"Author\0J\xF6rg", &mut 0),
+            Some(PngText {
+                keyword: "Author".to_owned(),
+                text: "Jörg".to_owned(),
+                language: None,
+                translated_keyword: None,
+            })
+        );
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib("Grüße".as_bytes(), 6);
+        let itxt = [&b"Title\0\x01\0de\0Titel\0"[..], &compressed].concat();
+        assert_eq!(
+            parse_text(b"iTXt", &itxt, &mut 0),
+            Some(PngText {
+                keyword: "Title".to_owned(),
+                text: "Grüße".to_owned(),
+                language: Some("de".to_owned()),
+                translated_keyword: Some("Titel".to_owned()),
+            })
+        );
+        assert_eq!(parse_text(b"zTXt", b"Comment\0\0\x78", &mut 0), None);
+
+        // the limit for the total of decompressed text
+        let ztxt = [&b"Comment\0\0"[..], &compressed].concat();
+        let mut inflated_size = MAX_TEXT_SIZE - 4;
+        assert_eq!(parse_text(b"zTXt", &ztxt, &mut inflated_size), None);
+        assert_eq!(inflated_size, MAX_TEXT_SIZE);
+        let mut inflated_size = MAX_TEXT_SIZE - 7;
+        assert!(parse_text(b"zTXt", &ztxt, &mut inflated_size).is_some());
+        assert_eq!(inflated_size, MAX_TEXT_SIZE);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+/// Value of a metadata tag.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TagValue {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    /// JPEG image, e.g. cover art.
+    Jpeg(Vec<u8>),
+    /// PNG image, e.g. cover art.
+    Png(Vec<u8>),
+    /// BMP image, e.g. cover art.
+    Bmp(Vec<u8>),
+    /// Data of an unknown or application-specific type.
+    Binary(Vec<u8>),
+}
+
+impl TagValue {
+    /// Returns the value if it is text.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            TagValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it is an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            TagValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// iTunes-style tags of an MP4 file from `moov/udta/meta/ilst` and the QuickTime `©xxx` user data
+/// text atoms.
+///
+/// Keys are atom types, e.g. `©nam` for the title or `covr` for the cover art, or `----:mean:name`
+/// for freeform tags like `----:com.apple.iTunes:iTunSMPB`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ItunesTags {
+    pub tags: BTreeMap<String, TagValue>,
+}
+
+impl ItunesTags {
+    pub fn get(&self, key: &str) -> Option<&TagValue> {
+        self.tags.get(key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Returns the title (`©nam`).
+    pub fn title(&self) -> Option<&str> {
+        self.text("©nam")
+    }
+
+    /// Returns the artist (`©ART`).
+    pub fn artist(&self) -> Option<&str> {
+        self.text("©ART")
+    }
+
+    /// Returns the album (`©alb`).
+    pub fn album(&self) -> Option<&str> {
+        self.text("©alb")
+    }
+
+    /// Returns the comment (`©cmt`).
+    pub fn comment(&self) -> Option<&str> {
+        self.text("©cmt")
+    }
+
+    /// Returns the description (`desc`).
+    pub fn description(&self) -> Option<&str> {
+        self.text("desc")
+    }
+
+    /// Returns the genre name (`©gen`).
+    pub fn genre(&self) -> Option<&str> {
+        self.text("©gen")
+    }
+
+    /// Returns the release or recording date (`©day`) as stored, usually a year or an ISO 8601
+    /// date.
+    pub fn date(&self) -> Option<&str> {
+        self.text("©day")
+    }
+
+    /// Returns the cover art (`covr`), usually [`TagValue::Jpeg`] or [`TagValue::Png`].
+    pub fn cover(&self) -> Option<&TagValue> {
+        self.get("covr")
+    }
+
+    fn text(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(TagValue::as_text)
+    }
+}
+
+/// Tag from the Matroska `Tags` element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatroskaTag {
+    /// Tag name, e.g. `TITLE` or `DATE_RECORDED`.
+    pub name: String,
+    /// [`TagValue::Text`] for `TagString` or [`TagValue::Binary`] for `TagBinary`.
+    pub value: TagValue,
+    /// Language of the value as an ISO 639-2 code or a BCP 47 tag.
+    pub language: Option<String>,
+    pub target: MatroskaTarget,
+}
+
+/// Elements a [`MatroskaTag`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatroskaTarget {
+    /// Logical level of the target, from 70 (collection) over 50 (movie or episode, the default)
+    /// and 30 (track or chapter) down to 10 (shot). `None` for non-standard levels.
+    pub type_value: Option<u64>,
+    /// Informational name of the level, e.g. `MOVIE` or `ALBUM`.
+    pub target_type: Option<String>,
+    pub track_uids: Vec<u64>,
+    pub edition_uids: Vec<u64>,
+    pub chapter_uids: Vec<u64>,
+    pub attachment_uids: Vec<u64>,
+}
+
+impl Default for MatroskaTarget {
+    fn default() -> Self {
+        Self {
+            type_value: Some(50),
+            target_type: None,
+            track_uids: Vec::new(),
+            edition_uids: Vec::new(),
+            chapter_uids: Vec::new(),
+            attachment_uids: Vec::new(),
+        }
+    }
+}
+
+impl MatroskaTarget {
+    /// Returns `true` if the tag applies to the whole segment, i.e. targets the movie level
+    /// rather than a collection, a part or a scene, and no specific tracks, editions, chapters or
+    /// attachments.
+    pub fn is_global(&self) -> bool {
+        self.type_value == Some(50)
+            && self.track_uids.is_empty()
+            && self.edition_uids.is_empty()
+            && self.chapter_uids.is_empty()
+            && self.attachment_uids.is_empty()
+    }
+}
+
+/// Text entry of a PNG `tEXt`, `zTXt` or `iTXt` chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PngText {
+    /// Keyword, e.g. `Software`, `Creation Time` or `XML:com.adobe.xmp` for XMP metadata.
+    pub keyword: String,
+    /// Decompressed text.
+    pub text: String,
+    /// Language of international text (`iTXt`) as a BCP 47 tag.
+    pub language: Option<String>,
+    /// Keyword translated into the language of international text.
+    pub translated_keyword: Option<String>,
+}
@@ -0,0 +1,180 @@
+use crate::{detect, exif_helper, jpeg, mkv_helper, mp4_helper, png_helper, ContainerType, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
+
+/// Preview image embedded in a media file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub source: ThumbnailSource,
+    /// Media type of the image data, e.g. `image/jpeg`.
+    ///
+    /// HEIF thumbnail items are reported as `image/heic` or `image/avif`, but their data is the
+    /// coded image only, which must be combined with the decoder configuration of the item.
+    pub mime_type: String,
+    /// Width in pixels, 0 if unknown.
+    pub width: u64,
+    /// Height in pixels, 0 if unknown.
+    pub height: u64,
+    /// Positions of the image data in the stream, usually a single range.
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl Thumbnail {
+    /// Returns the size of the image data in bytes, saturating at `u64::MAX`.
+    pub fn size(&self) -> u64 {
+        self.ranges.iter().fold(0, |sum, range| {
+            sum.saturating_add(range.end.saturating_sub(range.start))
+        })
+    }
+
+    pub(crate) fn new(source: ThumbnailSource, mime_type: &str, range: Range<u64>) -> Self {
+        Self {
+            source,
+            mime_type: mime_type.to_owned(),
+            width: 0,
+            height: 0,
+            ranges: vec![range],
+        }
+    }
+}
+
+/// Where a [`Thumbnail`] is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ThumbnailSource {
+    /// JPEG thumbnail of the Exif `IFD1`.
+    ExifThumbnail,
+    /// Preview image in a TIFF `IFD0`, `SubIFD` or a JPEG embedded in a RAW file.
+    TiffPreview,
+    /// Preview image in the Nikon or Sony `MakerNote`.
+    MakerNotePreview,
+    /// HEIF item that is a `thmb` reference of another item.
+    HeifThumbnail,
+    /// MP4 `covr` cover art.
+    Mp4Cover,
+    /// Matroska attachment named like `cover.jpg`.
+    MatroskaCover,
+}
+
+/// Lists the embedded preview images of a file, largest first.
+///
+/// The container type is detected like in [`extract_file_metadata`](crate::extract_file_metadata).
+pub fn extract_file_thumbnails<P: AsRef<Path>>(file_path: P) -> Result<Vec<Thumbnail>> {
+    let file = File::open(&file_path)?;
+    let mut reader = BufReader::new(file);
+    let container_type = detect::detect_file_container_type(&mut reader, &file_path)?;
+    extract_thumbnails(reader, container_type)
+}
+
+/// Lists the embedded preview images of a stream, largest first.
+///
+/// Only the headers are read, use [`read_thumbnail`] to load the image data. The stream must be
+/// positioned at the start of the file.
+pub fn extract_thumbnails<R>(mut io: R, container_type: ContainerType) -> Result<Vec<Thumbnail>>
+where
+    R: io::BufRead + io::Seek,
+{
+    let mut thumbnails = match container_type {
+        ContainerType::Mp4 => mp4_helper::find_mp4_thumbnails(&mut io)?,
+        ContainerType::Mkv => mkv_helper::find_mkv_thumbnails(&mut io)?,
+        ContainerType::Avi | ContainerType::MpegTs => Vec::new(),
+        ContainerType::Png => png_helper::find_png_thumbnails(&mut io)?,
+        ContainerType::Exif(extension) => exif_helper::find_exif_thumbnails(&mut io, &extension)?,
+    };
+    // the lengths are read from the file and may point past its end
+    let len = io.seek(io::SeekFrom::End(0))?;
+    thumbnails.retain(|thumbnail| thumbnail.ranges.iter().all(|range| range.end <= len));
+    for thumbnail in &mut thumbnails {
+        if thumbnail.width == 0 || thumbnail.height == 0 {
+            (thumbnail.width, thumbnail.height) =
+                read_dimensions(&mut io, thumbnail)?.unwrap_or_default();
+        }
+    }
+    // the same image can be referenced more than once, e.g. from IFD0 and a SubIFD
+    let mut unique: Vec<Thumbnail> = Vec::new();
+    for thumbnail in thumbnails {
+        if !unique.iter().any(|other| other.ranges == thumbnail.ranges) {
+            unique.push(thumbnail);
+        }
+    }
+    unique.sort_by_key(|thumbnail| {
+        std::cmp::Reverse((thumbnail.width * thumbnail.height, thumbnail.size()))
+    });
+    Ok(unique)
+}
+
+/// Reads the image data of a thumbnail listed by [`extract_thumbnails`] for the same stream.
+pub fn read_thumbnail<R: io::Read + io::Seek>(mut io: R, thumbnail: &Thumbnail) -> Result<Vec<u8>> {
+    // the ranges come from the file, so the buffer only grows with the data actually read
+    let mut data = Vec::new();
+    for range in &thumbnail.ranges {
+        io.seek(io::SeekFrom::Start(range.start))?;
+        let len = range.end - range.start;
+        let read = io.by_ref().take(len).read_to_end(&mut data)?;
+        if (read as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+    }
+    Ok(data)
+}
+
+/// Reads the dimensions from the header of a JPEG or PNG image.
+fn read_dimensions<R: io::Read + io::Seek>(
+    io: &mut R,
+    thumbnail: &Thumbnail,
+) -> io::Result<Option<(u64, u64)>> {
+    let [range] = thumbnail.ranges.as_slice() else {
+        return Ok(None);
+    };
+    match thumbnail.mime_type.as_str() {
+        "image/jpeg" => {
+            Ok(jpeg::read_frame(io, range.clone())?.map(|frame| (frame.width, frame.height)))
+        }
+        "image/png" => {
+            // the signature is followed by the IHDR chunk, which starts with width and height
+            let mut header = [0u8; 24];
+            io.seek(io::SeekFrom::Start(range.start))?;
+            if range.end - range.start < 24 || io.read_exact(&mut header).is_err() {
+                return Ok(None);
+            }
+            if &header[12..16] != b"IHDR" {
+                return Ok(None);
+            }
+            let width = u32::from_be_bytes(header[16..20].try_into().unwrap());
+            let height = u32::from_be_bytes(header[20..24].try_into().unwrap());
+            Ok(Some((width as u64, height as u64)))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_thumbnail, Thumbnail, ThumbnailSource};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_size() {
+        let mut thumbnail = Thumbnail::new(ThumbnailSource::HeifThumbnail, "image/heic", 8..24);
+        thumbnail.ranges.push(100..104);
+        assert_eq!(thumbnail.size(), 20);
+        thumbnail.ranges = vec![0..1 << 63, 0..1 << 63];
+        assert_eq!(thumbnail.size(), u64::MAX);
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        // a length close to 4 GiB from a broken Exif thumbnail
+        let thumbnail =
+            Thumbnail::new(ThumbnailSource::ExifThumbnail, "image/jpeg", 8..0xFFFF_FFF0);
+        assert!(read_thumbnail(Cursor::new([0u8; 16]), &thumbnail).is_err());
+        let thumbnail = Thumbnail::new(ThumbnailSource::ExifThumbnail, "image/jpeg", 8..12);
+        assert_eq!(
+            read_thumbnail(Cursor::new([1u8; 16]), &thumbnail).unwrap(),
+            [1; 4]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Minimal TIFF reader used to locate preview images in IFDs that `kamadak-exif` does not parse,
+//! like `SubIFDs` and maker notes.
+
+use crate::{jpeg, Thumbnail, ThumbnailSource};
+use std::io;
+
+/// Upper bound for the number of entries read from a single IFD.
+const MAX_IFD_ENTRIES: u16 = 1000;
+/// Upper bound for the number of values read from a single entry.
+const MAX_VALUES: u32 = 1024;
+/// Nesting limit for `SubIFDs`, which can form loops in broken files.
+const MAX_DEPTH: usize = 4;
+
+const COMPRESSION: u16 = 0x0103;
+const STRIP_OFFSETS: u16 = 0x0111;
+const STRIP_BYTE_COUNTS: u16 = 0x0117;
+const SUB_IFDS: u16 = 0x014A;
+const JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+/// Full-size JPEG embedded in Panasonic RW2 files.
+const JPG_FROM_RAW: u16 = 0x002E;
+const EXIF_IFD: u16 = 0x8769;
+const MAKER_NOTE: u16 = 0x927C;
+const NIKON_PREVIEW_IFD: u16 = 0x0011;
+const SONY_PREVIEW_IMAGE: u16 = 0x2001;
+
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const UNDEFINED: u16 = 7;
+const IFD: u16 = 13;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// Value, or the offset of the value if it does not fit into 4 bytes.
+    value: [u8; 4],
+}
+
+struct Tiff<'a, R> {
+    io: &'a mut R,
+    /// Position of the TIFF header, which all offsets are relative to.
+    base: u64,
+    big_endian: bool,
+}
+
+/// Finds the JPEG previews of the TIFF structure at `base`: the Exif thumbnail in `IFD1`, previews
+/// in `SubIFDs` and chained IFDs, and the previews of Nikon and Sony maker notes.
+///
+/// `IFD0` holds the main image of regular TIFF files, so it is only searched for `raw` files.
+/// Lossless JPEG images, which hold the raw data of some formats, are skipped.
+pub(crate) fn find_previews<R: io::Read + io::Seek>(
+    io: &mut R,
+    base: u64,
+    raw: bool,
+) -> io::Result<Vec<Thumbnail>> {
+    let Some((mut tiff, mut offset)) = Tiff::open(io, base)? else {
+        return Ok(Vec::new());
+    };
+    let mut previews = Vec::new();
+    let mut visited = Vec::new();
+    let mut index = 0;
+    while offset != 0 && index < 8 && !visited.contains(&offset) {
+        visited.push(offset);
+        let Some((entries, next)) = tiff.read_ifd(offset)? else {
+            break;
+        };
+        let source = if index == 1 {
+            ThumbnailSource::ExifThumbnail
+        } else {
+            ThumbnailSource::TiffPreview
+        };
+        tiff.collect(
+            &entries,
+            source,
+            raw || index > 0,
+            &mut previews,
+            &mut visited,
+            0,
+        )?;
+        offset = next;
+        index += 1;
+    }
+
+    let mut valid = Vec::new();
+    for mut preview in previews {
+        let frame = jpeg::read_frame(tiff.io, preview.ranges[0].clone())?;
+        if let Some(frame) = frame.filter(|frame| !frame.lossless) {
+            preview.width = frame.width;
+            preview.height = frame.height;
+            valid.push(preview);
+        }
+    }
+    Ok(valid)
+}
+
+impl<'a, R: io::Read + io::Seek> Tiff<'a, R> {
+    /// Reads the TIFF header at `base` and returns the offset of `IFD0`. Any magic number is
+    /// accepted, as some RAW formats like ORF and RW2 use their own.
+    fn open(io: &'a mut R, base: u64) -> io::Result<Option<(Self, u32)>> {
+        io.seek(io::SeekFrom::Start(base))?;
+        let mut header = [0u8; 8];
+        if io.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let big_endian = match &header[0..2] {
+            b"II" => false,
+            b"MM" => true,
+            _ => return Ok(None),
+        };
+        let tiff = Self {
+            io,
+            base,
+            big_endian,
+        };
+        let ifd0 = tiff.u32(&header[4..8]);
+        Ok(Some((tiff, ifd0)))
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Reads the entries of the IFD at `offset` and the offset of the next IFD. Returns `None` if
+    /// the IFD is outside of the stream.
+    fn read_ifd(&mut self, offset: u32) -> io::Result<Option<(Vec<Entry>, u32)>> {
+        self.io
+            .seek(io::SeekFrom::Start(self.base + offset as u64))?;
+        let mut count = [0u8; 2];
+        if self.io.read_exact(&mut count).is_err() {
+            return Ok(None);
+        }
+        let count = self.u16(&count).min(MAX_IFD_ENTRIES) as usize;
+        let mut data = vec![0u8; count * 12 + 4];
+        if self.io.read_exact(&mut data).is_err() {
+            return Ok(None);
+        }
+        let entries = data
+            .chunks_exact(12)
+            .map(|entry| Entry {
+                tag: self.u16(&entry[0..2]),
+                field_type: self.u16(&entry[2..4]),
+                count: self.u32(&entry[4..8]),
+                value: entry[8..12].try_into().unwrap(),
+            })
+            .collect();
+        Ok(Some((entries, self.u32(&data[count * 12..]))))
+    }
+
+    /// Reads the values of an unsigned integer entry.
+    fn uints(&mut self, entry: &Entry) -> io::Result<Vec<u32>> {
+        let size = match entry.field_type {
+            SHORT => 2,
+            LONG | IFD => 4,
+            _ => return Ok(Vec::new()),
+        };
+        let len = entry.count.min(MAX_VALUES) as usize * size;
+        let data = if len <= 4 {
+            entry.value[..len].to_vec()
+        } else {
+            let offset = self.base + self.u32(&entry.value) as u64;
+            self.io.seek(io::SeekFrom::Start(offset))?;
+            let mut data = vec![0u8; len];
+            if self.io.read_exact(&mut data).is_err() {
+                return Ok(Vec::new());
+            }
+            data
+        };
+        Ok(data
+            .chunks_exact(size)
+            .map(|value| match size {
+                2 => self.u16(value) as u32,
+                _ => self.u32(value),
+            })
+            .collect())
+    }
+
+    fn uint(&mut self, entries: &[Entry], tag: u16) -> io::Result<Option<u32>> {
+        Ok(self.entry_values(entries, tag)?.first().copied())
+    }
+
+    fn entry_values(&mut self, entries: &[Entry], tag: u16) -> io::Result<Vec<u32>> {
+        match entries.iter().find(|entry| entry.tag == tag) {
+            Some(entry) => self.uints(entry),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn collect(
+        &mut self,
+        entries: &[Entry],
+        source: ThumbnailSource,
+        strips: bool,
+        previews: &mut Vec<Thumbnail>,
+        visited: &mut Vec<u32>,
+        depth: usize,
+    ) -> io::Result<()> {
+        let offset = self.uint(entries, JPEG_INTERCHANGE_FORMAT)?;
+        let len = self.uint(entries, JPEG_INTERCHANGE_FORMAT_LENGTH)?;
+        if let (Some(offset), Some(len)) = (offset, len) {
+            push_jpeg(previews, source, self.base + offset as u64, len as u64);
+        }
+
+        // JPEG compressed images stored in a single strip, e.g. DNG and CR2 previews
+        if strips && matches!(self.uint(entries, COMPRESSION)?, Some(6 | 7)) {
+            let offsets = self.entry_values(entries, STRIP_OFFSETS)?;
+            let counts = self.entry_values(entries, STRIP_BYTE_COUNTS)?;
+            if let ([offset], [len]) = (offsets.as_slice(), counts.as_slice()) {
+                push_jpeg(previews, source, self.base + *offset as u64, *len as u64);
+            }
+        }
+
+        if let Some(entry) = entries.iter().find(|entry| entry.tag == JPG_FROM_RAW) {
+            if entry.field_type == UNDEFINED {
+                let offset = self.base + self.u32(&entry.value) as u64;
+                push_jpeg(
+                    previews,
+                    ThumbnailSource::TiffPreview,
+                    offset,
+                    entry.count as u64,
+                );
+            }
+        }
+
+        if depth >= MAX_DEPTH {
+            return Ok(());
+        }
+        for offset in self.entry_values(entries, SUB_IFDS)? {
+            if visited.contains(&offset) {
+                continue;
+            }
+            visited.push(offset);
+            if let Some((entries, _)) = self.read_ifd(offset)? {
+                let source = ThumbnailSource::TiffPreview;
+                self.collect(&entries, source, true, previews, visited, depth + 1)?;
+            }
+        }
+        if let Some(offset) = self.uint(entries, EXIF_IFD)? {
+            let maker_note = match self.read_ifd(offset)? {
+                Some((entries, _)) => entries.into_iter().find(|entry| entry.tag == MAKER_NOTE),
+                None => None,
+            };
+            if let Some(maker_note) = maker_note {
+                self.collect_maker_note(&maker_note, previews)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_maker_note(
+        &mut self,
+        maker_note: &Entry,
+        previews: &mut Vec<Thumbnail>,
+    ) -> io::Result<()> {
+        let offset = self.u32(&maker_note.value);
+        let position = self.base + offset as u64;
+        self.io.seek(io::SeekFrom::Start(position))?;
+        let mut header = [0u8; 12];
+        if maker_note.count < 18 || self.io.read_exact(&mut header).is_err() {
+            return Ok(());
+        }
+        let source = ThumbnailSource::MakerNotePreview;
+        if header.starts_with(b"Nikon\0") {
+            // Nikon type 3 maker notes contain a TIFF structure with its own byte order, all
+            // offsets are relative to its header
+            let Some((mut nikon, ifd0)) = Tiff::open(&mut *self.io, position + 10)? else {
+                return Ok(());
+            };
+            let Some((entries, _)) = nikon.read_ifd(ifd0)? else {
+                return Ok(());
+            };
+            let Some(preview_ifd) = nikon.uint(&entries, NIKON_PREVIEW_IFD)? else {
+                return Ok(());
+            };
+            if let Some((entries, _)) = nikon.read_ifd(preview_ifd)? {
+                let offset = nikon.uint(&entries, JPEG_INTERCHANGE_FORMAT)?;
+                let len = nikon.uint(&entries, JPEG_INTERCHANGE_FORMAT_LENGTH)?;
+                if let (Some(offset), Some(len)) = (offset, len) {
+                    push_jpeg(previews, source, nikon.base + offset as u64, len as u64);
+                }
+            }
+        } else if header.starts_with(b"SONY DSC ") || header.starts_with(b"SONY CAM ") {
+            // Sony maker notes are an IFD after a 12-byte header, with offsets relative to the
+            // main TIFF header
+            let Some((entries, _)) = self.read_ifd(offset + 12)? else {
+                return Ok(());
+            };
+            if let Some(entry) = entries.iter().find(|entry| entry.tag == SONY_PREVIEW_IMAGE) {
+                let offset = self.base + self.u32(&entry.value) as u64;
+                push_jpeg(previews, source, offset, entry.count as u64);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn push_jpeg(previews: &mut Vec<Thumbnail>, source: ThumbnailSource, offset: u64, len: u64) {
+    if len > 0 {
+        previews.push(Thumbnail::new(source, "image/jpeg", offset..offset + len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_previews;
+    use crate::jpeg::tests::make_jpeg;
+    use crate::ThumbnailSource;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Tag, Value};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_exif_thumbnail() -> anyhow::Result<()> {
+        let jpeg = make_jpeg(160, 120, b"JFIF\0");
+        let make = Field {
+            tag: Tag::Make,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![b"Test".to_vec()]),
+        };
+        let mut writer = Writer::new();
+        writer.push_field(&make);
+        writer.set_jpeg(&jpeg, In::THUMBNAIL);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true)?;
+
+        // the TIFF structure is stored after some other data, like in a JPEG `APP1` segment
+        let mut file = vec![0u8; 10];
+        file.extend_from_slice(tiff.get_ref());
+        let previews = find_previews(&mut Cursor::new(&file), 10, false)?;
+        assert_eq!(previews.len(), 1);
+        let preview = &previews[0];
+        assert_eq!(preview.source, ThumbnailSource::ExifThumbnail);
+        assert_eq!((preview.width, preview.height), (160, 120));
+        let range = preview.ranges[0].start as usize..preview.ranges[0].end as usize;
+        assert_eq!(&file[range], jpeg.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_ifd_preview() -> anyhow::Result<()> {
+        let preview = make_jpeg(1024, 768, b"JFIF\0");
+        let mut lossless = make_jpeg(6000, 4000, b"JFIF\0");
+        // SOF3 marks the lossless raw data
+        let sof = lossless.len() - 14;
+        lossless[sof] = 0xC3;
+
+        // little endian TIFF with IFD0 pointing to two SubIFDs with a single JPEG strip each
+        let entry = |tag: u16, field_type: u16, count: u32, value: u32| {
+            [
+                &tag.to_le_bytes()[..],
+                &field_type.to_le_bytes(),
+                &count.to_le_bytes(),
+                &value.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let ifd = |entries: &[Vec<u8>]| {
+            [
+                &(entries.len() as u16).to_le_bytes()[..],
+                &entries.concat(),
+                &[0; 4],
+            ]
+            .concat()
+        };
+        let data_start = 8 + 18 + 8 + 2 * 42;
+        let preview_offset = data_start as u32;
+        let raw_offset = preview_offset + preview.len() as u32;
+        let strip_ifd = |offset: u32, len: usize| {
+            ifd(&[
+                entry(0x0103, 3, 1, 7),
+                entry(0x0111, 4, 1, offset),
+                entry(0x0117, 4, 1, len as u32),
+            ])
+        };
+        let tiff = [
+            b"II*\0".to_vec(),
+            8u32.to_le_bytes().to_vec(),
+            ifd(&[entry(0x014A, 4, 2, 8 + 18)]),
+            (8 + 18 + 8u32).to_le_bytes().to_vec(),
+            (8 + 18 + 8 + 42u32).to_le_bytes().to_vec(),
+            strip_ifd(preview_offset, preview.len()),
+            strip_ifd(raw_offset, lossless.len()),
+            preview.clone(),
+            lossless,
+        ]
+        .concat();
+
+        let previews = find_previews(&mut Cursor::new(&tiff), 0, true)?;
+        assert_eq!(previews.len(), 1);
+        assert_eq!(previews[0].source, ThumbnailSource::TiffPreview);
+        assert_eq!((previews[0].width, previews[0].height), (1024, 768));
+        assert_eq!(
+            previews[0].ranges,
+            vec![preview_offset as u64..raw_offset as u64]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{Codec, CodecInfo};
+
+/// Type of content carried by a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Subtitle,
+    /// Timecode, metadata and other tracks without audiovisual content.
+    Other,
+}
+
+/// Description of a single track (stream) of a media container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackInfo {
+    pub kind: TrackKind,
+    pub codec: CodecInfo,
+    /// Language as an ISO 639-2 code or a BCP 47 tag. `None` if undetermined.
+    pub language: Option<String>,
+    /// Human-readable track name, e.g. `Director's commentary`.
+    pub name: Option<String>,
+    /// Whether the track is meant to be played.
+    pub enabled: bool,
+    /// Whether the track is selected by default.
+    pub default: bool,
+    /// Whether the track must be played regardless of user preferences, e.g. forced subtitles.
+    pub forced: bool,
+    pub details: TrackDetails,
+}
+
+/// Properties specific to the kind of the track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackDetails {
+    Video(VideoDetails),
+    Audio(AudioDetails),
+    Subtitle(SubtitleDetails),
+    None,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VideoDetails {
+    /// Coded width in pixels.
+    pub width: u64,
+    /// Coded height in pixels.
+    pub height: u64,
+    pub frame_rate: Option<FrameRate>,
+    /// Whether frames have an alpha channel, like transparent VP8 and VP9 video in WebM.
+    pub alpha: bool,
+}
+
+/// Frame rate of a video track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameRate {
+    /// Frames per `denominator` seconds, e.g. `30000` for 29.97 fps.
+    pub numerator: u32,
+    pub denominator: u32,
+    /// Whether frames have varying durations. In this case the rate is the average rate.
+    pub variable: bool,
+}
+
+impl FrameRate {
+    /// Returns the frame rate in frames per second.
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Creates a reduced frame rate from a fraction.
+    pub(crate) fn from_ratio(numerator: u64, denominator: u64, variable: bool) -> Option<Self> {
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        Some(Self {
+            numerator: u32::try_from(numerator / divisor).ok()?,
+            denominator: u32::try_from(denominator / divisor).ok()?,
+            variable,
+        })
+    }
+
+    /// Creates a frame rate from frames per second, snapping to integer and NTSC (`n*1000/1001`)
+    /// rates, which are rounded when stored as a decimal number or a frame duration.
+    pub(crate) fn from_fps(fps: f64, variable: bool) -> Option<Self> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return None;
+        }
+        let ntsc = (fps * 1.001).round();
+        if (fps - fps.round()).abs() < 0.001 {
+            Self::from_ratio(fps.round() as u64, 1, variable)
+        } else if (fps - ntsc / 1.001).abs() < 0.001 {
+            Self::from_ratio(ntsc as u64 * 1000, 1001, variable)
+        } else {
+            Self::from_ratio((fps * 1000.0).round() as u64, 1000, variable)
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AudioDetails {
+    /// Sample rate in Hz.
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleDetails {
+    pub format: SubtitleFormat,
+}
+
+/// How subtitles are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubtitleFormat {
+    /// Plain or styled text, e.g. SubRip, ASS, WebVTT or timed text.
+    Text,
+    /// Pre-rendered images, e.g. PGS, VobSub or DVB subtitles.
+    Bitmap,
+    /// Closed captions, e.g. CEA-608.
+    ClosedCaptions,
+    Unknown,
+}
+
+impl SubtitleFormat {
+    pub(crate) fn from_codec(codec: Codec) -> Self {
+        match codec {
+            Codec::SubRip | Codec::Ass | Codec::WebVtt | Codec::TimedText | Codec::Ttml => {
+                Self::Text
+            }
+            Codec::Pgs | Codec::VobSub | Codec::DvbSubtitle => Self::Bitmap,
+            Codec::Cea608 => Self::ClosedCaptions,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl TrackInfo {
+    /// Creates a track without optional information. It is enabled and selected by default.
+    pub(crate) fn new(kind: TrackKind, codec: CodecInfo) -> Self {
+        let details = match kind {
+            TrackKind::Video => TrackDetails::Video(VideoDetails::default()),
+            TrackKind::Audio => TrackDetails::Audio(AudioDetails::default()),
+            TrackKind::Subtitle => TrackDetails::Subtitle(SubtitleDetails {
+                format: SubtitleFormat::from_codec(codec.codec),
+            }),
+            TrackKind::Other => TrackDetails::None,
+        };
+        Self {
+            kind,
+            codec,
+            language: None,
+            name: None,
+            enabled: true,
+            default: true,
+            forced: false,
+            details,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FrameRate;
+
+    #[test]
+    fn test_frame_rate() {
+        let rate = |fps| {
+            let rate = FrameRate::from_fps(fps, false).unwrap();
+            (rate.numerator, rate.denominator)
+        };
+        assert_eq!(rate(25.0), (25, 1));
+        assert_eq!(rate(29.97), (30000, 1001));
+        assert_eq!(rate(1e9 / 41_708_333.0), (24000, 1001));
+        assert_eq!(rate(59.94), (60000, 1001));
+        assert_eq!(rate(12.5), (25, 2));
+        assert_eq!(FrameRate::from_fps(0.0, false), None);
+
+        let rate = FrameRate::from_ratio(90000, 3003, false).unwrap();
+        assert_eq!((rate.numerator, rate.denominator), (30000, 1001));
+    }
+}
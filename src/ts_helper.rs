@@ -0,0 +1,495 @@
+//! Native support for MPEG transport streams, including AVCHD camcorder files (`.mts`, `.m2ts`).
+//!
+//! The program tables and the first access unit of every video stream are read from the start of
+//! the stream, the duration is computed from the presentation timestamps at both of its ends.
+
+mod mdpm;
+mod nal;
+
+use crate::detect::is_transport_stream;
+use crate::{
+    Codec, CodecInfo, CreationDate, CreationDateOptions, DateSource, FrameRate, MediaDateTime,
+    MetaData, Result, TrackDetails, TrackInfo, TrackKind,
+};
+use std::io;
+use std::io::Read;
+use std::time::Duration;
+
+const PACKET_SIZE: usize = 188;
+/// Number of bytes scanned from the start of the stream for the program tables and the first
+/// access units.
+const HEAD_SCAN_SIZE: u64 = 8 * 1024 * 1024;
+/// Number of bytes scanned from the end of the stream for the last timestamps.
+const TAIL_SCAN_SIZE: u64 = 1024 * 1024;
+/// Upper bound for the data collected from the first access unit of a video stream.
+const MAX_ACCESS_UNIT_SIZE: usize = 256 * 1024;
+/// Number of timestamps collected to compute the frame rate of streams without timing
+/// information.
+const MAX_TIMESTAMPS: usize = 32;
+/// Presentation timestamps are 33-bit values in units of 1/90000 s.
+const PTS_CLOCK: u64 = 90_000;
+const PTS_WRAP: u64 = 1 << 33;
+
+const PAT_PID: u16 = 0;
+const PAT_TABLE_ID: u8 = 0x00;
+const PMT_TABLE_ID: u8 = 0x02;
+const ISO_639_LANGUAGE_DESCRIPTOR: u8 = 0x0A;
+
+/// Extracts metadata from an MPEG transport stream with 188-byte packets, or 192-byte packets
+/// with a timestamp prefix as used by AVCHD and Blu-ray (`.m2ts`).
+///
+/// Dimensions and frame rate are read from the H.264 or HEVC sequence parameter set, the
+/// recording date from the `MDPM` user data that AVCHD camcorders store in the H.264 SEI.
+pub fn extract_ts_metadata<R: io::Read + io::Seek>(io: R) -> Result<MetaData> {
+    extract_ts_metadata_with_options(io, &CreationDateOptions::default())
+}
+
+pub(crate) fn extract_ts_metadata_with_options<R: io::Read + io::Seek>(
+    mut io: R,
+    options: &CreationDateOptions,
+) -> Result<MetaData> {
+    let ts = read_ts(&mut io)?;
+    let tracks: Vec<TrackInfo> = ts.streams.iter().map(convert_stream).collect();
+    let (width, height) = tracks
+        .iter()
+        .find_map(|track| match &track.details {
+            TrackDetails::Video(details) => Some((details.width, details.height)),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let creation_date = options
+        .select(&get_creation_dates(&ts))
+        .map(|date| date.date);
+
+    Ok(MetaData {
+        width,
+        height,
+        creation_date: creation_date.map(|date| date.to_system_time()),
+        creation_datetime: creation_date,
+        duration: get_duration(&ts),
+        tracks,
+        ..Default::default()
+    })
+}
+
+pub(crate) fn extract_ts_creation_dates<R: io::Read + io::Seek>(
+    mut io: R,
+) -> Result<Vec<CreationDate>> {
+    let ts = read_ts(&mut io)?;
+    Ok(get_creation_dates(&ts))
+}
+
+/// Programs and streams of a transport stream.
+#[derive(Debug, Default)]
+struct TransportStream {
+    /// PIDs of the program map tables, and whether each has been parsed.
+    pmt_pids: Vec<(u16, bool)>,
+    streams: Vec<Stream>,
+    /// Last presentation timestamp of the first stream, found at the end of the file.
+    last_pts: Option<u64>,
+}
+
+/// Elementary stream listed in a program map table.
+#[derive(Debug, Default)]
+struct Stream {
+    pid: u16,
+    stream_type: u8,
+    /// Tags of the descriptors of the stream, which identify the codec of private data streams.
+    descriptor_tags: Vec<u8>,
+    language: Option<String>,
+    /// Number of PES packets started so far.
+    pes_packets: usize,
+    /// Presentation timestamps of the first PES packets, in stream order.
+    timestamps: Vec<u64>,
+    /// Data of the first access unit, collected from the first PES packet.
+    access_unit: Vec<u8>,
+    /// Whether the first PES packet is complete.
+    access_unit_done: bool,
+    sps: Option<nal::Sps>,
+    recording_date: Option<MediaDateTime>,
+}
+
+impl Stream {
+    fn codec(&self) -> CodecInfo {
+        CodecInfo::from_ts_stream_type(self.stream_type, &self.descriptor_tags)
+    }
+
+    fn is_video(&self) -> bool {
+        stream_kind(self.stream_type, self.codec().codec) == TrackKind::Video
+    }
+
+    fn is_complete(&self) -> bool {
+        (!self.is_video() || self.access_unit_done) && self.timestamps.len() >= MAX_TIMESTAMPS
+    }
+}
+
+/// Size of the packets and offset of the sync byte, which is 4 for 192-byte packets.
+#[derive(Debug, Clone, Copy)]
+struct PacketLayout {
+    size: usize,
+    offset: usize,
+}
+
+fn read_ts<R: io::Read + io::Seek>(io: &mut R) -> io::Result<TransportStream> {
+    let start = io.stream_position()?;
+    let mut prefix = Vec::new();
+    io.by_ref().take(3 * 192).read_to_end(&mut prefix)?;
+    let layout = if is_transport_stream(&prefix, PACKET_SIZE, 0) {
+        PacketLayout {
+            size: PACKET_SIZE,
+            offset: 0,
+        }
+    } else if is_transport_stream(&prefix, PACKET_SIZE + 4, 4) {
+        PacketLayout {
+            size: PACKET_SIZE + 4,
+            offset: 4,
+        }
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an MPEG transport stream",
+        ));
+    };
+
+    let mut ts = TransportStream::default();
+    io.seek(io::SeekFrom::Start(start))?;
+    let mut packet = vec![0u8; layout.size];
+    let mut position = start;
+    while position < start + HEAD_SCAN_SIZE {
+        if !read_packet(io, &mut packet)? {
+            break;
+        }
+        position += layout.size as u64;
+        let Some(packet) = Packet::parse(&packet[layout.offset..]) else {
+            continue;
+        };
+        handle_packet(&mut ts, &packet);
+        let tables_done = !ts.pmt_pids.is_empty() && ts.pmt_pids.iter().all(|(_, done)| *done);
+        if tables_done && ts.streams.iter().all(Stream::is_complete) {
+            break;
+        }
+    }
+    for stream in &mut ts.streams {
+        parse_access_unit(stream);
+    }
+
+    // the last timestamps of the first stream, which is usually the video stream
+    if let Some(pid) = ts.streams.first().map(|stream| stream.pid) {
+        let end = io.seek(io::SeekFrom::End(0))?;
+        let packets = end.saturating_sub(start).saturating_sub(TAIL_SCAN_SIZE) / layout.size as u64;
+        io.seek(io::SeekFrom::Start(start + packets * layout.size as u64))?;
+        while read_packet(io, &mut packet)? {
+            let Some(packet) = Packet::parse(&packet[layout.offset..]) else {
+                continue;
+            };
+            if packet.pid != pid || !packet.unit_start {
+                continue;
+            }
+            if let Some((Some(pts), _)) = parse_pes_header(packet.payload) {
+                ts.last_pts = Some(ts.last_pts.map_or(pts, |last| last.max(pts)));
+            }
+        }
+    }
+    Ok(ts)
+}
+
+/// Reads the next packet, returning `false` at the end of the stream.
+fn read_packet<R: io::Read>(io: &mut R, packet: &mut [u8]) -> io::Result<bool> {
+    match io.read_exact(packet) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Transport stream packet without its adaptation field.
+struct Packet<'a> {
+    pid: u16,
+    /// Whether a PES packet or a table section starts in this packet.
+    unit_start: bool,
+    payload: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if *data.first()? != 0x47 {
+            return None;
+        }
+        let unit_start = data[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([data[1] & 0x1F, data[2]]);
+        let adaptation_field_control = (data[3] >> 4) & 0x03;
+        let payload_start = match adaptation_field_control {
+            0b01 => 4,
+            0b11 => 5 + *data.get(4)? as usize,
+            // no payload
+            _ => return None,
+        };
+        Some(Self {
+            pid,
+            unit_start,
+            payload: data.get(payload_start..)?,
+        })
+    }
+}
+
+fn handle_packet(ts: &mut TransportStream, packet: &Packet) {
+    if packet.pid == PAT_PID {
+        if packet.unit_start && ts.pmt_pids.is_empty() {
+            ts.pmt_pids = parse_pat(packet.payload)
+                .into_iter()
+                .map(|pid| (pid, false))
+                .collect();
+        }
+        return;
+    }
+    if let Some((_, done)) = ts.pmt_pids.iter_mut().find(|(pid, _)| *pid == packet.pid) {
+        if packet.unit_start && !*done {
+            if let Some(streams) = parse_pmt(packet.payload) {
+                *done = true;
+                for stream in streams {
+                    if !ts.streams.iter().any(|other| other.pid == stream.pid) {
+                        ts.streams.push(stream);
+                    }
+                }
+            }
+        }
+        return;
+    }
+    let Some(stream) = ts
+        .streams
+        .iter_mut()
+        .find(|stream| stream.pid == packet.pid)
+    else {
+        return;
+    };
+    let mut data = packet.payload;
+    if packet.unit_start {
+        let Some((pts, header_len)) = parse_pes_header(data) else {
+            return;
+        };
+        if let Some(pts) = pts.filter(|_| stream.timestamps.len() < MAX_TIMESTAMPS) {
+            stream.timestamps.push(pts);
+        }
+        if stream.pes_packets > 0 {
+            stream.access_unit_done = true;
+        }
+        stream.pes_packets += 1;
+        data = &data[header_len..];
+    } else if stream.pes_packets == 0 {
+        // the stream was joined in the middle of a PES packet
+        return;
+    }
+    if stream.is_video() && !stream.access_unit_done {
+        let len = data
+            .len()
+            .min(MAX_ACCESS_UNIT_SIZE - stream.access_unit.len());
+        stream.access_unit.extend_from_slice(&data[..len]);
+        stream.access_unit_done = stream.access_unit.len() >= MAX_ACCESS_UNIT_SIZE;
+    }
+}
+
+/// Returns the section data that follows the pointer field of a packet that starts a section.
+fn section(payload: &[u8], table_id: u8) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    if 1 + pointer >= payload.len() {
+        return None;
+    }
+    let section = &payload[1 + pointer..];
+    if section[0] != table_id {
+        return None;
+    }
+    let section_length =
+        (u16::from_be_bytes([*section.get(1)?, *section.get(2)?]) & 0x0FFF) as usize;
+    // skip the header and the CRC
+    section.get(8..(3 + section_length).checked_sub(4)?)
+}
+
+/// Returns the PIDs of the program map tables from the program association table.
+fn parse_pat(payload: &[u8]) -> Vec<u16> {
+    let Some(programs) = section(payload, PAT_TABLE_ID) else {
+        return Vec::new();
+    };
+    programs
+        .chunks_exact(4)
+        // program 0 is the network information table
+        .filter(|program| program[0..2] != [0, 0])
+        .map(|program| u16::from_be_bytes([program[2] & 0x1F, program[3]]))
+        .collect()
+}
+
+/// Returns the elementary streams of a program map table.
+fn parse_pmt(payload: &[u8]) -> Option<Vec<Stream>> {
+    let data = section(payload, PMT_TABLE_ID)?;
+    let program_info_length =
+        (u16::from_be_bytes([*data.get(2)?, *data.get(3)?]) & 0x0FFF) as usize;
+    let mut rest = data.get(4 + program_info_length..)?;
+    let mut streams = Vec::new();
+    while rest.len() >= 5 {
+        let stream_type = rest[0];
+        let pid = u16::from_be_bytes([rest[1] & 0x1F, rest[2]]);
+        let es_info_length = (u16::from_be_bytes([rest[3], rest[4]]) & 0x0FFF) as usize;
+        let descriptors = rest.get(5..5 + es_info_length)?;
+        rest = &rest[5 + es_info_length..];
+
+        let mut stream = Stream {
+            pid,
+            stream_type,
+            ..Default::default()
+        };
+        let mut descriptors = descriptors;
+        while let [tag, len, data @ ..] = descriptors {
+            let Some(value) = data.get(..*len as usize) else {
+                break;
+            };
+            stream.descriptor_tags.push(*tag);
+            if *tag == ISO_639_LANGUAGE_DESCRIPTOR {
+                stream.language = value
+                    .get(0..3)
+                    .map(|code| String::from_utf8_lossy(code).into_owned())
+                    .filter(|code| code.chars().all(|c| c.is_ascii_alphabetic()) && code != "und");
+            }
+            descriptors = &data[*len as usize..];
+        }
+        streams.push(stream);
+    }
+    Some(streams)
+}
+
+/// Parses the header of a PES packet, returning its presentation timestamp and the length of
+/// the header.
+fn parse_pes_header(data: &[u8]) -> Option<(Option<u64>, usize)> {
+    if data.get(0..3)? != [0, 0, 1] {
+        return None;
+    }
+    let flags = *data.get(7)?;
+    let header_len = (9 + *data.get(8)? as usize).min(data.len());
+    if flags & 0x80 == 0 {
+        return Some((None, header_len));
+    }
+    let pts = data.get(9..14)?;
+    let pts = ((pts[0] as u64 >> 1) & 0x07) << 30
+        | (pts[1] as u64) << 22
+        | (pts[2] as u64 >> 1) << 15
+        | (pts[3] as u64) << 7
+        | pts[4] as u64 >> 1;
+    Some((Some(pts), header_len))
+}
+
+/// Reads the sequence parameter set and the `MDPM` recording date from the first access unit.
+fn parse_access_unit(stream: &mut Stream) {
+    let codec = stream.codec().codec;
+    for unit in nal::nal_units(&stream.access_unit) {
+        let (nal_type, header_len) = match codec {
+            Codec::H264 => (unit[0] & 0x1F, 1),
+            Codec::Hevc => ((unit[0] >> 1) & 0x3F, 2),
+            _ => return,
+        };
+        match (codec, nal_type) {
+            (Codec::H264, 7) | (Codec::Hevc, 33) if stream.sps.is_none() => {
+                let rbsp = nal::unescape(unit);
+                stream.sps = match codec {
+                    Codec::H264 => nal::parse_h264_sps(&rbsp),
+                    _ => nal::parse_hevc_sps(&rbsp),
+                };
+            }
+            // SEI, or prefix SEI for HEVC
+            (Codec::H264, 6) | (Codec::Hevc, 39) if stream.recording_date.is_none() => {
+                let rbsp = nal::unescape(unit);
+                stream.recording_date = rbsp.get(header_len..).and_then(mdpm::parse_sei);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn stream_kind(stream_type: u8, codec: Codec) -> TrackKind {
+    match codec {
+        Codec::H264 | Codec::Hevc | Codec::Mpeg2Video | Codec::Mpeg1Video | Codec::Mpeg4Visual => {
+            TrackKind::Video
+        }
+        Codec::Aac
+        | Codec::Mp3
+        | Codec::MpegAudio
+        | Codec::Ac3
+        | Codec::Eac3
+        | Codec::Dts
+        | Codec::Pcm => TrackKind::Audio,
+        Codec::Pgs | Codec::DvbSubtitle => TrackKind::Subtitle,
+        // Blu-ray text subtitles
+        _ if stream_type == 0x92 => TrackKind::Subtitle,
+        _ => TrackKind::Other,
+    }
+}
+
+fn convert_stream(stream: &Stream) -> TrackInfo {
+    let codec = stream.codec();
+    let mut info = TrackInfo::new(stream_kind(stream.stream_type, codec.codec), codec);
+    info.language = stream.language.clone();
+    if let TrackDetails::Video(details) = &mut info.details {
+        if let Some(sps) = &stream.sps {
+            details.width = sps.width;
+            details.height = sps.height;
+            details.frame_rate = sps.frame_rate;
+        }
+        if details.frame_rate.is_none() {
+            details.frame_rate = frame_duration(&stream.timestamps)
+                .and_then(|duration| FrameRate::from_ratio(PTS_CLOCK, duration, false));
+        }
+    }
+    info
+}
+
+/// Returns the smallest difference between the timestamps of consecutive PES packets, which is
+/// the frame duration if every PES packet holds a frame. The packets are sorted first, as
+/// B-frames are stored out of presentation order.
+fn frame_duration(timestamps: &[u64]) -> Option<u64> {
+    let mut timestamps = timestamps.to_vec();
+    timestamps.sort_unstable();
+    timestamps
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|duration| *duration > 0)
+        .min()
+}
+
+/// Computes the duration from the first and last presentation timestamps of the first stream,
+/// plus the duration of the last frame.
+fn get_duration(ts: &TransportStream) -> Option<Duration> {
+    let stream = ts.streams.first()?;
+    let first = *stream.timestamps.iter().min()?;
+    let last = ts.last_pts?;
+    let frame = match stream.sps.and_then(|sps| sps.frame_rate) {
+        Some(rate) => PTS_CLOCK as u128 * rate.denominator as u128 / rate.numerator as u128,
+        None => frame_duration(&stream.timestamps).unwrap_or_default() as u128,
+    };
+    // timestamps wrap around after about 26.5 hours
+    let ticks = ((last + PTS_WRAP - first) % PTS_WRAP) as u128 + frame;
+    let nanos = u64::try_from(ticks * 1_000_000_000 / PTS_CLOCK as u128).ok()?;
+    Some(Duration::from_nanos(nanos)).filter(|d| !d.is_zero())
+}
+
+fn get_creation_dates(ts: &TransportStream) -> Vec<CreationDate> {
+    ts.streams
+        .iter()
+        .find_map(|stream| stream.recording_date)
+        .map(|date| CreationDate::new(date, DateSource::AvchdDateTimeOriginal))
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{section, PAT_TABLE_ID};
+
+    #[test]
+    fn test_truncated_section() {
+        // the pointer field leaves a single byte of the section in the packet
+        let mut payload = [0u8; 184];
+        payload[0] = 182;
+        assert_eq!(section(&payload, PAT_TABLE_ID), None);
+        payload[0] = 183;
+        assert_eq!(section(&payload, PAT_TABLE_ID), None);
+        payload[0] = 255;
+        assert_eq!(section(&payload, PAT_TABLE_ID), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Modified digital video pack metadata (`MDPM`) of AVCHD camcorders, stored as unregistered user
+//! data in the SEI of the first access unit.
+
+use crate::MediaDateTime;
+use chrono::{FixedOffset, NaiveDate};
+
+/// SEI payload type of unregistered user data.
+const USER_DATA_UNREGISTERED: u32 = 5;
+/// UUID that precedes the `MDPM` signature.
+const MDPM_UUID: [u8; 16] = [
+    0x17, 0xEE, 0x8C, 0x60, 0xF8, 0x4D, 0x11, 0xD9, 0x8C, 0xD6, 0x08, 0x00, 0x20, 0x0C, 0x9A, 0x66,
+];
+/// Time zone, year and month of the recording time.
+const DATE_TIME_ORIGINAL_1: u8 = 0x18;
+/// Day, hour, minute and second of the recording time.
+const DATE_TIME_ORIGINAL_2: u8 = 0x19;
+
+/// Returns the recording time from the SEI messages of an unescaped SEI NAL unit without its
+/// header.
+pub(super) fn parse_sei(rbsp: &[u8]) -> Option<MediaDateTime> {
+    let mut rest = rbsp;
+    // stop at the trailing bits
+    while rest.len() > 1 {
+        let (payload_type, len) = read_sei_value(rest)?;
+        rest = &rest[len..];
+        let (payload_size, len) = read_sei_value(rest)?;
+        rest = &rest[len..];
+        let payload = rest.get(..payload_size as usize)?;
+        rest = &rest[payload_size as usize..];
+        if payload_type == USER_DATA_UNREGISTERED {
+            if let Some(mdpm) = payload.strip_prefix(&MDPM_UUID[..]) {
+                if let Some(date) = parse_mdpm(mdpm) {
+                    return Some(date);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Reads an SEI payload type or size, which is coded as a sum of bytes, `0xFF` meaning that
+/// another byte follows.
+fn read_sei_value(data: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0;
+    for (index, &byte) in data.iter().enumerate() {
+        value += byte as u32;
+        if byte != 0xFF {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+/// Parses the `MDPM` signature, followed by the number of entries and entries of a tag and four
+/// bytes of data.
+fn parse_mdpm(data: &[u8]) -> Option<MediaDateTime> {
+    let data = data.strip_prefix(b"MDPM")?;
+    let count = *data.first()? as usize;
+    let entries = data.get(1..1 + count * 5)?;
+    let entry = |tag| {
+        entries
+            .chunks_exact(5)
+            .find(|entry| entry[0] == tag)
+            .map(|entry| &entry[1..])
+    };
+    parse_date_time(entry(DATE_TIME_ORIGINAL_1)?, entry(DATE_TIME_ORIGINAL_2)?)
+}
+
+/// Decodes the recording time, which is stored as BCD digits in local time, together with the
+/// time zone byte: bit 5 is the sign, bits 1 to 4 the hours and bit 0 an additional half hour of
+/// the offset, bit 6 is set if daylight saving time is in effect.
+fn parse_date_time(first: &[u8], second: &[u8]) -> Option<MediaDateTime> {
+    let year = bcd(first[1])? as i32 * 100 + bcd(first[2])? as i32;
+    let date = NaiveDate::from_ymd_opt(year, bcd(first[3])?, bcd(second[0])?)?;
+    let datetime = date.and_hms_opt(bcd(second[1])?, bcd(second[2])?, bcd(second[3])?)?;
+
+    let time_zone = first[0];
+    // unset on some models
+    if time_zone == 0xFF {
+        return Some(MediaDateTime::floating(datetime));
+    }
+    let mut minutes = ((time_zone >> 1) & 0x0F) as i32 * 60;
+    if time_zone & 0x01 != 0 {
+        minutes += 30;
+    }
+    if time_zone & 0x20 != 0 {
+        minutes = -minutes;
+    }
+    if time_zone & 0x40 != 0 {
+        minutes += 60;
+    }
+    let offset = FixedOffset::east_opt(minutes * 60)?;
+    Some(MediaDateTime::fixed(datetime, offset))
+}
+
+fn bcd(byte: u8) -> Option<u32> {
+    let (high, low) = (byte >> 4, byte & 0x0F);
+    (high < 10 && low < 10).then_some(high as u32 * 10 + low as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sei, MDPM_UUID};
+    use crate::MediaDateTime;
+
+    /// Builds an SEI NAL unit payload with an `MDPM` message holding the recording time.
+    fn make_mdpm_sei(time_zone: u8, date: [u8; 3], time: [u8; 4]) -> Vec<u8> {
+        let [year_high, year_low, month] = date;
+        let mut mdpm = MDPM_UUID.to_vec();
+        mdpm.extend_from_slice(b"MDPM");
+        mdpm.push(3);
+        mdpm.extend_from_slice(&[0x18, time_zone, year_high, year_low, month]);
+        mdpm.push(0x19);
+        mdpm.extend_from_slice(&time);
+        // maker code
+        mdpm.extend_from_slice(&[0xE0, 0x01, 0x08, 0x00, 0x00]);
+        let mut sei = vec![5, mdpm.len() as u8];
+        sei.extend(mdpm);
+        sei.push(0x80);
+        sei
+    }
+
+    #[test]
+    fn test_parse_sei() {
+        // UTC+1 with daylight saving time
+        let sei = make_mdpm_sei(0x42, [0x20, 0x13, 0x11], [0x09, 0x16, 0x07, 0x11]);
+        let date = parse_sei(&sei).unwrap();
+        assert_eq!(
+            date,
+            MediaDateTime::parse_iso8601("2013-11-09T16:07:11+02:00").unwrap()
+        );
+
+        let sei = make_mdpm_sei(0x29, [0x20, 0x13, 0x11], [0x09, 0x16, 0x07, 0x11]);
+        let date = parse_sei(&sei).unwrap();
+        assert_eq!(
+            date,
+            MediaDateTime::parse_iso8601("2013-11-09T16:07:11-04:30").unwrap()
+        );
+
+        let sei = make_mdpm_sei(0xFF, [0x20, 0x13, 0x11], [0x09, 0x16, 0x07, 0x11]);
+        let date = parse_sei(&sei).unwrap();
+        assert_eq!(
+            date,
+            MediaDateTime::parse_iso8601("2013-11-09T16:07:11").unwrap()
+        );
+        assert_eq!(parse_sei(&[5, 2, 0, 0, 0x80]), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! NAL unit splitting and sequence parameter set parsing for H.264 and HEVC video streams.
+
+use crate::bits::BitReader;
+use crate::FrameRate;
+
+/// Values read from a sequence parameter set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Sps {
+    /// Width in pixels after cropping.
+    pub width: u64,
+    /// Height in pixels after cropping.
+    pub height: u64,
+    /// Frame rate from the VUI timing information, if present.
+    pub frame_rate: Option<FrameRate>,
+}
+
+/// Iterates over the NAL units of an Annex B byte stream, without their start codes.
+pub(super) fn nal_units(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = data;
+    std::iter::from_fn(move || loop {
+        let start = find_start_code(rest)? + 3;
+        rest = &rest[start..];
+        let end = find_start_code(rest).unwrap_or(rest.len());
+        // the zero byte of a 4-byte start code belongs to the next start code
+        let nal = &rest[..end];
+        let nal = &nal[..nal.len() - nal.iter().rev().take_while(|byte| **byte == 0).count()];
+        if !nal.is_empty() {
+            return Some(nal);
+        }
+    })
+}
+
+fn find_start_code(data: &[u8]) -> Option<usize> {
+    data.windows(3).position(|window| window == [0, 0, 1])
+}
+
+/// Removes the emulation prevention bytes (`00 00 03`) from a NAL unit.
+pub(super) fn unescape(nal: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
+
+/// Parses an H.264 sequence parameter set, given the unescaped NAL unit including its header.
+pub(super) fn parse_h264_sps(rbsp: &[u8]) -> Option<Sps> {
+    let mut reader = BitReader::new(rbsp.get(1..)?);
+    let profile_idc = reader.bits(8)?;
+    reader.skip(16)?; // constraint flags and level
+    reader.ue()?; // seq_parameter_set_id
+    let mut chroma_format_idc = 1;
+    let mut separate_colour_plane = false;
+    if matches!(
+        profile_idc,
+        100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+    ) {
+        chroma_format_idc = reader.ue()?;
+        if chroma_format_idc == 3 {
+            separate_colour_plane = reader.flag()?;
+        }
+        reader.ue()?; // bit_depth_luma_minus8
+        reader.ue()?; // bit_depth_chroma_minus8
+        reader.skip(1)?; // qpprime_y_zero_transform_bypass_flag
+        if reader.flag()? {
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for index in 0..lists {
+                if reader.flag()? {
+                    skip_scaling_list(&mut reader, if index < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+    reader.ue()?; // log2_max_frame_num_minus4
+    match reader.ue()? {
+        0 => {
+            reader.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            reader.skip(1)?; // delta_pic_order_always_zero_flag
+            reader.se()?; // offset_for_non_ref_pic
+            reader.se()?; // offset_for_top_to_bottom_field
+            for _ in 0..reader.ue()? {
+                reader.se()?; // offset_for_ref_frame
+            }
+        }
+        _ => {}
+    }
+    reader.ue()?; // max_num_ref_frames
+    reader.skip(1)?; // gaps_in_frame_num_value_allowed_flag
+    let width_in_mbs = reader.ue()? as u64 + 1;
+    let height_in_map_units = reader.ue()? as u64 + 1;
+    let frame_mbs_only = reader.flag()?;
+    if !frame_mbs_only {
+        reader.skip(1)?; // mb_adaptive_frame_field_flag
+    }
+    reader.skip(1)?; // direct_8x8_inference_flag
+    let field_factor = if frame_mbs_only { 1 } else { 2 };
+    let mut width = width_in_mbs * 16;
+    let mut height = height_in_map_units * 16 * field_factor;
+    if reader.flag()? {
+        let (crop_x, crop_y) = match (chroma_format_idc, separate_colour_plane) {
+            (0, _) | (3, true) => (1, field_factor),
+            (1, _) => (2, 2 * field_factor),
+            (2, _) => (2, field_factor),
+            _ => (1, field_factor),
+        };
+        let (left, right) = (reader.ue()? as u64, reader.ue()? as u64);
+        let (top, bottom) = (reader.ue()? as u64, reader.ue()? as u64);
+        width = width.checked_sub(crop_x * (left + right))?;
+        height = height.checked_sub(crop_y * (top + bottom))?;
+    }
+    let frame_rate = if reader.flag()? {
+        parse_h264_vui_frame_rate(&mut reader)
+    } else {
+        None
+    };
+    Some(Sps {
+        width,
+        height,
+        frame_rate,
+    })
+}
+
+fn skip_scaling_list(reader: &mut BitReader, size: usize) -> Option<()> {
+    let mut last_scale = 8;
+    let mut next_scale = 8;
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta_scale = reader.se()?;
+            // the range is limited to -128..=127, which rules out overflows
+            if !(-128..=127).contains(&delta_scale) {
+                return None;
+            }
+            next_scale = (last_scale + delta_scale + 256) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Some(())
+}
+
+/// Reads the frame rate from the timing information of the VUI parameters.
+fn parse_h264_vui_frame_rate(reader: &mut BitReader) -> Option<FrameRate> {
+    if reader.flag()? {
+        // aspect_ratio_idc, followed by the sample aspect ratio for `Extended_SAR`
+        if reader.bits(8)? == 255 {
+            reader.skip(32)?;
+        }
+    }
+    if reader.flag()? {
+        reader.skip(1)?; // overscan_appropriate_flag
+    }
+    if reader.flag()? {
+        reader.skip(4)?; // video_format and video_full_range_flag
+        if reader.flag()? {
+            reader.skip(24)?; // colour primaries, transfer characteristics and matrix
+        }
+    }
+    if reader.flag()? {
+        reader.ue()?; // chroma_sample_loc_type_top_field
+        reader.ue()?; // chroma_sample_loc_type_bottom_field
+    }
+    if !reader.flag()? {
+        return None;
+    }
+    let num_units_in_tick = reader.bits(32)?;
+    let time_scale = reader.bits(32)?;
+    let fixed_frame_rate = reader.flag()?;
+    // a frame consists of two fields, which are the unit of the clock tick
+    FrameRate::from_ratio(
+        time_scale as u64,
+        2 * num_units_in_tick as u64,
+        !fixed_frame_rate,
+    )
+}
+
+/// Parses the dimensions from an HEVC sequence parameter set, given the unescaped NAL unit
+/// including its header. The VUI is not read, as it follows the reference picture sets.
+pub(super) fn parse_hevc_sps(rbsp: &[u8]) -> Option<Sps> {
+    let mut reader = BitReader::new(rbsp.get(2..)?);
+    reader.skip(4)?; // sps_video_parameter_set_id
+    let max_sub_layers_minus1 = reader.bits(3)?;
+    reader.skip(1)?; // sps_temporal_id_nesting_flag
+
+    // profile_tier_level: general profile and level, then optional sub-layer profiles and levels
+    reader.skip(96)?;
+    let mut sub_layers = Vec::new();
+    for _ in 0..max_sub_layers_minus1 {
+        sub_layers.push((reader.flag()?, reader.flag()?));
+    }
+    if max_sub_layers_minus1 > 0 {
+        reader.skip(2 * (8 - max_sub_layers_minus1 as usize))?;
+    }
+    for (profile_present, level_present) in sub_layers {
+        if profile_present {
+            reader.skip(88)?;
+        }
+        if level_present {
+            reader.skip(8)?;
+        }
+    }
+
+    reader.ue()?; // sps_seq_parameter_set_id
+    let chroma_format_idc = reader.ue()?;
+    let separate_colour_plane = chroma_format_idc == 3 && reader.flag()?;
+    let mut width = reader.ue()? as u64;
+    let mut height = reader.ue()? as u64;
+    if reader.flag()? {
+        let (sub_width, sub_height) = match (chroma_format_idc, separate_colour_plane) {
+            (1, _) => (2, 2),
+            (2, _) => (2, 1),
+            _ => (1, 1),
+        };
+        let (left, right) = (reader.ue()? as u64, reader.ue()? as u64);
+        let (top, bottom) = (reader.ue()? as u64, reader.ue()? as u64);
+        width = width.checked_sub(sub_width * (left + right))?;
+        height = height.checked_sub(sub_height * (top + bottom))?;
+    }
+    Some(Sps {
+        width,
+        height,
+        frame_rate: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nal_units, parse_h264_sps, parse_hevc_sps, skip_scaling_list, unescape};
+    use crate::bits::BitReader;
+
+    #[test]
+    fn test_nal_units() {
+        let stream = [
+            0, 0, 0, 1, 0x09, 0xF0, 0, 0, 1, 0x67, 1, 0, 0, 3, 1, 0, 0, 0, 1, 0x68,
+        ];
+        let units: Vec<&[u8]> = nal_units(&stream).collect();
+        assert_eq!(units, [&[0x09, 0xF0][..], &[0x67, 1, 0, 0, 3, 1], &[0x68]]);
+        assert_eq!(unescape(units[1]), [0x67, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_h264_sps() {
+        // 1920x1080 interlaced High profile with 50 fields per second, as written by AVCHD
+        // camcorders
+        let sps = [
+            0x67, 0x64, 0x00, 0x28, 0xAC, 0xD9, 0x40, 0x78, 0x04, 0x4F, 0xDE, 0x02, 0x20, 0x00,
+            0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x06, 0x58,
+        ];
+        let sps = parse_h264_sps(&unescape(&sps)).unwrap();
+        assert_eq!((sps.width, sps.height), (1920, 1080));
+        let frame_rate = sps.frame_rate.unwrap();
+        assert_eq!((frame_rate.numerator, frame_rate.denominator), (25, 1));
+    }
+
+    #[test]
+    fn test_hevc_sps() {
+        // 1920x1080 Main profile, coded as 1920x1088 with a conformance window
+        let sps = [
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x03, 0x00, 0x78, 0xA0, 0x03, 0xC0, 0x80, 0x11, 0x07, 0xCB, 0xC0,
+        ];
+        let sps = parse_hevc_sps(&unescape(&sps)).unwrap();
+        assert_eq!((sps.width, sps.height), (1920, 1080));
+    }
+
+    #[test]
+    fn test_scaling_list_out_of_range() {
+        // delta_scale of 2^31 - 1
+        let data = [0x00, 0x00, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFC];
+        assert_eq!(skip_scaling_list(&mut BitReader::new(&data), 16), None);
+        // delta_scale of 0 for all entries
+        assert_eq!(
+            skip_scaling_list(&mut BitReader::new(&[0xFF, 0xFF]), 16),
+            Some(())
+        );
+    }
+}
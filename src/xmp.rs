@@ -0,0 +1,82 @@
+//! Minimal reading of XMP packets, which are RDF/XML documents with a fixed set of namespace
+//! prefixes in practice.
+
+use crate::MediaDateTime;
+use chrono::NaiveDate;
+
+/// Date properties in the order of preference, the time the image was taken coming first.
+const DATE_PROPERTIES: [&str; 3] = [
+    "exif:DateTimeOriginal",
+    "photoshop:DateCreated",
+    "xmp:CreateDate",
+];
+
+/// Returns the value of a simple property, which is written either as an attribute of
+/// `rdf:Description` like `xmp:CreateDate="..."` or as an element like
+/// `<xmp:CreateDate>...</xmp:CreateDate>`.
+pub(crate) fn find_property<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let attribute = format!("{name}={quote}");
+        let found = xmp.match_indices(&attribute).find(|(index, _)| {
+            xmp[..*index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_whitespace)
+        });
+        if let Some((index, _)) = found {
+            let value = &xmp[index + attribute.len()..];
+            return value.split(quote).next();
+        }
+    }
+    let start = format!("<{name}>");
+    let index = xmp.find(&start)? + start.len();
+    let value = &xmp[index..];
+    let end = value.find(&format!("</{name}>"))?;
+    Some(value[..end].trim())
+}
+
+/// Returns the first date property that can be parsed. Dates without time are at midnight.
+pub(crate) fn get_date_created(xmp: &str) -> Option<MediaDateTime> {
+    DATE_PROPERTIES.iter().find_map(|name| {
+        let value = find_property(xmp, name)?;
+        MediaDateTime::parse_iso8601(value).or_else(|| {
+            let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+            Some(MediaDateTime::floating(date.and_hms_opt(0, 0, 0)?))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_property, get_date_created};
+    use crate::MediaDateTime;
+
+    #[test]
+    fn test_find_property() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmp:CreatorTool='Screenshot'
+    xmp:CreateDate="2021-08-13T18:04:35+02:00">
+   <exif:PixelXDimension>1920</exif:PixelXDimension>
+   <photoshop:DateCreated>2021-08-13</photoshop:DateCreated>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        assert_eq!(find_property(xmp, "xmp:CreatorTool"), Some("Screenshot"));
+        assert_eq!(find_property(xmp, "exif:PixelXDimension"), Some("1920"));
+        assert_eq!(find_property(xmp, "exif:PixelYDimension"), None);
+        assert_eq!(
+            find_property(xmp, "xmp:CreateDate"),
+            Some("2021-08-13T18:04:35+02:00")
+        );
+        assert_eq!(
+            get_date_created(xmp),
+            MediaDateTime::parse_iso8601("2021-08-13T00:00:00")
+        );
+        let xmp = xmp.replace("photoshop:DateCreated", "photoshop:City");
+        assert_eq!(
+            get_date_created(&xmp),
+            MediaDateTime::parse_iso8601("2021-08-13T18:04:35+02:00")
+        );
+    }
+}
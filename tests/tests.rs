@@ -1,11 +1,25 @@
+#[path = "tests/avi.rs"]
+mod avi;
+#[path = "tests/detect.rs"]
+mod detect;
 #[path = "tests/exif.rs"]
 mod exif;
+#[path = "../src/fixtures.rs"]
+mod fixtures;
+#[path = "tests/heif.rs"]
+mod heif;
 #[path = "tests/metadata.rs"]
 mod metadata;
 #[path = "tests/mkv.rs"]
 mod mkv;
 #[path = "tests/mp4.rs"]
 mod mp4;
+#[path = "tests/png.rs"]
+mod png;
+#[path = "tests/ts.rs"]
+mod ts;
+#[path = "tests/webm.rs"]
+mod webm;
 
 #[cfg(feature = "mediainfo")]
 #[path = "tests/mediainfo.rs"]
@@ -14,4 +28,38 @@ mod mediainfo;
 #[path = "tests/extract_file_creation_date.rs"]
 mod extract_file_creation_date;
 
-use mediameta::parse_date;
+use mediameta::{parse_date, MatroskaTag, MatroskaTarget, TagValue};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn media_datetime(date: &str, offset: mediameta::DateTimeOffset) -> mediameta::MediaDateTime {
+    let datetime: chrono::DateTime<chrono::Utc> = parse_date(date).into();
+    mediameta::MediaDateTime {
+        datetime: datetime.naive_utc(),
+        offset,
+    }
+}
+
+/// Temporary directory of a single test, which is removed with its content when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> std::io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let index = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("mediameta-test-{}-{index}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
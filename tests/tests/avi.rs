@@ -0,0 +1,141 @@
+use mediameta::{Codec, DateSource, TrackDetails};
+use std::io::Cursor;
+use std::time::Duration;
+
+fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    chunk(b"LIST", &[&list_type[..], &chunks.concat()].concat())
+}
+
+fn u32s(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Builds an AVI file with a 640x480 MJPEG stream of 300 frames at 30000/1001 fps and a mono
+/// PCM audio stream.
+fn make_avi(idit: Option<&[u8]>, icrd: Option<&[u8]>) -> Vec<u8> {
+    // 33367 microseconds per frame, 300 frames, 2 streams, 640x480
+    let avih = u32s(&[33367, 0, 0, 0x10, 300, 0, 2, 0, 640, 480, 0, 0, 0, 0]);
+    let video_strh = [
+        &b"vidsMJPG"[..],
+        &u32s(&[0, 0, 0, 1001, 30000, 0, 300, 0, 0, 0, 0, 0]),
+    ]
+    .concat();
+    let video_strf = [
+        &u32s(&[40, 640, 480])[..],
+        &[1, 0, 24, 0],
+        b"MJPG",
+        &u32s(&[0; 5]),
+    ]
+    .concat();
+    let audio_strh = [&b"auds\0\0\0\0"[..], &u32s(&[0; 12])].concat();
+    let audio_strf = [&[1, 0, 1, 0][..], &u32s(&[48000, 96000]), &[2, 0, 16, 0]].concat();
+    let mut hdrl = vec![
+        chunk(b"avih", &avih),
+        list(
+            b"strl",
+            &[
+                chunk(b"strh", &video_strh),
+                chunk(b"strf", &video_strf),
+                chunk(b"strn", b"Video\0"),
+            ],
+        ),
+        list(
+            b"strl",
+            &[chunk(b"strh", &audio_strh), chunk(b"strf", &audio_strf)],
+        ),
+    ];
+    if let Some(idit) = idit {
+        hdrl.push(chunk(b"IDIT", idit));
+    }
+    let mut riff = vec![list(b"hdrl", &hdrl)];
+    if let Some(icrd) = icrd {
+        riff.push(list(
+            b"INFO",
+            &[chunk(b"ISFT", b"Camera\0"), chunk(b"ICRD", icrd)],
+        ));
+    }
+    riff.push(list(b"movi", &[chunk(b"00dc", &[0xFF, 0xD8, 0xFF, 0xD9])]));
+    chunk(b"RIFF", &[&b"AVI "[..], &riff.concat()].concat())
+}
+
+#[test]
+fn test_avi_metadata() -> anyhow::Result<()> {
+    let avi = make_avi(Some(b"SAT FEB 14 16:25:03 2009\n\0"), Some(b"2009-02-15\0"));
+    let meta = mediameta::extract_stream_metadata(Cursor::new(avi), None)?;
+    assert_eq!((meta.width, meta.height), (640, 480));
+    assert_eq!(meta.duration, Some(Duration::from_millis(10010)));
+    assert_eq!(
+        meta.creation_datetime,
+        Some(crate::media_datetime(
+            "2009-02-14T16:25:03",
+            mediameta::DateTimeOffset::Floating
+        ))
+    );
+
+    assert_eq!(meta.tracks.len(), 2);
+    let video = &meta.tracks[0];
+    assert_eq!(video.codec.codec, Codec::Mjpeg);
+    assert_eq!(video.name.as_deref(), Some("Video"));
+    let TrackDetails::Video(details) = &video.details else {
+        panic!("expected a video track");
+    };
+    let frame_rate = details.frame_rate.expect("frame rate");
+    assert_eq!(
+        (frame_rate.numerator, frame_rate.denominator),
+        (30000, 1001)
+    );
+    let audio = &meta.tracks[1];
+    assert_eq!(audio.codec.codec, Codec::Pcm);
+    let TrackDetails::Audio(details) = &audio.details else {
+        panic!("expected an audio track");
+    };
+    assert_eq!(details.sample_rate, Some(48000));
+    assert_eq!(details.channels, Some(1));
+    Ok(())
+}
+
+#[test]
+fn test_unfinished_recording() -> anyhow::Result<()> {
+    let mut avi = make_avi(Some(b"SAT FEB 14 16:25:03 2009\n\0"), None);
+    // the RIFF size is only written when the recording is stopped
+    avi[4..8].fill(0);
+    let meta = mediameta::extract_stream_metadata(Cursor::new(avi), None)?;
+    assert_eq!((meta.width, meta.height), (640, 480));
+    assert_eq!(meta.tracks.len(), 2);
+    assert_eq!(
+        meta.creation_datetime,
+        Some(crate::media_datetime(
+            "2009-02-14T16:25:03",
+            mediameta::DateTimeOffset::Floating
+        ))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_avi_info_creation_date() -> anyhow::Result<()> {
+    let dir = crate::TempDir::new()?;
+    let path = dir.path().join("info-date.avi");
+    std::fs::write(&path, make_avi(None, Some(b"2009-02-15 08:00:00\0")))?;
+
+    let date = mediameta::extract_file_creation_date(&path)?;
+    assert_eq!(date.source, DateSource::AviInfoCreationDate);
+    assert_eq!(
+        date.date,
+        crate::media_datetime("2009-02-15T08:00:00", mediameta::DateTimeOffset::Floating)
+    );
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use mediameta::{detect_container_type, ContainerType};
+use std::fs::File;
+use std::io::{BufReader, Seek};
+
+#[test]
+fn test_mp4() -> anyhow::Result<()> {
+    let mut reader = BufReader::new(File::open("test-data/sample-mp4-files-sample_640x360.mp4")?);
+    let detected = detect_container_type(&mut reader)?;
+    assert_eq!(detected.container_type, ContainerType::Mp4);
+    assert_eq!(detected.brand.as_deref(), Some("isom"));
+    assert_eq!(reader.stream_position()?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_mkv_with_wrong_extension() -> anyhow::Result<()> {
+    let path = "test-data/sample-mkv-files-sample_640x360_with_date.test";
+    let mut reader = BufReader::new(File::open(path)?);
+    let detected = detect_container_type(&mut reader)?;
+    assert_eq!(detected.container_type, ContainerType::Mkv);
+    assert_eq!(detected.brand.as_deref(), Some("matroska"));
+    Ok(())
+}
+
+#[test]
+fn test_jpg() -> anyhow::Result<()> {
+    let mut reader = BufReader::new(File::open("test-data/sample-no-exif_1200x800.jpg")?);
+    let detected = detect_container_type(&mut reader)?;
+    assert_eq!(
+        detected.container_type,
+        ContainerType::Exif("jpg".to_string())
+    );
+    assert_eq!(detected.brand, None);
+    Ok(())
+}
+
+#[test]
+fn test_txt_file() -> anyhow::Result<()> {
+    let mut reader = BufReader::new(File::open("test-data/source.txt")?);
+    assert!(detect_container_type(&mut reader).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_extract_stream_metadata() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360_with_date.mp4";
+    let reader = BufReader::new(File::open(path)?);
+    let meta = mediameta::extract_stream_metadata(reader, None)?;
+    assert_eq!(meta, mediameta::extract_file_metadata(path)?);
+
+    let path = "test-data/sample-mkv-files-sample_640x360_with_date.test";
+    let bytes = std::fs::read(path)?;
+    let size = bytes.len() as u64;
+    let meta = mediameta::extract_stream_metadata(std::io::Cursor::new(bytes), Some(size))?;
+    assert_eq!(meta.width, 640);
+    assert_eq!(
+        meta.creation_date,
+        Some(super::parse_date("2011-04-17T17:33:45"))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_extract_stream_metadata_unknown() {
+    let reader = std::io::Cursor::new(b"not a media file".to_vec());
+    assert!(mediameta::extract_stream_metadata(reader, None).is_err());
+}
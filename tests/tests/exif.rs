@@ -1,3 +1,5 @@
+use mediameta::DateTimeOffset;
+
 #[test]
 fn test_no_exif() {
     let path = "test-data/sample-no-exif_1200x800.jpg";
@@ -13,13 +15,25 @@ fn test_date() -> anyhow::Result<()> {
     let expected = mediameta::MetaData {
         width: 0,
         height: 0,
-        creation_date: Some(super::parse_date("2015-07-16T13:34:48")),
+        creation_date: Some(super::parse_date("2015-07-16T13:34:48.620")),
+        creation_datetime: Some(super::media_datetime(
+            "2015-07-16T13:34:48.620",
+            DateTimeOffset::Floating,
+        )),
+        duration: None,
+        ..Default::default()
     };
     #[cfg(feature = "image")]
     let expected = mediameta::MetaData {
         width: 1200,
         height: 800,
-        creation_date: Some(super::parse_date("2015-07-16T13:34:48")),
+        creation_date: Some(super::parse_date("2015-07-16T13:34:48.620")),
+        creation_datetime: Some(super::media_datetime(
+            "2015-07-16T13:34:48.620",
+            DateTimeOffset::Floating,
+        )),
+        duration: None,
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -33,7 +47,24 @@ fn test_datetime_tag() -> anyhow::Result<()> {
         width: 826,
         height: 1062,
         creation_date: Some(super::parse_date("2017-02-08T09:28:36")),
+        creation_datetime: Some(super::media_datetime(
+            "2017-02-08T09:28:36",
+            DateTimeOffset::Floating,
+        )),
+        duration: None,
+        device: Some(mediameta::Device {
+            software: Some("paint.net 5.0.13".to_owned()),
+            ..Default::default()
+        }),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
 }
+
+#[test]
+fn test_no_thumbnail() -> anyhow::Result<()> {
+    let path = "test-data/sample-exif_1200x800_with_date.jpg";
+    assert!(mediameta::extract_file_thumbnails(path)?.is_empty());
+    Ok(())
+}
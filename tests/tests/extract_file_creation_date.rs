@@ -1,4 +1,7 @@
-use mediameta::extract_file_creation_date;
+use mediameta::{
+    extract_file_creation_date, extract_file_creation_date_with_options, CreationDateOptions,
+    DateSource,
+};
 
 #[test]
 fn test_jpg_no_exif() {
@@ -10,8 +13,9 @@ fn test_jpg_no_exif() {
 fn test_jpg_with_date() -> anyhow::Result<()> {
     let path = "test-data/sample-exif_1200x800_with_date.jpg";
     let creation_date = extract_file_creation_date(path)?;
-    let expected = super::parse_date("2015-07-16T13:34:48");
-    assert_eq!(expected, creation_date);
+    let expected = super::parse_date("2015-07-16T13:34:48.620");
+    assert_eq!(expected, creation_date.date.to_system_time());
+    assert_eq!(creation_date.source, DateSource::ExifDateTimeOriginal);
     Ok(())
 }
 
@@ -26,7 +30,8 @@ fn test_mkv_date() -> anyhow::Result<()> {
     let path = "test-data/sample-mkv-files-sample_640x360_with_date.mkv";
     let creation_date = extract_file_creation_date(path)?;
     let expected = super::parse_date("2011-04-17T17:33:45");
-    assert_eq!(expected, creation_date);
+    assert_eq!(expected, creation_date.date.to_system_time());
+    assert_eq!(creation_date.source, DateSource::MatroskaDateUtc);
     Ok(())
 }
 
@@ -41,7 +46,8 @@ fn test_mp4_date() -> anyhow::Result<()> {
     let path = "test-data/sample-mp4-files-sample_640x360_with_date.mp4";
     let creation_date = extract_file_creation_date(path)?;
     let expected = super::parse_date("2021-08-13T18:04:35");
-    assert_eq!(expected, creation_date);
+    assert_eq!(expected, creation_date.date.to_system_time());
+    assert_eq!(creation_date.source, DateSource::Mp4CreationTime);
     Ok(())
 }
 
@@ -51,21 +57,69 @@ fn test_txt_file() {
     assert!(extract_file_creation_date(path).is_err());
 }
 
-#[cfg(not(feature = "mediainfo"))]
 #[test]
-fn test_wrong_extension() {
+fn test_wrong_extension() -> anyhow::Result<()> {
     let path = "test-data/sample-mkv-files-sample_640x360_with_date.test";
-    assert!(extract_file_creation_date(path).is_err());
+    let creation_date = extract_file_creation_date(path)?;
+    println!("{}", creation_date.date);
+    let expected = super::parse_date("2011-04-17T17:33:45");
+    assert_eq!(expected, creation_date.date.to_system_time());
+    Ok(())
 }
 
-#[cfg(feature = "mediainfo")]
 #[test]
-fn test_wrong_extension() -> anyhow::Result<()> {
-    let path = "test-data/sample-mkv-files-sample_640x360_with_date.test";
+fn test_source_order() -> anyhow::Result<()> {
+    let path = "test-data/sample-exif-tag-datetime.jpg";
     let creation_date = extract_file_creation_date(path)?;
-    let datetime: chrono::DateTime<chrono::Utc> = creation_date.into();
-    println!("{}", datetime.to_rfc3339());
-    let expected = super::parse_date("2011-04-17T17:33:45");
-    assert_eq!(expected, creation_date);
+    assert_eq!(creation_date.source, DateSource::ExifDateTime);
+
+    let options = CreationDateOptions::with_sources([DateSource::ExifDateTimeOriginal]);
+    assert!(extract_file_creation_date_with_options(path, &options).is_err());
+
+    let path = "test-data/sample-mp4-files-sample_640x360_with_date.mp4";
+    let options = CreationDateOptions::with_sources([
+        DateSource::QuickTimeCreationDate,
+        DateSource::Mp4CreationTime,
+    ]);
+    let creation_date = extract_file_creation_date_with_options(path, &options)?;
+    assert_eq!(creation_date.source, DateSource::Mp4CreationTime);
+    Ok(())
+}
+
+#[test]
+fn test_filename_fallback() -> anyhow::Result<()> {
+    let dir = crate::TempDir::new()?;
+    let path = dir.path().join("IMG-20210813-WA0003.jpg");
+    std::fs::copy("test-data/sample-no-exif_1200x800.jpg", &path)?;
+
+    assert!(extract_file_creation_date(&path).is_err());
+
+    let options = CreationDateOptions::default().with_filename([]);
+    let creation_date = extract_file_creation_date_with_options(&path, &options)?;
+    assert_eq!(creation_date.source, DateSource::FileName);
+    assert_eq!(creation_date.date.to_string(), "2021-08-13T00:00:00");
+
+    // metadata takes precedence over the file name
+    let path = "test-data/sample-exif_1200x800_with_date.jpg";
+    let creation_date = extract_file_creation_date_with_options(path, &options)?;
+    assert_eq!(creation_date.source, DateSource::ExifDateTimeOriginal);
+    Ok(())
+}
+
+#[test]
+fn test_file_system_fallback() -> anyhow::Result<()> {
+    let path = "test-data/sample-no-exif_1200x800.jpg";
+    let options = CreationDateOptions::default().with_file_system();
+    let creation_date = extract_file_creation_date_with_options(path, &options)?;
+    assert!(matches!(
+        creation_date.source,
+        DateSource::FileSystemCreated | DateSource::FileSystemModified
+    ));
+    assert!(!creation_date.source.is_metadata());
+
+    let modified = std::fs::metadata(path)?.modified()?;
+    let options = CreationDateOptions::with_sources([DateSource::FileSystemModified]);
+    let creation_date = extract_file_creation_date_with_options(path, &options)?;
+    assert_eq!(creation_date.date.to_system_time(), modified);
     Ok(())
 }
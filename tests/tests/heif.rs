@@ -0,0 +1,88 @@
+use crate::fixtures::make_box;
+use mediameta::{ContainerType, Orientation};
+use std::io::Cursor;
+
+fn u32s(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+/// Builds an AVIF file without Exif data, whose primary image is a 2x2 grid of 1024x1024 tiles
+/// with an output size of 2000x1500, cropped to 1920x1080 and rotated by 90 degrees.
+fn make_avif() -> Vec<u8> {
+    let ftyp = make_box(b"ftyp", b"avif\0\0\0\0avifmif1miaf");
+    let infe = |id: u8, item_type: &[u8; 4]| {
+        make_box(
+            b"infe",
+            &[&[2, 0, 0, 0, 0, id, 0, 0][..], item_type, b"\0"].concat(),
+        )
+    };
+    let iinf = [
+        vec![0, 0, 0, 0, 0, 5],
+        infe(1, b"grid"),
+        infe(2, b"av01"),
+        infe(3, b"av01"),
+        infe(4, b"av01"),
+        infe(5, b"av01"),
+    ]
+    .concat();
+    let iref = [
+        vec![0, 0, 0, 0],
+        make_box(b"dimg", &[0, 1, 0, 4, 0, 2, 0, 3, 0, 4, 0, 5]),
+    ]
+    .concat();
+    // version 1 with 4-byte offsets and lengths, the grid is stored in `idat`, the tiles are
+    // placeholders in the file
+    let mut iloc = vec![1, 0, 0, 0, 0x44, 0, 0, 5];
+    iloc.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 1]);
+    iloc.extend_from_slice(&u32s(&[0, 8]));
+    for item in 2..=5 {
+        iloc.extend_from_slice(&[0, item, 0, 0, 0, 0, 0, 1]);
+        iloc.extend_from_slice(&u32s(&[0, 0]));
+    }
+    let idat = [0, 0, 1, 1, 0x07, 0xD0, 0x05, 0xDC];
+    let ipco = [
+        make_box(b"ispe", &u32s(&[0, 1024, 1024])),
+        make_box(b"ispe", &u32s(&[0, 2000, 1500])),
+        make_box(b"clap", &u32s(&[1920, 1, 1080, 1, 0, 1, 0, 1])),
+        make_box(b"irot", &[1]),
+    ]
+    .concat();
+    let mut ipma = vec![0, 0, 0, 0, 0, 0, 0, 5, 0, 1, 3, 2, 0x83, 0x84];
+    for item in 2..=5 {
+        ipma.extend_from_slice(&[0, item, 1, 0x81]);
+    }
+    let iprp = [make_box(b"ipco", &ipco), make_box(b"ipma", &ipma)].concat();
+    let meta = [
+        vec![0, 0, 0, 0],
+        make_box(b"hdlr", &[&[0; 8][..], b"pict", &[0; 13]].concat()),
+        make_box(b"pitm", &[0, 0, 0, 0, 0, 1]),
+        make_box(b"iinf", &iinf),
+        make_box(b"iref", &iref),
+        make_box(b"iloc", &iloc),
+        make_box(b"idat", &idat),
+        make_box(b"iprp", &iprp),
+    ]
+    .concat();
+    [ftyp, make_box(b"meta", &meta)].concat()
+}
+
+#[test]
+fn test_container_type() {
+    assert_eq!(
+        mediameta::get_container_type("image.avif").unwrap(),
+        ContainerType::Exif("avif".to_owned())
+    );
+}
+
+#[test]
+fn test_avif_grid() -> anyhow::Result<()> {
+    let meta = mediameta::extract_stream_metadata(Cursor::new(make_avif()), None)?;
+    assert_eq!((meta.width, meta.height), (1920, 1080));
+    assert_eq!(meta.orientation, Orientation::from_exif(8).unwrap());
+    assert_eq!((meta.display_width(), meta.display_height()), (1080, 1920));
+    assert_eq!(meta.creation_date, None);
+    Ok(())
+}
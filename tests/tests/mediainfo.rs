@@ -1,4 +1,40 @@
 use mediameta::mediainfo::extract_metadata;
+use mediameta::DateTimeOffset;
+use std::time::Duration;
+
+/// Matroska files report the muxing library in addition to the writing application.
+fn lavf_device(with_encoder: bool) -> mediameta::Device {
+    mediameta::Device {
+        software: Some("Lavf57.83.100".to_owned()),
+        encoder: with_encoder.then(|| "Lavf57.83.100".to_owned()),
+        ..Default::default()
+    }
+}
+
+fn video_track(width: u64, height: u64) -> mediameta::TrackInfo {
+    mediameta::TrackInfo {
+        kind: mediameta::TrackKind::Video,
+        codec: mediameta::CodecInfo {
+            codec: mediameta::Codec::H264,
+            id: "AVC".to_owned(),
+        },
+        language: None,
+        name: None,
+        enabled: true,
+        default: true,
+        forced: false,
+        details: mediameta::TrackDetails::Video(mediameta::VideoDetails {
+            width,
+            height,
+            frame_rate: Some(mediameta::FrameRate {
+                numerator: 30000,
+                denominator: 1001,
+                variable: false,
+            }),
+            alpha: false,
+        }),
+    }
+}
 
 #[test]
 fn test_jpg_no_exif() -> anyhow::Result<()> {
@@ -8,6 +44,8 @@ fn test_jpg_no_exif() -> anyhow::Result<()> {
         width: 1200,
         height: 800,
         creation_date: None,
+        duration: None,
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -22,8 +60,14 @@ fn test_jpg_with_date() -> anyhow::Result<()> {
         height: 800,
         #[cfg(windows)]
         creation_date: Some(super::parse_date("2015-07-16T13:34:48.620")), // added in mediainfo 25.04
+        creation_datetime: Some(super::media_datetime(
+            "2015-07-16T13:34:48.620",
+            DateTimeOffset::Floating,
+        )),
         #[cfg(not(windows))]
         creation_date: None,
+        duration: None,
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -38,6 +82,10 @@ fn test_mkv_360() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: None,
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device(true)),
+        tracks: vec![video_track(640, 360)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -52,6 +100,10 @@ fn test_mkv_540() -> anyhow::Result<()> {
         width: 960,
         height: 540,
         creation_date: None,
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device(true)),
+        tracks: vec![video_track(960, 540)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -66,6 +118,14 @@ fn test_mkv_date() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2011-04-17T17:33:45")),
+        creation_datetime: Some(super::media_datetime(
+            "2011-04-17T17:33:45",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device(true)),
+        tracks: vec![video_track(640, 360)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -80,6 +140,10 @@ fn test_mp4_360() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: None,
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device(false)),
+        tracks: vec![video_track(640, 360)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -94,6 +158,10 @@ fn test_mp4_540() -> anyhow::Result<()> {
         width: 960,
         height: 540,
         creation_date: None,
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device(false)),
+        tracks: vec![video_track(960, 540)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -108,6 +176,14 @@ fn test_mp4_date() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2021-08-13T18:04:35")),
+        creation_datetime: Some(super::media_datetime(
+            "2021-08-13T18:04:35",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device(false)),
+        tracks: vec![video_track(640, 360)],
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -4,6 +4,8 @@ fn test_display() {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2021-08-13T18:04:35")),
+        duration: None,
+        ..Default::default()
     };
 
     let meta_str = format!("{meta}");
@@ -1,5 +1,61 @@
+use crate::fixtures::tag;
+use mediameta::DateTimeOffset;
 use std::fs::File;
 use std::io::BufReader;
+use std::time::Duration;
+
+fn lavf_device() -> mediameta::Device {
+    mediameta::Device {
+        software: Some("Lavf57.83.100".to_owned()),
+        encoder: Some("Lavf57.83.100".to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Tags written by ffmpeg when converting from MP4.
+fn lavf_tags() -> Vec<mediameta::MatroskaTag> {
+    vec![
+        tag("MAJOR_BRAND", "mp42", vec![]),
+        tag("MINOR_VERSION", "0", vec![]),
+        tag("COMPATIBLE_BRANDS", "mp42mp41isomavc1", vec![]),
+        tag("ENCODER", "Lavf57.83.100", vec![]),
+        tag("HANDLER_NAME", "L-SMASH Video Handler", vec![1]),
+        tag("ENCODER", "Lavc57.107.100 libx264", vec![1]),
+        tag("DURATION", "00:00:13.346000000", vec![1]),
+    ]
+}
+
+fn matroska_doc_type() -> mediameta::DocType {
+    mediameta::DocType {
+        name: "matroska".to_owned(),
+        version: 4,
+    }
+}
+
+fn video_track(width: u64, height: u64) -> mediameta::TrackInfo {
+    mediameta::TrackInfo {
+        kind: mediameta::TrackKind::Video,
+        codec: mediameta::CodecInfo {
+            codec: mediameta::Codec::H264,
+            id: "V_MPEG4/ISO/AVC".to_owned(),
+        },
+        language: None,
+        name: None,
+        enabled: true,
+        default: true,
+        forced: false,
+        details: mediameta::TrackDetails::Video(mediameta::VideoDetails {
+            width,
+            height,
+            frame_rate: Some(mediameta::FrameRate {
+                numerator: 30000,
+                denominator: 1001,
+                variable: false,
+            }),
+            alpha: false,
+        }),
+    }
+}
 
 #[test]
 fn test_360() -> anyhow::Result<()> {
@@ -10,6 +66,12 @@ fn test_360() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: None,
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        matroska_tags: lavf_tags(),
+        doc_type: Some(matroska_doc_type()),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -24,6 +86,12 @@ fn test_540() -> anyhow::Result<()> {
         width: 960,
         height: 540,
         creation_date: None,
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(960, 540)],
+        matroska_tags: lavf_tags(),
+        doc_type: Some(matroska_doc_type()),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -38,6 +106,16 @@ fn test_date() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2011-04-17T17:33:45")),
+        creation_datetime: Some(super::media_datetime(
+            "2011-04-17T17:33:45",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        matroska_tags: lavf_tags(),
+        doc_type: Some(matroska_doc_type()),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -46,7 +124,23 @@ fn test_date() -> anyhow::Result<()> {
 #[test]
 fn test_wrong_extension() -> anyhow::Result<()> {
     let path = "test-data/sample-mkv-files-sample_640x360_with_date.test";
-    assert!(mediameta::extract_file_metadata(path).is_err());
+    let meta = mediameta::extract_file_metadata(path)?;
+    let expected = mediameta::MetaData {
+        width: 640,
+        height: 360,
+        creation_date: Some(super::parse_date("2011-04-17T17:33:45")),
+        creation_datetime: Some(super::media_datetime(
+            "2011-04-17T17:33:45",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        matroska_tags: lavf_tags(),
+        doc_type: Some(matroska_doc_type()),
+        ..Default::default()
+    };
+    assert_eq!(expected, meta);
 
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -56,7 +150,32 @@ fn test_wrong_extension() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2011-04-17T17:33:45")),
+        creation_datetime: Some(super::media_datetime(
+            "2011-04-17T17:33:45",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13346)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        matroska_tags: lavf_tags(),
+        doc_type: Some(matroska_doc_type()),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
 }
+
+#[test]
+fn test_no_attachments() -> anyhow::Result<()> {
+    let path = "test-data/sample-mkv-files-sample_640x360.mkv";
+    let attachments = mediameta::list_mkv_attachments(BufReader::new(File::open(path)?))?;
+    assert!(attachments.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_no_thumbnails() -> anyhow::Result<()> {
+    let path = "test-data/sample-mkv-files-sample_640x360.mkv";
+    assert!(mediameta::extract_file_thumbnails(path)?.is_empty());
+    Ok(())
+}
@@ -1,3 +1,49 @@
+use crate::fixtures::make_box;
+use mediameta::DateTimeOffset;
+use std::time::Duration;
+
+fn lavf_device() -> mediameta::Device {
+    mediameta::Device {
+        software: Some("Lavf57.83.100".to_owned()),
+        ..Default::default()
+    }
+}
+
+fn lavf_tags() -> mediameta::ItunesTags {
+    let tags = [(
+        "©too".to_owned(),
+        mediameta::TagValue::Text("Lavf57.83.100".to_owned()),
+    )];
+    mediameta::ItunesTags {
+        tags: tags.into_iter().collect(),
+    }
+}
+
+fn video_track(width: u64, height: u64) -> mediameta::TrackInfo {
+    mediameta::TrackInfo {
+        kind: mediameta::TrackKind::Video,
+        codec: mediameta::CodecInfo {
+            codec: mediameta::Codec::H264,
+            id: "avc1".to_owned(),
+        },
+        language: None,
+        name: None,
+        enabled: true,
+        default: true,
+        forced: false,
+        details: mediameta::TrackDetails::Video(mediameta::VideoDetails {
+            width,
+            height,
+            frame_rate: Some(mediameta::FrameRate {
+                numerator: 30000,
+                denominator: 1001,
+                variable: false,
+            }),
+            alpha: false,
+        }),
+    }
+}
+
 #[test]
 fn test_360() -> anyhow::Result<()> {
     let path = "test-data/sample-mp4-files-sample_640x360.mp4";
@@ -7,6 +53,11 @@ fn test_360() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: None,
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        itunes_tags: lavf_tags(),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -21,6 +72,11 @@ fn test_540() -> anyhow::Result<()> {
         width: 960,
         height: 540,
         creation_date: None,
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(960, 540)],
+        itunes_tags: lavf_tags(),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
@@ -35,7 +91,226 @@ fn test_date() -> anyhow::Result<()> {
         width: 640,
         height: 360,
         creation_date: Some(super::parse_date("2021-08-13T18:04:35")),
+        creation_datetime: Some(super::media_datetime(
+            "2021-08-13T18:04:35",
+            DateTimeOffset::Utc,
+        )),
+        duration: Some(Duration::from_millis(13347)),
+        device: Some(lavf_device()),
+        tracks: vec![video_track(640, 360)],
+        itunes_tags: lavf_tags(),
+        ..Default::default()
     };
     assert_eq!(expected, meta);
     Ok(())
 }
+
+#[test]
+fn test_rotation_matrix() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360.mp4";
+    let mut bytes = std::fs::read(path)?;
+
+    // rotate the video track by 90 degrees clockwise, as phones do for portrait recordings
+    let tkhd = bytes
+        .windows(4)
+        .position(|window| window == b"tkhd")
+        .expect("tkhd box not found");
+    let matrix = tkhd + 4 + 40;
+    let one = 0x10000i32.to_be_bytes();
+    let minus_one = (-0x10000i32).to_be_bytes();
+    bytes[matrix..matrix + 4].copy_from_slice(&[0; 4]);
+    bytes[matrix + 4..matrix + 8].copy_from_slice(&one);
+    bytes[matrix + 12..matrix + 16].copy_from_slice(&minus_one);
+    bytes[matrix + 16..matrix + 20].copy_from_slice(&[0; 4]);
+
+    let meta = mediameta::extract_stream_metadata(std::io::Cursor::new(bytes), None)?;
+    assert_eq!((meta.width, meta.height), (640, 360));
+    assert_eq!(
+        meta.orientation,
+        mediameta::Orientation::from_exif(6).unwrap()
+    );
+    assert_eq!((meta.display_width(), meta.display_height()), (360, 640));
+    Ok(())
+}
+
+#[test]
+fn test_bogus_date() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360_with_date.mp4";
+    let mut bytes = std::fs::read(path)?;
+
+    // a creation time of 42 seconds after the MP4 epoch, as written by devices without a clock
+    let mvhd = bytes
+        .windows(4)
+        .position(|window| window == b"mvhd")
+        .expect("mvhd box not found");
+    assert_eq!(bytes[mvhd + 4], 0, "expected mvhd version 0");
+    bytes[mvhd + 8..mvhd + 12].copy_from_slice(&42u32.to_be_bytes());
+    let dir = crate::TempDir::new()?;
+    let path = dir.path().join("bogus-date.mp4");
+    std::fs::write(&path, bytes)?;
+
+    let meta = mediameta::extract_file_metadata(&path)?;
+    let date = meta.creation_datetime.expect("creation date not found");
+    assert_eq!(date.bogus_reason(), Some(mediameta::BogusDate::Epoch));
+
+    let options = mediameta::CreationDateOptions::default().reject_bogus(true);
+    let meta = mediameta::extract_file_metadata_with_options(&path, &options)?;
+    assert_eq!(meta.creation_date, None);
+    assert!(mediameta::extract_file_creation_date_with_options(&path, &options).is_err());
+    Ok(())
+}
+
+/// Appends a box to `moov`, which must be the last top-level box.
+fn append_to_moov(bytes: &mut Vec<u8>, data: &[u8]) {
+    let moov = bytes
+        .windows(4)
+        .position(|window| window == b"moov")
+        .expect("moov box not found")
+        - 4;
+    let size = u32::from_be_bytes(bytes[moov..moov + 4].try_into().unwrap()) as usize;
+    assert_eq!(moov + size, bytes.len(), "expected moov at the end");
+    bytes[moov..moov + 4].copy_from_slice(&((size + data.len()) as u32).to_be_bytes());
+    bytes.extend_from_slice(data);
+}
+
+/// Turns the first box of the given type into a `free` box, so that it is ignored.
+fn free_box(bytes: &mut [u8], box_type: &[u8; 4]) {
+    let position = bytes
+        .windows(4)
+        .position(|window| window == box_type)
+        .expect("box not found");
+    bytes[position..position + 4].copy_from_slice(b"free");
+}
+
+#[test]
+fn test_quicktime_keys() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360_with_date.mp4";
+    let mut bytes = std::fs::read(path)?;
+
+    // append an iPhone-style `meta` box with `keys` and `ilst` to `moov`, the last top-level box
+    let items = [
+        (
+            "com.apple.quicktime.creationdate",
+            "2021-08-13T19:30:00+0200",
+        ),
+        ("com.apple.quicktime.make", "Apple"),
+        ("com.apple.quicktime.content.identifier", "8A4C1E0B"),
+    ];
+    let mut keys = vec![0, 0, 0, 0];
+    keys.extend_from_slice(&(items.len() as u32).to_be_bytes());
+    let mut ilst = Vec::new();
+    for (index, (key, value)) in items.iter().enumerate() {
+        keys.extend(make_box(b"mdta", key.as_bytes()));
+        let data = [&[0, 0, 0, 1, 0, 0, 0, 0][..], value.as_bytes()].concat();
+        let index = (index as u32 + 1).to_be_bytes();
+        ilst.extend(make_box(&index, &make_box(b"data", &data)));
+    }
+    let meta = [
+        make_box(b"hdlr", &[&[0; 8][..], b"mdta", &[0; 13]].concat()),
+        make_box(b"keys", &keys),
+        make_box(b"ilst", &ilst),
+    ]
+    .concat();
+    let meta = make_box(b"meta", &meta);
+    append_to_moov(&mut bytes, &meta);
+
+    let meta = mediameta::extract_stream_metadata(std::io::Cursor::new(bytes), None)?;
+    assert_eq!(
+        meta.creation_datetime.map(|date| date.to_string()),
+        Some("2021-08-13T19:30:00+02:00".to_owned())
+    );
+    assert_eq!(
+        meta.creation_date,
+        Some(super::parse_date("2021-08-13T17:30:00"))
+    );
+    assert_eq!(
+        meta.quicktime_keys
+            .get("com.apple.quicktime.content.identifier")
+            .and_then(|value| value.as_text()),
+        Some("8A4C1E0B")
+    );
+    assert_eq!(meta.quicktime_keys.len(), 3);
+    assert_eq!(
+        meta.device.and_then(|device| device.make).as_deref(),
+        Some("Apple")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_itunes_tags() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360.mp4";
+    let mut bytes = std::fs::read(path)?;
+
+    // replace the `udta` box written by ffmpeg with one holding iTunes-style items and a QuickTime
+    // text atom, appended to `moov`
+    free_box(&mut bytes, b"udta");
+    let item = |atom: &[u8; 4], data_type: u32, value: &[u8]| {
+        let data = [&data_type.to_be_bytes()[..], &[0; 4], value].concat();
+        make_box(atom, &make_box(b"data", &data))
+    };
+    let jpeg = [0xFF, 0xD8, 0xFF, 0xD9];
+    let ilst = [
+        item(b"\xA9nam", 1, b"Holiday"),
+        item(b"\xA9ART", 1, b"Jane Doe"),
+        item(b"covr", 13, &jpeg),
+    ]
+    .concat();
+    let hdlr = make_box(b"hdlr", &[&[0; 8][..], b"mdirappl", &[0; 9]].concat());
+    let meta = [&[0; 4][..], &hdlr, &make_box(b"ilst", &ilst)].concat();
+    let day = [&[0, 4, 0x15, 0xC7][..], b"2019"].concat();
+    let udta = [make_box(b"meta", &meta), make_box(b"\xA9day", &day)].concat();
+    let udta = make_box(b"udta", &udta);
+    append_to_moov(&mut bytes, &udta);
+
+    let meta = mediameta::extract_stream_metadata(std::io::Cursor::new(&bytes), None)?;
+    let tags = &meta.itunes_tags;
+    assert_eq!(tags.title(), Some("Holiday"));
+    assert_eq!(tags.artist(), Some("Jane Doe"));
+    assert_eq!(tags.date(), Some("2019"));
+    assert_eq!(
+        tags.cover(),
+        Some(&mediameta::TagValue::Jpeg(jpeg.to_vec()))
+    );
+
+    let container_type = mediameta::ContainerType::Mp4;
+    let thumbnails = mediameta::extract_thumbnails(std::io::Cursor::new(&bytes), container_type)?;
+    assert_eq!(thumbnails.len(), 1);
+    assert_eq!(thumbnails[0].source, mediameta::ThumbnailSource::Mp4Cover);
+    assert_eq!(thumbnails[0].mime_type, "image/jpeg");
+    let data = mediameta::read_thumbnail(std::io::Cursor::new(&bytes), &thumbnails[0])?;
+    assert_eq!(data, jpeg);
+    Ok(())
+}
+
+#[test]
+fn test_nero_chapters() -> anyhow::Result<()> {
+    let path = "test-data/sample-mp4-files-sample_640x360.mp4";
+    let mut bytes = std::fs::read(path)?;
+
+    // replace the `udta` box written by ffmpeg with one holding a Nero `chpl` atom
+    free_box(&mut bytes, b"udta");
+    let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+    for (start, title) in [(0u64, "Intro"), (50_000_000, "Main")] {
+        chpl.extend_from_slice(&start.to_be_bytes());
+        chpl.push(title.len() as u8);
+        chpl.extend_from_slice(title.as_bytes());
+    }
+    let udta = make_box(b"udta", &make_box(b"chpl", &chpl));
+    append_to_moov(&mut bytes, &udta);
+
+    let meta = mediameta::extract_stream_metadata(std::io::Cursor::new(bytes), None)?;
+    let chapters: Vec<_> = meta
+        .chapters
+        .iter()
+        .map(|chapter| (chapter.title(), chapter.start, chapter.end))
+        .collect();
+    assert_eq!(
+        chapters,
+        [
+            (Some("Intro"), Duration::ZERO, Some(Duration::from_secs(5))),
+            (Some("Main"), Duration::from_secs(5), meta.duration),
+        ]
+    );
+    Ok(())
+}
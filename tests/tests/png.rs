@@ -0,0 +1,212 @@
+use mediameta::{ContainerType, DateSource, DateTimeOffset, PngText};
+use std::io::Cursor;
+use std::time::Duration;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let body = [&chunk_type[..], data].concat();
+    [
+        &(data.len() as u32).to_be_bytes()[..],
+        &body,
+        &crc32(&body).to_be_bytes(),
+    ]
+    .concat()
+}
+
+fn text(keyword: &str, text: &str) -> PngText {
+    PngText {
+        keyword: keyword.to_owned(),
+        text: text.to_owned(),
+        language: None,
+        translated_keyword: None,
+    }
+}
+
+/// Builds a 1920x1080 PNG file with 16-bit samples, whose metadata chunks come before and after
+/// the image data.
+fn make_png(head: &[Vec<u8>], tail: &[Vec<u8>]) -> Vec<u8> {
+    let ihdr = [
+        &1920u32.to_be_bytes()[..],
+        &1080u32.to_be_bytes(),
+        &[16, 2, 0, 0, 0],
+    ]
+    .concat();
+    [
+        vec![b"\x89PNG\r\n\x1a\n".to_vec(), chunk(b"IHDR", &ihdr)],
+        head.to_vec(),
+        vec![chunk(
+            b"IDAT",
+            &[0x78, 0x9C, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+        )],
+        tail.to_vec(),
+        vec![chunk(b"IEND", &[])],
+    ]
+    .concat()
+    .concat()
+}
+
+/// Big-endian TIFF structure with `DateTimeOriginal` and `OffsetTimeOriginal` in the Exif IFD.
+fn make_exif() -> Vec<u8> {
+    let mut tiff = b"MM\0\x2A\0\0\0\x08".to_vec();
+    // IFD0 with the Exif IFD pointer at offset 26
+    tiff.extend_from_slice(&[0, 1, 0x87, 0x69, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0]);
+    // Exif IFD with the values at offsets 56 and 76
+    tiff.extend_from_slice(&[0, 2, 0x90, 0x03, 0, 2, 0, 0, 0, 20, 0, 0, 0, 56]);
+    tiff.extend_from_slice(&[0x90, 0x11, 0, 2, 0, 0, 0, 7, 0, 0, 0, 76, 0, 0, 0, 0]);
+    tiff.extend_from_slice(b"2021:08:13 18:04:35\0+02:00\0");
+    tiff
+}
+
+fn xmp_chunk() -> Vec<u8> {
+    let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF
+        xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description
+        xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/"
+        photoshop:DateCreated="2021-08-12T09:30:00+02:00"/></rdf:RDF></x:xmpmeta>"#;
+    chunk(
+        b"iTXt",
+        &[&b"XML:com.adobe.xmp\0\0\0\0\0"[..], xmp.as_bytes()].concat(),
+    )
+}
+
+#[test]
+fn test_container_type() {
+    assert_eq!(
+        mediameta::get_container_type("Screenshot.png").unwrap(),
+        ContainerType::Png
+    );
+}
+
+#[test]
+fn test_png_metadata() -> anyhow::Result<()> {
+    let png = make_png(
+        &[
+            chunk(b"eXIf", &make_exif()),
+            chunk(b"tEXt", b"Software\0GIMP 2.10.34"),
+            chunk(b"tEXt", b"Creation Time\0Thu, 12 Aug 2021 07:00:00 GMT"),
+            xmp_chunk(),
+        ],
+        &[chunk(b"tIME", &[0x07, 0xE5, 8, 14, 10, 0, 0])],
+    );
+    let meta = mediameta::extract_stream_metadata(Cursor::new(png), None)?;
+    assert_eq!((meta.width, meta.height), (1920, 1080));
+    assert_eq!(meta.bit_depth, Some(16));
+    assert_eq!(meta.frame_count, None);
+    assert_eq!(meta.duration, None);
+    assert_eq!(
+        meta.creation_datetime,
+        Some(crate::media_datetime(
+            "2021-08-13T18:04:35",
+            DateTimeOffset::Fixed(chrono::FixedOffset::east_opt(2 * 3600).unwrap())
+        ))
+    );
+    assert_eq!(
+        meta.device.and_then(|device| device.software).as_deref(),
+        Some("GIMP 2.10.34")
+    );
+    assert_eq!(meta.png_text.len(), 3);
+    assert_eq!(
+        meta.png_text[1],
+        text("Creation Time", "Thu, 12 Aug 2021 07:00:00 GMT")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_truncated_png() -> anyhow::Result<()> {
+    let mut png = make_png(
+        &[chunk(b"tEXt", b"Software\0GIMP 2.10.34")],
+        &[chunk(b"tIME", &[0x07, 0xE5, 8, 14, 10, 0, 0])],
+    );
+    // cut off `IEND` and the `tIME` chunk after the first byte of its data
+    png.truncate(png.len() - 22);
+    let meta = mediameta::extract_metadata(Cursor::new(png), 0, ContainerType::Png)?;
+    assert_eq!((meta.width, meta.height), (1920, 1080));
+    assert_eq!(meta.png_text, [text("Software", "GIMP 2.10.34")]);
+    assert_eq!(meta.creation_date, None);
+    Ok(())
+}
+
+#[test]
+fn test_png_creation_dates() -> anyhow::Result<()> {
+    let dir = crate::TempDir::new()?;
+    let path = dir.path().join("creation-time.png");
+    let time = chunk(b"tIME", &[0x07, 0xE5, 8, 14, 10, 0, 0]);
+    let creation_time = chunk(b"tEXt", b"Creation Time\x002021:08:13 18:04:35");
+
+    std::fs::write(
+        &path,
+        make_png(&[xmp_chunk()], std::slice::from_ref(&creation_time)),
+    )?;
+    let date = mediameta::extract_file_creation_date(&path)?;
+    assert_eq!(date.source, DateSource::XmpDateCreated);
+    assert_eq!(
+        date.date.to_fixed_offset().map(|date| date.to_rfc3339()),
+        Some("2021-08-12T09:30:00+02:00".to_owned())
+    );
+
+    std::fs::write(&path, make_png(&[], &[creation_time, time.clone()]))?;
+    let date = mediameta::extract_file_creation_date(&path)?;
+    assert_eq!(date.source, DateSource::PngCreationTime);
+    assert_eq!(
+        date.date,
+        crate::media_datetime("2021-08-13T18:04:35", DateTimeOffset::Floating)
+    );
+
+    std::fs::write(&path, make_png(&[], &[time]))?;
+    let date = mediameta::extract_file_creation_date(&path)?;
+    assert_eq!(date.source, DateSource::PngModificationTime);
+    assert_eq!(
+        date.date,
+        crate::media_datetime("2021-08-14T10:00:00", DateTimeOffset::Utc)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_apng() -> anyhow::Result<()> {
+    let fctl = |sequence: u32, delay_num: u16, delay_den: u16| {
+        let mut data = [sequence, 1920, 1080, 0, 0]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+        data.extend_from_slice(&delay_num.to_be_bytes());
+        data.extend_from_slice(&delay_den.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        chunk(b"fcTL", &data)
+    };
+    let comment = miniz_oxide::deflate::compress_to_vec_zlib(b"three frames", 6);
+    let png = make_png(
+        &[
+            chunk(b"acTL", &[0, 0, 0, 3, 0, 0, 0, 0]),
+            fctl(0, 1, 10),
+            chunk(b"zTXt", &[&b"Comment\0\0"[..], &comment].concat()),
+        ],
+        &[
+            fctl(1, 25, 100),
+            chunk(b"fdAT", &[0, 0, 0, 2]),
+            // a denominator of 0 means hundredths of a second
+            fctl(3, 35, 0),
+            chunk(b"fdAT", &[0, 0, 0, 4]),
+        ],
+    );
+    let meta = mediameta::extract_metadata(Cursor::new(png), 0, ContainerType::Png)?;
+    assert_eq!(meta.frame_count, Some(3));
+    assert_eq!(meta.duration, Some(Duration::from_millis(700)));
+    assert_eq!(meta.png_text, [text("Comment", "three frames")]);
+    assert_eq!(meta.creation_date, None);
+    Ok(())
+}
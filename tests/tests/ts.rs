@@ -0,0 +1,156 @@
+use mediameta::{Codec, DateSource, DateTimeOffset, TrackDetails, TrackKind};
+use std::time::Duration;
+
+const VIDEO_PID: u16 = 0x1011;
+const AUDIO_PID: u16 = 0x1100;
+const PMT_PID: u16 = 0x0100;
+
+/// 1920x1080 interlaced H.264 High profile with 50 fields per second.
+const SPS: [u8; 22] = [
+    0x67, 0x64, 0x00, 0x28, 0xAC, 0xD9, 0x40, 0x78, 0x04, 0x4F, 0xDE, 0x02, 0x20, 0x00, 0x00, 0x03,
+    0x00, 0x20, 0x00, 0x00, 0x06, 0x58,
+];
+
+/// Splits a payload into transport stream packets, padding the last one with an adaptation
+/// field. Every packet is prefixed with a 4-byte timestamp like in AVCHD files.
+fn packets(pid: u16, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (index, chunk) in payload.chunks(184).enumerate() {
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        let unit_start = if index == 0 { 0x40 } else { 0 };
+        data.extend_from_slice(&[0x47, unit_start | (pid >> 8) as u8, pid as u8]);
+        if chunk.len() == 184 {
+            data.push(0x10 | (index & 0x0F) as u8);
+        } else {
+            let stuffing = 183 - chunk.len();
+            data.push(0x30 | (index & 0x0F) as u8);
+            data.push(stuffing as u8);
+            if stuffing > 0 {
+                data.push(0);
+                data.extend(std::iter::repeat(0xFF).take(stuffing - 1));
+            }
+        }
+        data.extend_from_slice(chunk);
+    }
+    data
+}
+
+/// Builds a table section with a pointer field and a dummy CRC.
+fn section(table_id: u8, id: u16, body: &[u8]) -> Vec<u8> {
+    let len = 5 + body.len() + 4;
+    let mut section = vec![0, table_id, 0xB0 | (len >> 8) as u8, len as u8];
+    section.extend_from_slice(&id.to_be_bytes());
+    section.extend_from_slice(&[0xC1, 0, 0]);
+    section.extend_from_slice(body);
+    section.extend_from_slice(&[0; 4]);
+    section
+}
+
+fn pes(stream_id: u8, pts: u64, data: &[u8]) -> Vec<u8> {
+    let mut pes = vec![0, 0, 1, stream_id, 0, 0, 0x80, 0x80, 5];
+    pes.extend_from_slice(&[
+        0x21 | ((pts >> 29) & 0x0E) as u8,
+        (pts >> 22) as u8,
+        ((pts >> 14) & 0xFE) as u8 | 1,
+        (pts >> 7) as u8,
+        ((pts << 1) & 0xFE) as u8 | 1,
+    ]);
+    pes.extend_from_slice(data);
+    pes
+}
+
+fn mdpm_sei(time_zone: u8) -> Vec<u8> {
+    let mut mdpm = vec![
+        0x17, 0xEE, 0x8C, 0x60, 0xF8, 0x4D, 0x11, 0xD9, 0x8C, 0xD6, 0x08, 0x00, 0x20, 0x0C, 0x9A,
+        0x66,
+    ];
+    mdpm.extend_from_slice(b"MDPM");
+    mdpm.extend_from_slice(&[
+        2, 0x18, time_zone, 0x20, 0x13, 0x11, 0x19, 0x09, 0x16, 0x07, 0x11,
+    ]);
+    [&[0x06, 5, mdpm.len() as u8][..], &mdpm, &[0x80]].concat()
+}
+
+/// Builds an AVCHD stream with a 10 second H.264 video stream and an AC-3 audio stream.
+fn make_m2ts() -> Vec<u8> {
+    let pat = section(0x00, 1, &[0, 1, 0xE0 | (PMT_PID >> 8) as u8, PMT_PID as u8]);
+    let pmt_body = [
+        &[0xE0 | (VIDEO_PID >> 8) as u8, VIDEO_PID as u8, 0xF0, 0][..],
+        &[
+            0x1B,
+            0xE0 | (VIDEO_PID >> 8) as u8,
+            VIDEO_PID as u8,
+            0xF0,
+            0,
+        ],
+        &[
+            0x81,
+            0xE0 | (AUDIO_PID >> 8) as u8,
+            AUDIO_PID as u8,
+            0xF0,
+            6,
+        ],
+        &[0x0A, 4, b'e', b'n', b'g', 0],
+    ]
+    .concat();
+    let pmt = section(0x02, 1, &pmt_body);
+
+    let start_code = [0, 0, 0, 1];
+    // UTC+1 without daylight saving time
+    let access_unit = [
+        &start_code[..],
+        &[0x09, 0xF0],
+        &start_code,
+        &SPS,
+        &start_code,
+        &mdpm_sei(0x02),
+        &start_code,
+        &[0x65, 0x88, 0x80, 0x40],
+    ]
+    .concat();
+    let last_frame = [&start_code[..], &[0x41, 0x9A, 0x02]].concat();
+    [
+        packets(0, &pat),
+        packets(PMT_PID, &pmt),
+        packets(VIDEO_PID, &pes(0xE0, 90_000, &access_unit)),
+        packets(AUDIO_PID, &pes(0xBD, 90_000, &[0x0B, 0x77, 0, 0])),
+        packets(VIDEO_PID, &pes(0xE0, 90_000 + 896_400, &last_frame)),
+    ]
+    .concat()
+}
+
+#[test]
+fn test_avchd() -> anyhow::Result<()> {
+    let dir = crate::TempDir::new()?;
+    let path = dir.path().join("00000.MTS");
+    std::fs::write(&path, make_m2ts())?;
+
+    let meta = mediameta::extract_file_metadata(&path)?;
+    assert_eq!((meta.width, meta.height), (1920, 1080));
+    assert_eq!(meta.duration, Some(Duration::from_secs(10)));
+    let offset = chrono::FixedOffset::east_opt(3600).unwrap();
+    assert_eq!(
+        meta.creation_datetime,
+        Some(crate::media_datetime(
+            "2013-11-09T16:07:11",
+            DateTimeOffset::Fixed(offset)
+        ))
+    );
+
+    assert_eq!(meta.tracks.len(), 2);
+    let video = &meta.tracks[0];
+    assert_eq!(video.codec.codec, Codec::H264);
+    let TrackDetails::Video(details) = &video.details else {
+        panic!("expected a video track");
+    };
+    let frame_rate = details.frame_rate.expect("frame rate");
+    assert_eq!((frame_rate.numerator, frame_rate.denominator), (25, 1));
+    let audio = &meta.tracks[1];
+    assert_eq!(audio.kind, TrackKind::Audio);
+    assert_eq!(audio.codec.codec, Codec::Ac3);
+    assert_eq!(audio.language.as_deref(), Some("eng"));
+
+    let date = mediameta::extract_file_creation_date(&path)?;
+    assert_eq!(date.source, DateSource::AvchdDateTimeOriginal);
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use crate::fixtures::ebml::{element, id_bytes, uint};
+use mediameta::{Codec, ContainerType, TrackDetails, TrackKind};
+use std::io::Cursor;
+use std::time::Duration;
+
+/// Size of an element that is still being written.
+const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+fn simple_block(track: u8, timestamp: i16, flags: u8, frame: &[u8]) -> Vec<u8> {
+    let header = [&[0x80 | track][..], &timestamp.to_be_bytes(), &[flags]].concat();
+    element(0xA3, &[&header[..], frame].concat())
+}
+
+/// Builds a WebM file like the screen recordings of browsers, which write the segment and the
+/// clusters with an unknown size and no duration. The transparent VP9 track lacks its pixel
+/// size, which is found in its first key frame.
+fn make_live_webm(timestamp_scale: u64) -> Vec<u8> {
+    let ebml_header = element(
+        0x1A45_DFA3,
+        &[
+            uint(0x4286, 1),
+            uint(0x42F7, 1),
+            uint(0x42F2, 4),
+            uint(0x42F3, 8),
+            element(0x4282, b"webm"),
+            uint(0x4287, 4),
+            uint(0x4285, 2),
+        ]
+        .concat(),
+    );
+    let info = element(
+        0x1549_A966,
+        &[
+            uint(0x2A_D7B1, timestamp_scale),
+            element(0x4D80, b"Chrome"),
+            element(0x5741, b"Chrome"),
+        ]
+        .concat(),
+    );
+    let video = element(
+        0xAE,
+        &[
+            uint(0xD7, 1),
+            uint(0x73C5, 1),
+            uint(0x83, 1),
+            element(0x86, b"V_VP9"),
+            uint(0x23_E383, 33_333_333),
+            element(0xE0, &uint(0x53C0, 1)),
+        ]
+        .concat(),
+    );
+    let audio = element(
+        0xAE,
+        &[
+            uint(0xD7, 2),
+            uint(0x73C5, 2),
+            uint(0x83, 2),
+            element(0x86, b"A_OPUS"),
+            element(
+                0xE1,
+                &[element(0xB5, &48000f64.to_be_bytes()), uint(0x9F, 2)].concat(),
+            ),
+        ]
+        .concat(),
+    );
+    let tracks = element(0x1654_AE6B, &[video, audio].concat());
+
+    // a 1280x720 VP9 key frame header
+    let key_frame = [0x82, 0x49, 0x83, 0x42, 0x40, 0x4F, 0xF0, 0x2C, 0xF8];
+    let first_cluster = [
+        uint(0xE7, 0),
+        simple_block(1, 0, 0x80, &key_frame),
+        simple_block(2, 0, 0x80, &[0xFC]),
+    ]
+    .concat();
+    let last_cluster = [
+        uint(0xE7, 5000),
+        simple_block(1, 0, 0, &[0x86]),
+        simple_block(1, 4967, 0, &[0x86]),
+        element(
+            0xA0,
+            &[
+                element(0xA1, &[0x82, 0x13, 0x74, 0x00, 0xFC]),
+                uint(0x9B, 20),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+
+    [
+        ebml_header,
+        id_bytes(0x1853_8067),
+        UNKNOWN_SIZE.to_vec(),
+        info,
+        tracks,
+        id_bytes(0x1F43_B675),
+        UNKNOWN_SIZE.to_vec(),
+        first_cluster,
+        id_bytes(0x1F43_B675),
+        UNKNOWN_SIZE.to_vec(),
+        last_cluster,
+    ]
+    .concat()
+}
+
+#[test]
+fn test_container_type() {
+    assert_eq!(
+        mediameta::get_container_type("recording.webm").unwrap(),
+        ContainerType::Mkv
+    );
+}
+
+#[test]
+fn test_live_recording() -> anyhow::Result<()> {
+    let meta = mediameta::extract_mkv_metadata(Cursor::new(make_live_webm(1_000_000)))?;
+    let doc_type = meta.doc_type.expect("doc type");
+    assert_eq!((doc_type.name.as_str(), doc_type.version), ("webm", 4));
+    assert_eq!((meta.width, meta.height), (1280, 720));
+    // the last audio block starts at 9980 ms and lasts 20 ms
+    assert_eq!(meta.duration, Some(Duration::from_secs(10)));
+
+    assert_eq!(meta.tracks.len(), 2);
+    let video = &meta.tracks[0];
+    assert_eq!(video.codec.codec, Codec::Vp9);
+    let TrackDetails::Video(details) = &video.details else {
+        panic!("expected a video track");
+    };
+    assert_eq!((details.width, details.height), (1280, 720));
+    assert!(details.alpha);
+    let audio = &meta.tracks[1];
+    assert_eq!(audio.kind, TrackKind::Audio);
+    assert_eq!(audio.codec.codec, Codec::Opus);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_timestamp_scale() -> anyhow::Result<()> {
+    // the default scale of 1 ms is used instead
+    let meta = mediameta::extract_mkv_metadata(Cursor::new(make_live_webm(0)))?;
+    assert_eq!(meta.duration, Some(Duration::from_secs(10)));
+    Ok(())
+}
+
+#[test]
+fn test_broken_chapters() -> anyhow::Result<()> {
+    // a `Chapters` element before the first cluster whose size exceeds the file
+    let mut webm = make_live_webm(1_000_000);
+    let cluster = id_bytes(0x1F43_B675);
+    let position = webm
+        .windows(cluster.len())
+        .position(|window| window == cluster)
+        .unwrap();
+    let chapters = [
+        id_bytes(0x1043_A770),
+        (1u64 << 56 | 1 << 30).to_be_bytes().to_vec(),
+    ]
+    .concat();
+    webm.splice(position..position, chapters);
+
+    let meta = mediameta::extract_mkv_metadata(Cursor::new(webm))?;
+    assert!(meta.chapters.is_empty());
+    assert_eq!(meta.tracks.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_detected_stream() -> anyhow::Result<()> {
+    let meta = mediameta::extract_stream_metadata(Cursor::new(make_live_webm(1_000_000)), None)?;
+    assert_eq!(
+        meta.doc_type.map(|doc_type| doc_type.name).as_deref(),
+        Some("webm")
+    );
+    assert_eq!(meta.duration, Some(Duration::from_secs(10)));
+    Ok(())
+}